@@ -0,0 +1,44 @@
+// Skinning and morph targets of the mesh vertex shaders, included after their attributes.
+
+#ifdef SKINNED
+layout (location = 3) in vec4 va_joints;
+layout (location = 4) in vec4 va_weights;
+
+// Must match MAX_JOINTS in the renderer.
+uniform mat4 u_joint_matrices[128];
+#endif
+
+// Must match MAX_MORPH_TARGETS in the renderer.
+uniform float u_morph_weights[64];
+uniform int u_morph_target_count;
+uniform int u_morph_vertex_count;
+// Position and normal deltas of each vertex, target after target.
+uniform samplerBuffer u_morph_deltas;
+
+// Model matrix of the vertex, moved by its joints when skinned.
+mat4 deformed_model(mat4 model)
+{
+#ifdef SKINNED
+    mat4 skin = va_weights.x * u_joint_matrices[int(va_joints.x)]
+        + va_weights.y * u_joint_matrices[int(va_joints.y)]
+        + va_weights.z * u_joint_matrices[int(va_joints.z)]
+        + va_weights.w * u_joint_matrices[int(va_joints.w)];
+    return model * skin;
+#else
+    return model;
+#endif
+}
+
+// Adds the weighted deltas of the morph targets to the vertex.
+void morph(inout vec3 position, inout vec3 normal)
+{
+    for (int i = 0; i < u_morph_target_count; i++) {
+        float weight = u_morph_weights[i];
+        if (weight == 0.0) {
+            continue;
+        }
+        int texel = (i * u_morph_vertex_count + gl_VertexID) * 2;
+        position += weight * texelFetch(u_morph_deltas, texel).xyz;
+        normal += weight * texelFetch(u_morph_deltas, texel + 1).xyz;
+    }
+}
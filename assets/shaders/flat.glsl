@@ -4,13 +4,18 @@
 layout (location = 0) in vec3 va_position;
 layout (location = 1) in vec3 va_normal;
 
+#include "deform.glsl"
+
 uniform mat4 u_model;
 uniform mat4 u_view;
 uniform mat4 u_projection;
 
 void main()
 {
-    vec4 vertex_position = u_model * vec4(va_position, 1.0);
+    vec3 position = va_position;
+    vec3 normal = va_normal;
+    morph(position, normal);
+    vec4 vertex_position = deformed_model(u_model) * vec4(position, 1.0);
     gl_Position = u_projection * u_view * vertex_position;
 }
 
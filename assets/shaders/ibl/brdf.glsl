@@ -0,0 +1,93 @@
+#vertex
+
+#version 330 core
+
+out vec2 texture_coordinates;
+
+void main()
+{
+    vec2 position = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2);
+    texture_coordinates = position;
+    gl_Position = vec4(position * 2.0 - 1.0, 0.0, 1.0);
+}
+
+#fragment
+
+#version 330 core
+
+in vec2 texture_coordinates;
+
+out vec2 fragment_color;
+
+const float PI = 3.14159265359;
+const uint SAMPLE_COUNT = 1024u;
+
+float radical_inverse_vdc(uint bits)
+{
+    bits = (bits << 16u) | (bits >> 16u);
+    bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+    bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+    bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+    bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+    return float(bits) * 2.3283064365386963e-10;
+}
+
+vec2 hammersley(uint i, uint count)
+{
+    return vec2(float(i) / float(count), radical_inverse_vdc(i));
+}
+
+vec3 importance_sample_ggx(vec2 xi, vec3 normal, float roughness)
+{
+    float a = roughness * roughness;
+    float phi = 2.0 * PI * xi.x;
+    float cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+    float sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+    vec3 halfway = vec3(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+
+    vec3 up = abs(normal.z) < 0.999 ? vec3(0.0, 0.0, 1.0) : vec3(1.0, 0.0, 0.0);
+    vec3 tangent = normalize(cross(up, normal));
+    vec3 bitangent = cross(normal, tangent);
+    return normalize(tangent * halfway.x + bitangent * halfway.y + normal * halfway.z);
+}
+
+float geometry_schlick_ggx(float n_dot_v, float roughness)
+{
+    // k is remapped for image based lighting.
+    float k = (roughness * roughness) / 2.0;
+    return n_dot_v / (n_dot_v * (1.0 - k) + k);
+}
+
+float geometry_smith(float n_dot_v, float n_dot_l, float roughness)
+{
+    return geometry_schlick_ggx(n_dot_v, roughness) * geometry_schlick_ggx(n_dot_l, roughness);
+}
+
+void main()
+{
+    float n_dot_v = texture_coordinates.x;
+    float roughness = texture_coordinates.y;
+
+    vec3 view = vec3(sqrt(1.0 - n_dot_v * n_dot_v), 0.0, n_dot_v);
+    vec3 normal = vec3(0.0, 0.0, 1.0);
+
+    float scale = 0.0;
+    float bias = 0.0;
+    for (uint i = 0u; i < SAMPLE_COUNT; i++) {
+        vec2 xi = hammersley(i, SAMPLE_COUNT);
+        vec3 halfway = importance_sample_ggx(xi, normal, roughness);
+        vec3 light = normalize(2.0 * dot(view, halfway) * halfway - view);
+
+        float n_dot_l = max(light.z, 0.0);
+        float n_dot_h = max(halfway.z, 0.0);
+        float v_dot_h = max(dot(view, halfway), 0.0);
+        if (n_dot_l > 0.0) {
+            float g = geometry_smith(n_dot_v, n_dot_l, roughness);
+            float g_visibility = (g * v_dot_h) / (n_dot_h * n_dot_v);
+            float fresnel = pow(1.0 - v_dot_h, 5.0);
+            scale += (1.0 - fresnel) * g_visibility;
+            bias += fresnel * g_visibility;
+        }
+    }
+    fragment_color = vec2(scale, bias) / float(SAMPLE_COUNT);
+}
@@ -0,0 +1,36 @@
+#vertex
+
+#version 330 core
+
+layout (location = 0) in vec3 va_position;
+
+uniform mat4 u_view;
+uniform mat4 u_projection;
+
+out vec3 local_position;
+
+void main()
+{
+    local_position = va_position;
+    gl_Position = u_projection * u_view * vec4(va_position, 1.0);
+}
+
+#fragment
+
+#version 330 core
+
+uniform sampler2D u_equirectangular_map;
+
+in vec3 local_position;
+
+out vec4 fragment_color;
+
+const vec2 INVERSE_ATAN = vec2(0.1591, 0.3183);
+
+void main()
+{
+    vec3 direction = normalize(local_position);
+    // The first row of the image is the top of the sky.
+    vec2 uv = vec2(atan(direction.z, direction.x), -asin(direction.y)) * INVERSE_ATAN + 0.5;
+    fragment_color = vec4(texture(u_equirectangular_map, uv).rgb, 1.0);
+}
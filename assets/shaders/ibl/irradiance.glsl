@@ -0,0 +1,49 @@
+#vertex
+
+#version 330 core
+
+layout (location = 0) in vec3 va_position;
+
+uniform mat4 u_view;
+uniform mat4 u_projection;
+
+out vec3 local_position;
+
+void main()
+{
+    local_position = va_position;
+    gl_Position = u_projection * u_view * vec4(va_position, 1.0);
+}
+
+#fragment
+
+#version 330 core
+
+uniform samplerCube u_environment_map;
+
+in vec3 local_position;
+
+out vec4 fragment_color;
+
+const float PI = 3.14159265359;
+const float SAMPLE_DELTA = 0.025;
+
+void main()
+{
+    vec3 normal = normalize(local_position);
+    vec3 up = abs(normal.y) < 0.999 ? vec3(0.0, 1.0, 0.0) : vec3(0.0, 0.0, 1.0);
+    vec3 right = normalize(cross(up, normal));
+    up = cross(normal, right);
+
+    vec3 irradiance = vec3(0.0);
+    float sample_count = 0.0;
+    for (float phi = 0.0; phi < 2.0 * PI; phi += SAMPLE_DELTA) {
+        for (float theta = 0.0; theta < 0.5 * PI; theta += SAMPLE_DELTA) {
+            vec3 tangent_sample = vec3(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+            vec3 sample_direction = tangent_sample.x * right + tangent_sample.y * up + tangent_sample.z * normal;
+            irradiance += texture(u_environment_map, sample_direction).rgb * cos(theta) * sin(theta);
+            sample_count++;
+        }
+    }
+    fragment_color = vec4(PI * irradiance / sample_count, 1.0);
+}
@@ -0,0 +1,37 @@
+#vertex
+
+#version 330 core
+
+layout (location = 0) in vec3 va_position;
+
+// View matrix without its translation, the sky is infinitely far away.
+uniform mat4 u_view;
+uniform mat4 u_projection;
+
+out vec3 local_position;
+
+void main()
+{
+    local_position = va_position;
+    vec4 position = u_projection * u_view * vec4(va_position, 1.0);
+    // Always on the far plane.
+    gl_Position = position.xyww;
+}
+
+#fragment
+
+#version 330 core
+
+uniform samplerCube u_environment_map;
+uniform float u_lod;
+uniform float u_intensity;
+
+in vec3 local_position;
+
+out vec4 fragment_color;
+
+void main()
+{
+    vec3 color = textureLod(u_environment_map, local_position, u_lod).rgb;
+    fragment_color = vec4(color * u_intensity, 1.0);
+}
@@ -0,0 +1,79 @@
+#vertex
+
+#version 330 core
+
+uniform mat4 u_view;
+uniform mat4 u_projection;
+
+out vec3 near_point;
+out vec3 far_point;
+
+vec3 unproject(vec2 position, float depth)
+{
+    vec4 world_position = inverse(u_projection * u_view) * vec4(position, depth, 1.0);
+    return world_position.xyz / world_position.w;
+}
+
+void main()
+{
+    vec2 position = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2) * 2.0 - 1.0;
+    near_point = unproject(position, -1.0);
+    far_point = unproject(position, 1.0);
+    gl_Position = vec4(position, 0.0, 1.0);
+}
+
+#fragment
+
+#version 330 core
+
+uniform mat4 u_view;
+uniform mat4 u_projection;
+uniform float u_cell_size;
+uniform float u_fade_distance;
+
+in vec3 near_point;
+in vec3 far_point;
+
+out vec4 fragment_color;
+
+// Anti-aliased lines every `spacing` units, returns the line coverage.
+float grid(vec2 coordinates, float spacing)
+{
+    vec2 scaled = coordinates / spacing;
+    vec2 derivative = fwidth(scaled);
+    vec2 lines = abs(fract(scaled - 0.5) - 0.5) / derivative;
+    return 1.0 - min(min(lines.x, lines.y), 1.0);
+}
+
+float axis(float coordinate)
+{
+    return 1.0 - min(abs(coordinate) / fwidth(coordinate), 1.0);
+}
+
+void main()
+{
+    // Intersection of the view ray with the y = 0 plane.
+    float t = -near_point.y / (far_point.y - near_point.y);
+    if (t <= 0.0)
+        discard;
+    vec3 position = near_point + t * (far_point - near_point);
+
+    vec4 clip_position = u_projection * u_view * vec4(position, 1.0);
+    gl_FragDepth = clip_position.z / clip_position.w * 0.5 + 0.5;
+
+    float minor = grid(position.xz, u_cell_size) * 0.3;
+    float major = grid(position.xz, u_cell_size * 10.0) * 0.6;
+    vec4 color = vec4(vec3(0.5), max(minor, major));
+
+    float x_axis = axis(position.z);
+    float z_axis = axis(position.x);
+    color = mix(color, vec4(0.9, 0.2, 0.2, 1.0), x_axis);
+    color = mix(color, vec4(0.2, 0.3, 0.9, 1.0), z_axis);
+
+    vec3 camera_position = inverse(u_view)[3].xyz;
+    float fade = 1.0 - smoothstep(0.0, u_fade_distance, distance(camera_position.xz, position.xz));
+    color.a *= fade;
+    if (color.a < 0.01)
+        discard;
+    fragment_color = color;
+}
@@ -0,0 +1,30 @@
+#vertex
+
+#version 330 core
+
+layout (location = 0) in vec3 va_position;
+layout (location = 1) in vec3 va_color;
+
+uniform mat4 u_view;
+uniform mat4 u_projection;
+
+out vec3 color;
+
+void main()
+{
+    color = va_color;
+    gl_Position = u_projection * u_view * vec4(va_position, 1.0);
+}
+
+#fragment
+
+#version 330 core
+
+in vec3 color;
+
+out vec4 fragment_color;
+
+void main()
+{
+    fragment_color = vec4(color, 1.0);
+}
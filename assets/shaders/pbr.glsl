@@ -0,0 +1,173 @@
+#vertex
+
+#version 330 core
+
+layout (location = 0) in vec3 va_position;
+layout (location = 1) in vec3 va_normal;
+layout (location = 2) in vec2 va_texture_coordinates;
+#include "deform.glsl"
+
+uniform mat4 u_model;
+uniform mat4 u_view;
+uniform mat4 u_projection;
+
+out VS_OUTPUT {
+    vec3 world_position;
+    vec3 world_normal;
+    vec2 texture_coordinates;
+} OUT;
+
+void main()
+{
+    mat4 model = deformed_model(u_model);
+    vec3 position = va_position;
+    vec3 normal = va_normal;
+    morph(position, normal);
+
+    vec4 world_position = model * vec4(position, 1.0);
+    gl_Position = u_projection * u_view * world_position;
+
+    OUT.world_position = world_position.xyz;
+    OUT.world_normal = mat3(transpose(inverse(model))) * normal;
+    OUT.texture_coordinates = va_texture_coordinates;
+}
+
+#fragment
+
+#version 330 core
+struct Material {
+    vec3 diffuse;
+    sampler2D diffuse_texture;
+    bool use_diffuse_texture;
+    float metallic;
+    float roughness;
+    float opacity;
+    float alpha_cutoff;
+    // 0: opaque, 1: masked, 2: blended
+    int blend_mode;
+};
+
+uniform Material material;
+
+uniform vec3 u_camera_position;
+
+uniform vec3 u_light_direction;
+uniform vec3 u_light_color;
+uniform float u_light_power;
+uniform float u_light_ambient_strength;
+
+uniform bool u_use_environment;
+uniform samplerCube u_irradiance_map;
+uniform samplerCube u_prefiltered_map;
+uniform sampler2D u_brdf_lut;
+uniform float u_prefiltered_max_lod;
+uniform float u_environment_intensity;
+
+// Writes the accumulation and weight targets of weighted blended transparency.
+uniform bool u_weighted_blended;
+
+in VS_OUTPUT {
+    vec3 world_position;
+    vec3 world_normal;
+    vec2 texture_coordinates;
+} IN;
+
+layout (location = 0) out vec4 fragment_color;
+// Only written by the weighted blended pass, its target has a second attachment.
+layout (location = 1) out vec4 fragment_weight;
+
+const float PI = 3.14159265359;
+
+float distribution_ggx(float n_dot_h, float roughness)
+{
+    float a = roughness * roughness;
+    float a2 = a * a;
+    float denominator = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * denominator * denominator);
+}
+
+float geometry_schlick_ggx(float n_dot_v, float roughness)
+{
+    float r = roughness + 1.0;
+    float k = (r * r) / 8.0;
+    return n_dot_v / (n_dot_v * (1.0 - k) + k);
+}
+
+float geometry_smith(float n_dot_v, float n_dot_l, float roughness)
+{
+    return geometry_schlick_ggx(n_dot_v, roughness) * geometry_schlick_ggx(n_dot_l, roughness);
+}
+
+vec3 fresnel_schlick(float cos_theta, vec3 f0)
+{
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+vec3 fresnel_schlick_roughness(float cos_theta, vec3 f0, float roughness)
+{
+    return f0 + (max(vec3(1.0 - roughness), f0) - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+void main()
+{
+    vec4 albedo;
+    if (material.use_diffuse_texture) {
+        albedo = texture(material.diffuse_texture, IN.texture_coordinates);
+    } else {
+        albedo = vec4(material.diffuse, 1.0);
+    }
+    float alpha = albedo.a * material.opacity;
+    if (material.blend_mode == 0) {
+        alpha = 1.0;
+    } else if (material.blend_mode == 1) {
+        if (alpha < material.alpha_cutoff) {
+            discard;
+        }
+        alpha = 1.0;
+    }
+    float metallic = material.metallic;
+    float roughness = max(material.roughness, 0.04);
+
+    vec3 normal = normalize(IN.world_normal);
+    vec3 view = normalize(u_camera_position - IN.world_position);
+    float n_dot_v = max(dot(normal, view), 0.0001);
+    vec3 f0 = mix(vec3(0.04), albedo.rgb, metallic);
+
+    // Directional light, u_light_direction points towards the light.
+    vec3 light = normalize(u_light_direction);
+    vec3 halfway = normalize(view + light);
+    float n_dot_l = max(dot(normal, light), 0.0);
+    float n_dot_h = max(dot(normal, halfway), 0.0);
+
+    vec3 fresnel = fresnel_schlick(max(dot(halfway, view), 0.0), f0);
+    vec3 specular = distribution_ggx(n_dot_h, roughness) * geometry_smith(n_dot_v, n_dot_l, roughness) * fresnel
+        / (4.0 * n_dot_v * max(n_dot_l, 0.0001));
+    vec3 diffuse = (1.0 - fresnel) * (1.0 - metallic) * albedo.rgb / PI;
+    vec3 radiance = u_light_color * u_light_power * PI;
+    vec3 color = (diffuse + specular) * radiance * n_dot_l;
+
+    if (u_use_environment) {
+        vec3 ambient_fresnel = fresnel_schlick_roughness(n_dot_v, f0, roughness);
+        vec3 ambient_diffuse = (1.0 - ambient_fresnel) * (1.0 - metallic)
+            * texture(u_irradiance_map, normal).rgb * albedo.rgb;
+
+        vec3 reflection = reflect(-view, normal);
+        vec3 prefiltered = textureLod(u_prefiltered_map, reflection, roughness * u_prefiltered_max_lod).rgb;
+        vec2 brdf = texture(u_brdf_lut, vec2(n_dot_v, roughness)).rg;
+        vec3 ambient_specular = prefiltered * (ambient_fresnel * brdf.x + brdf.y);
+
+        color += (ambient_diffuse + ambient_specular) * u_environment_intensity;
+    } else {
+        color += u_light_ambient_strength * u_light_color * albedo.rgb;
+    }
+
+    if (u_weighted_blended) {
+        // Weight from McGuire and Bavoil 2013, favouring the surfaces close to the camera.
+        float weight = clamp(alpha * max(0.01, 3000.0 * pow(1.0 - gl_FragCoord.z, 3.0)), 0.01, 3000.0);
+        // The alpha of the first target accumulates the revealage, the product of 1 - alpha.
+        fragment_color = vec4(color * alpha * weight, alpha);
+        fragment_weight = vec4(alpha * weight);
+    } else {
+        fragment_color = vec4(color, alpha);
+    }
+}
@@ -0,0 +1,31 @@
+#vertex
+
+#version 330 core
+
+out vec2 texture_coordinates;
+
+void main()
+{
+    vec2 position = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2);
+    texture_coordinates = position;
+    gl_Position = vec4(position * 2.0 - 1.0, 0.0, 1.0);
+}
+
+#fragment
+
+#version 330 core
+
+uniform sampler2D u_image;
+uniform float u_threshold;
+
+in vec2 texture_coordinates;
+
+out vec4 fragment_color;
+
+void main()
+{
+    vec3 color = texture(u_image, texture_coordinates).rgb;
+    float brightness = max(color.r, max(color.g, color.b));
+    float contribution = max(brightness - u_threshold, 0.0) / max(brightness, 0.0001);
+    fragment_color = vec4(color * contribution, 1.0);
+}
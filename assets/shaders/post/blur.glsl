@@ -0,0 +1,36 @@
+#vertex
+
+#version 330 core
+
+out vec2 texture_coordinates;
+
+void main()
+{
+    vec2 position = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2);
+    texture_coordinates = position;
+    gl_Position = vec4(position * 2.0 - 1.0, 0.0, 1.0);
+}
+
+#fragment
+
+#version 330 core
+
+uniform sampler2D u_image;
+// Offset between two samples, in texture coordinates.
+uniform vec2 u_direction;
+
+in vec2 texture_coordinates;
+
+out vec4 fragment_color;
+
+const float weights[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+void main()
+{
+    vec3 color = texture(u_image, texture_coordinates).rgb * weights[0];
+    for (int i = 1; i < 5; i++) {
+        color += texture(u_image, texture_coordinates + u_direction * i).rgb * weights[i];
+        color += texture(u_image, texture_coordinates - u_direction * i).rgb * weights[i];
+    }
+    fragment_color = vec4(color, 1.0);
+}
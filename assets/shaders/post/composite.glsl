@@ -0,0 +1,103 @@
+#vertex
+
+#version 330 core
+
+out vec2 texture_coordinates;
+
+void main()
+{
+    vec2 position = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2);
+    texture_coordinates = position;
+    gl_Position = vec4(position * 2.0 - 1.0, 0.0, 1.0);
+}
+
+#fragment
+
+#version 330 core
+
+uniform sampler2D u_image;
+uniform sampler2D u_bloom;
+uniform float u_bloom_intensity;
+
+uniform float u_exposure;
+// 0: none, 1: Reinhard, 2: ACES, 3: Filmic
+uniform int u_tonemapping;
+
+uniform float u_vignette_intensity;
+uniform float u_vignette_smoothness;
+
+uniform bool u_srgb_output;
+
+in vec2 texture_coordinates;
+
+out vec4 fragment_color;
+
+vec3 tonemap_reinhard(vec3 color)
+{
+    return color / (color + 1.0);
+}
+
+// Krzysztof Narkowicz's fit of the ACES filmic curve.
+vec3 tonemap_aces(vec3 color)
+{
+    const float a = 2.51;
+    const float b = 0.03;
+    const float c = 2.43;
+    const float d = 0.59;
+    const float e = 0.14;
+    return clamp((color * (a * color + b)) / (color * (c * color + d) + e), 0.0, 1.0);
+}
+
+// John Hable's Uncharted 2 curve.
+vec3 hable(vec3 x)
+{
+    const float a = 0.15;
+    const float b = 0.50;
+    const float c = 0.10;
+    const float d = 0.20;
+    const float e = 0.02;
+    const float f = 0.30;
+    return ((x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f)) - e / f;
+}
+
+vec3 tonemap_filmic(vec3 color)
+{
+    const float white_point = 11.2;
+    const float exposure_bias = 2.0;
+    return hable(color * exposure_bias) / hable(vec3(white_point));
+}
+
+vec3 linear_to_srgb(vec3 color)
+{
+    vec3 low = color * 12.92;
+    vec3 high = 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055;
+    return mix(high, low, vec3(lessThanEqual(color, vec3(0.0031308))));
+}
+
+void main()
+{
+    vec4 image = texture(u_image, texture_coordinates);
+    vec3 color = image.rgb;
+    if (u_bloom_intensity > 0.0) {
+        color += texture(u_bloom, texture_coordinates).rgb * u_bloom_intensity;
+    }
+
+    color *= u_exposure;
+    if (u_tonemapping == 1) {
+        color = tonemap_reinhard(color);
+    } else if (u_tonemapping == 2) {
+        color = tonemap_aces(color);
+    } else if (u_tonemapping == 3) {
+        color = tonemap_filmic(color);
+    }
+
+    vec2 from_center = texture_coordinates - 0.5;
+    float vignette = smoothstep(0.8, 0.8 - u_vignette_smoothness, length(from_center) * 1.414);
+    color *= mix(1.0, vignette, u_vignette_intensity);
+
+    color = clamp(color, 0.0, 1.0);
+    if (u_srgb_output) {
+        color = linear_to_srgb(color);
+    }
+    fragment_color = vec4(color, image.a);
+}
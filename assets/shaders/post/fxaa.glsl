@@ -0,0 +1,76 @@
+#vertex
+
+#version 330 core
+
+out vec2 texture_coordinates;
+
+void main()
+{
+    vec2 position = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2);
+    texture_coordinates = position;
+    gl_Position = vec4(position * 2.0 - 1.0, 0.0, 1.0);
+}
+
+#fragment
+
+#version 330 core
+
+// Simplified FXAA from Timothy Lottes, working on the gamma-corrected image.
+uniform sampler2D u_image;
+uniform vec2 u_texel_size;
+
+in vec2 texture_coordinates;
+
+out vec4 fragment_color;
+
+const float EDGE_THRESHOLD_MIN = 0.0312;
+const float EDGE_THRESHOLD_MAX = 0.125;
+const float SPAN_MAX = 8.0;
+const float REDUCE_MUL = 1.0 / 8.0;
+const float REDUCE_MIN = 1.0 / 128.0;
+
+float luma(vec3 color)
+{
+    return dot(color, vec3(0.299, 0.587, 0.114));
+}
+
+void main()
+{
+    vec4 center = texture(u_image, texture_coordinates);
+    float luma_center = luma(center.rgb);
+    float luma_nw = luma(texture(u_image, texture_coordinates + vec2(-1.0, -1.0) * u_texel_size).rgb);
+    float luma_ne = luma(texture(u_image, texture_coordinates + vec2(1.0, -1.0) * u_texel_size).rgb);
+    float luma_sw = luma(texture(u_image, texture_coordinates + vec2(-1.0, 1.0) * u_texel_size).rgb);
+    float luma_se = luma(texture(u_image, texture_coordinates + vec2(1.0, 1.0) * u_texel_size).rgb);
+
+    float luma_min = min(luma_center, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    float luma_max = max(luma_center, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+    if (luma_max - luma_min < max(EDGE_THRESHOLD_MIN, luma_max * EDGE_THRESHOLD_MAX)) {
+        fragment_color = center;
+        return;
+    }
+
+    vec2 direction = vec2(
+        -((luma_nw + luma_ne) - (luma_sw + luma_se)),
+        ((luma_nw + luma_sw) - (luma_ne + luma_se))
+    );
+    float direction_reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    float inverse_direction_min = 1.0 / (min(abs(direction.x), abs(direction.y)) + direction_reduce);
+    direction = clamp(direction * inverse_direction_min, vec2(-SPAN_MAX), vec2(SPAN_MAX)) * u_texel_size;
+
+    vec3 color_a = 0.5 * (
+        texture(u_image, texture_coordinates + direction * (1.0 / 3.0 - 0.5)).rgb +
+        texture(u_image, texture_coordinates + direction * (2.0 / 3.0 - 0.5)).rgb
+    );
+    vec3 color_b = color_a * 0.5 + 0.25 * (
+        texture(u_image, texture_coordinates + direction * -0.5).rgb +
+        texture(u_image, texture_coordinates + direction * 0.5).rgb
+    );
+
+    float luma_b = luma(color_b);
+    if (luma_b < luma_min || luma_b > luma_max) {
+        fragment_color = vec4(color_a, center.a);
+    } else {
+        fragment_color = vec4(color_b, center.a);
+    }
+}
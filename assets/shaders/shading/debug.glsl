@@ -0,0 +1,115 @@
+#vertex
+
+#version 330 core
+
+layout (location = 0) in vec3 va_position;
+layout (location = 1) in vec3 va_normal;
+layout (location = 2) in vec2 va_texture_coordinates;
+
+#include "../deform.glsl"
+
+uniform mat4 u_model;
+uniform mat4 u_view;
+uniform mat4 u_projection;
+
+out VS_OUTPUT {
+    vec3 world_position;
+    vec3 world_normal;
+    vec2 texture_coordinates;
+} OUT;
+
+void main()
+{
+    mat4 model = deformed_model(u_model);
+    vec3 position = va_position;
+    vec3 normal = va_normal;
+    morph(position, normal);
+
+    vec4 world_position = model * vec4(position, 1.0);
+    gl_Position = u_projection * u_view * world_position;
+
+    OUT.world_position = world_position.xyz;
+    OUT.world_normal = mat3(transpose(inverse(model))) * normal;
+    OUT.texture_coordinates = va_texture_coordinates;
+}
+
+#fragment
+
+#version 330 core
+struct Material {
+    vec3 diffuse;
+    sampler2D diffuse_texture;
+    bool use_diffuse_texture;
+    float metallic;
+    float roughness;
+};
+
+// Must match ShadingMode::debug_index.
+const int MODE_FLAT = 0;
+const int MODE_WORLD_NORMALS = 1;
+const int MODE_UV_CHECKER = 2;
+const int MODE_MATCAP = 3;
+const int MODE_UNLIT_TEXTURE = 4;
+const int MODE_LOD_LEVELS = 5;
+
+const vec3 LOD_COLORS[5] = vec3[](
+    vec3(0.9, 0.9, 0.9),
+    vec3(0.3, 0.8, 0.3),
+    vec3(0.3, 0.5, 0.9),
+    vec3(0.9, 0.8, 0.2),
+    vec3(0.9, 0.3, 0.3)
+);
+
+uniform Material material;
+uniform mat4 u_view;
+
+uniform int u_mode;
+uniform sampler2D u_uv_checker;
+uniform float u_uv_checker_scale;
+uniform sampler2D u_matcap;
+uniform int u_lod_level;
+
+uniform vec3 u_light_direction;
+uniform vec3 u_light_color;
+uniform float u_light_power;
+uniform float u_light_ambient_strength;
+
+in VS_OUTPUT {
+    vec3 world_position;
+    vec3 world_normal;
+    vec2 texture_coordinates;
+} IN;
+
+out vec4 fragment_color;
+
+vec3 base_color()
+{
+    if (material.use_diffuse_texture)
+        return texture(material.diffuse_texture, IN.texture_coordinates).rgb;
+    return material.diffuse;
+}
+
+void main()
+{
+    vec3 color;
+    if (u_mode == MODE_FLAT) {
+        // Face normal from the screen space derivatives of the position.
+        vec3 normal = normalize(cross(dFdx(IN.world_position), dFdy(IN.world_position)));
+        float diffuse = max(dot(normal, normalize(u_light_direction)), 0.0);
+        vec3 light = u_light_color * (diffuse * u_light_power * 0.5 + u_light_ambient_strength);
+        color = base_color() * light;
+    } else if (u_mode == MODE_WORLD_NORMALS) {
+        color = normalize(IN.world_normal) * 0.5 + 0.5;
+    } else if (u_mode == MODE_UV_CHECKER) {
+        color = texture(u_uv_checker, fract(IN.texture_coordinates * u_uv_checker_scale)).rgb;
+    } else if (u_mode == MODE_MATCAP) {
+        vec3 view_normal = normalize(mat3(u_view) * normalize(IN.world_normal));
+        color = texture(u_matcap, view_normal.xy * 0.49 + 0.5).rgb;
+    } else if (u_mode == MODE_LOD_LEVELS) {
+        float diffuse = max(dot(normalize(IN.world_normal), normalize(u_light_direction)), 0.0);
+        color = LOD_COLORS[min(u_lod_level, 4)] * (diffuse * 0.6 + 0.4);
+    } else {
+        color = base_color();
+    }
+    fragment_color = vec4(color, 1.0);
+}
@@ -0,0 +1,66 @@
+#vertex
+
+#version 330 core
+
+layout (location = 0) in vec3 va_position;
+layout (location = 1) in vec3 va_normal;
+
+#include "../deform.glsl"
+
+uniform mat4 u_model;
+
+out VS_OUTPUT {
+    vec3 world_normal;
+} OUT;
+
+void main()
+{
+    mat4 model = deformed_model(u_model);
+    vec3 position = va_position;
+    vec3 normal = va_normal;
+    morph(position, normal);
+
+    gl_Position = model * vec4(position, 1.0);
+    OUT.world_normal = normalize(mat3(transpose(inverse(model))) * normal);
+}
+
+#geometry
+
+#version 330 core
+
+layout (triangles) in;
+layout (line_strip, max_vertices = 6) out;
+
+uniform mat4 u_view;
+uniform mat4 u_projection;
+uniform float u_normal_length;
+
+in VS_OUTPUT {
+    vec3 world_normal;
+} IN[];
+
+void main()
+{
+    mat4 view_projection = u_projection * u_view;
+    for (int i = 0; i < 3; i++) {
+        vec4 position = gl_in[i].gl_Position;
+        gl_Position = view_projection * position;
+        EmitVertex();
+        gl_Position = view_projection * (position + vec4(IN[i].world_normal * u_normal_length, 0.0));
+        EmitVertex();
+        EndPrimitive();
+    }
+}
+
+#fragment
+
+#version 330 core
+
+uniform vec3 u_color;
+
+out vec4 fragment_color;
+
+void main()
+{
+    fragment_color = vec4(u_color, 1.0);
+}
@@ -0,0 +1,35 @@
+#vertex
+
+#version 330 core
+
+out vec2 texture_coordinates;
+
+void main()
+{
+    vec2 position = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2);
+    texture_coordinates = position;
+    gl_Position = vec4(position * 2.0 - 1.0, 0.0, 1.0);
+}
+
+#fragment
+
+#version 330 core
+
+// Weighted colors, with the revealage in alpha.
+uniform sampler2D u_accumulation;
+uniform sampler2D u_weight;
+
+in vec2 texture_coordinates;
+
+out vec4 fragment_color;
+
+void main()
+{
+    vec4 accumulation = texture(u_accumulation, texture_coordinates);
+    float revealage = accumulation.a;
+    if (revealage >= 1.0) {
+        discard;
+    }
+    float weight = max(texture(u_weight, texture_coordinates).r, 0.00001);
+    fragment_color = vec4(accumulation.rgb / weight, 1.0 - revealage);
+}
@@ -0,0 +1,250 @@
+use cgmath::prelude::*;
+use cgmath::{Matrix4, Point3, Vector3};
+
+/// Axis aligned bounding box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vector3<f32>,
+    pub max: Vector3<f32>,
+}
+
+impl Aabb {
+    pub fn new(min: Vector3<f32>, max: Vector3<f32>) -> Self {
+        Aabb { min, max }
+    }
+    /// Box containing nothing, any union with it gives the other box.
+    pub fn empty() -> Self {
+        Aabb {
+            min: Vector3::new(f32::MAX, f32::MAX, f32::MAX),
+            max: Vector3::new(f32::MIN, f32::MIN, f32::MIN),
+        }
+    }
+    pub fn from_points<I: IntoIterator<Item = Vector3<f32>>>(points: I) -> Self {
+        points
+            .into_iter()
+            .fold(Aabb::empty(), |aabb, point| aabb.extended(point))
+    }
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+    pub fn extended(&self, point: Vector3<f32>) -> Self {
+        Aabb {
+            min: Vector3::new(
+                self.min.x.min(point.x),
+                self.min.y.min(point.y),
+                self.min.z.min(point.z),
+            ),
+            max: Vector3::new(
+                self.max.x.max(point.x),
+                self.max.y.max(point.y),
+                self.max.z.max(point.z),
+            ),
+        }
+    }
+    pub fn union(&self, other: &Aabb) -> Self {
+        if other.is_empty() {
+            return *self;
+        }
+        self.extended(other.min).extended(other.max)
+    }
+    pub fn center(&self) -> Vector3<f32> {
+        (self.min + self.max) * 0.5
+    }
+    pub fn size(&self) -> Vector3<f32> {
+        self.max - self.min
+    }
+    pub fn corners(&self) -> [Vector3<f32>; 8] {
+        let (min, max) = (self.min, self.max);
+        [
+            Vector3::new(min.x, min.y, min.z),
+            Vector3::new(max.x, min.y, min.z),
+            Vector3::new(min.x, max.y, min.z),
+            Vector3::new(max.x, max.y, min.z),
+            Vector3::new(min.x, min.y, max.z),
+            Vector3::new(max.x, min.y, max.z),
+            Vector3::new(min.x, max.y, max.z),
+            Vector3::new(max.x, max.y, max.z),
+        ]
+    }
+    /// Box containing this one once transformed by `matrix`.
+    pub fn transformed(&self, matrix: &Matrix4<f32>) -> Self {
+        if self.is_empty() {
+            return *self;
+        }
+        Aabb::from_points(
+            self.corners()
+                .iter()
+                .map(|corner| matrix.transform_point(Point3::from_vec(*corner)).to_vec()),
+        )
+    }
+    /// Distance along the ray to the box entry, 0 when the origin is inside.
+    pub fn intersect_ray(&self, origin: Vector3<f32>, direction: Vector3<f32>) -> Option<f32> {
+        let mut near = 0.0f32;
+        let mut far = f32::MAX;
+        for axis in 0..3 {
+            let inverse = 1.0 / direction[axis];
+            let mut t0 = (self.min[axis] - origin[axis]) * inverse;
+            let mut t1 = (self.max[axis] - origin[axis]) * inverse;
+            if t0 > t1 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            near = near.max(t0);
+            far = far.min(t1);
+            if near > far {
+                return None;
+            }
+        }
+        Some(near)
+    }
+}
+
+/// Sphere containing a set of points, cheaper to test than a box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Vector3<f32>,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    pub fn new(center: Vector3<f32>, radius: f32) -> Self {
+        BoundingSphere { center, radius }
+    }
+    /// Sphere containing nothing, see `is_empty`.
+    pub fn empty() -> Self {
+        BoundingSphere {
+            center: Vector3::zero(),
+            radius: -1.0,
+        }
+    }
+    /// Sphere centered on the points' bounding box, not the smallest one but close to it.
+    pub fn from_points<I>(points: I) -> Self
+    where
+        I: IntoIterator<Item = Vector3<f32>>,
+        I::IntoIter: Clone,
+    {
+        let points = points.into_iter();
+        let bounds = Aabb::from_points(points.clone());
+        if bounds.is_empty() {
+            return BoundingSphere::empty();
+        }
+        let center = bounds.center();
+        let radius = points
+            .map(|point| (point - center).magnitude2())
+            .fold(0.0, f32::max)
+            .sqrt();
+        BoundingSphere { center, radius }
+    }
+    /// A negative radius contains nothing.
+    pub fn is_empty(&self) -> bool {
+        self.radius < 0.0
+    }
+    pub fn union(&self, other: &BoundingSphere) -> Self {
+        if other.is_empty() {
+            return *self;
+        }
+        if self.is_empty() {
+            return *other;
+        }
+        let offset = other.center - self.center;
+        let distance = offset.magnitude();
+        if distance + other.radius <= self.radius {
+            return *self;
+        }
+        if distance + self.radius <= other.radius {
+            return *other;
+        }
+        let radius = (distance + self.radius + other.radius) * 0.5;
+        let center = self.center + offset * ((radius - self.radius) / distance);
+        BoundingSphere { center, radius }
+    }
+    /// Sphere containing this one once transformed by `matrix`, scaled by its largest axis.
+    pub fn transformed(&self, matrix: &Matrix4<f32>) -> Self {
+        if self.is_empty() {
+            return *self;
+        }
+        let scale = matrix
+            .x
+            .truncate()
+            .magnitude()
+            .max(matrix.y.truncate().magnitude())
+            .max(matrix.z.truncate().magnitude());
+        BoundingSphere {
+            center: matrix
+                .transform_point(Point3::from_vec(self.center))
+                .to_vec(),
+            radius: self.radius * scale,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{Deg, Matrix3};
+
+    fn unit_box() -> Aabb {
+        Aabb::new(Vector3::new(-1.0, -1.0, -1.0), Vector3::new(1.0, 1.0, 1.0))
+    }
+
+    fn assert_close(a: Vector3<f32>, b: Vector3<f32>) {
+        assert!((a - b).magnitude() < 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn transformed_box() {
+        let matrix = Matrix4::from_translation(Vector3::new(5.0, 0.0, 0.0))
+            * Matrix4::from_nonuniform_scale(2.0, 1.0, 1.0);
+        let moved = unit_box().transformed(&matrix);
+        assert_close(moved.min, Vector3::new(3.0, -1.0, -1.0));
+        assert_close(moved.max, Vector3::new(7.0, 1.0, 1.0));
+
+        // A rotated box grows to contain the rotated corners.
+        let rotated = unit_box().transformed(&Matrix4::from(Matrix3::from_angle_z(Deg(45.0))));
+        let half = 2.0f32.sqrt();
+        assert_close(rotated.min, Vector3::new(-half, -half, -1.0));
+        assert_close(rotated.max, Vector3::new(half, half, 1.0));
+
+        assert!(Aabb::empty().transformed(&matrix).is_empty());
+    }
+
+    #[test]
+    fn ray_hits() {
+        let aabb = unit_box();
+        let x = Vector3::unit_x();
+        assert_eq!(
+            aabb.intersect_ray(Vector3::new(-5.0, 0.0, 0.0), x),
+            Some(4.0)
+        );
+        // Inside, the entry is the origin.
+        assert_eq!(aabb.intersect_ray(Vector3::zero(), x), Some(0.0));
+        // Pointing away and passing beside.
+        assert_eq!(aabb.intersect_ray(Vector3::new(5.0, 0.0, 0.0), x), None);
+        assert_eq!(aabb.intersect_ray(Vector3::new(-5.0, 2.0, 0.0), x), None);
+        // Diagonal through the corner region.
+        let direction = Vector3::new(1.0, 1.0, 0.0).normalize();
+        let distance = aabb
+            .intersect_ray(Vector3::new(-3.0, -3.0, 0.0), direction)
+            .unwrap();
+        assert!((distance - 2.0 * 2.0f32.sqrt()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn sphere_union() {
+        let a = BoundingSphere::new(Vector3::zero(), 1.0);
+        let b = BoundingSphere::new(Vector3::new(4.0, 0.0, 0.0), 1.0);
+        let union = a.union(&b);
+        assert_close(union.center, Vector3::new(2.0, 0.0, 0.0));
+        assert!((union.radius - 3.0).abs() < 1e-5);
+        assert_eq!(union, b.union(&a));
+
+        // A contained sphere leaves the other one unchanged.
+        let inner = BoundingSphere::new(Vector3::new(0.5, 0.0, 0.0), 0.25);
+        assert_eq!(a.union(&inner), a);
+        assert_eq!(inner.union(&a), a);
+
+        let empty = BoundingSphere::empty();
+        assert_eq!(a.union(&empty), a);
+        assert_eq!(empty.union(&a), a);
+        assert!(empty.union(&empty).is_empty());
+    }
+}
@@ -0,0 +1,478 @@
+use crate::{draw_fullscreen_triangle, ShaderProgram, SubMesh, TCamera, Vertex, VertexArray};
+use cgmath::prelude::*;
+use cgmath::{Deg, Matrix4, PerspectiveFov, Point3, Rad, Vector2, Vector3, Vector4};
+use image::codecs::hdr::HdrDecoder;
+use image::ImageError;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::os::raw::c_void;
+use std::path::Path;
+
+const ENVIRONMENT_SIZE: i32 = 512;
+const IRRADIANCE_SIZE: i32 = 32;
+const PREFILTERED_SIZE: i32 = 128;
+const PREFILTERED_MIP_LEVELS: i32 = 5;
+const BRDF_LUT_SIZE: i32 = 512;
+
+#[derive(Debug)]
+pub enum EnvironmentError {
+    Io(io::Error),
+    Image(ImageError),
+}
+
+impl From<io::Error> for EnvironmentError {
+    fn from(other: io::Error) -> Self {
+        EnvironmentError::Io(other)
+    }
+}
+
+impl From<ImageError> for EnvironmentError {
+    fn from(other: ImageError) -> Self {
+        EnvironmentError::Image(other)
+    }
+}
+
+impl std::fmt::Display for EnvironmentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            EnvironmentError::Io(err) => err.fmt(f),
+            EnvironmentError::Image(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for EnvironmentError {}
+
+/// Decoded equirectangular HDR image, in linear RGB.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HdrImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<f32>,
+}
+
+impl HdrImage {
+    /// Reads a Radiance `.hdr` file, this does not need a GL context.
+    pub fn open(path: &Path) -> Result<Self, EnvironmentError> {
+        let decoder = HdrDecoder::new(BufReader::new(File::open(path)?))?;
+        let metadata = decoder.metadata();
+        let pixels = decoder
+            .read_image_hdr()?
+            .iter()
+            .flat_map(|pixel| pixel.0.to_vec())
+            .collect();
+        Ok(HdrImage {
+            width: metadata.width,
+            height: metadata.height,
+            pixels,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Cubemap {
+    id: u32,
+    size: i32,
+}
+
+impl Cubemap {
+    fn new(size: i32, mipmaps: bool) -> Self {
+        let mut cubemap = Cubemap { id: 0, size };
+        unsafe {
+            gl::GenTextures(1, &mut cubemap.id);
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, cubemap.id);
+            for face in 0..6 {
+                gl::TexImage2D(
+                    gl::TEXTURE_CUBE_MAP_POSITIVE_X + face,
+                    0,
+                    gl::RGB16F as i32,
+                    size,
+                    size,
+                    0,
+                    gl::RGB,
+                    gl::FLOAT,
+                    std::ptr::null(),
+                );
+            }
+            let min_filter = if mipmaps {
+                gl::LINEAR_MIPMAP_LINEAR
+            } else {
+                gl::LINEAR
+            };
+            gl::TexParameteri(
+                gl::TEXTURE_CUBE_MAP,
+                gl::TEXTURE_MIN_FILTER,
+                min_filter as i32,
+            );
+            gl::TexParameteri(
+                gl::TEXTURE_CUBE_MAP,
+                gl::TEXTURE_MAG_FILTER,
+                gl::LINEAR as i32,
+            );
+            gl::TexParameteri(
+                gl::TEXTURE_CUBE_MAP,
+                gl::TEXTURE_WRAP_S,
+                gl::CLAMP_TO_EDGE as i32,
+            );
+            gl::TexParameteri(
+                gl::TEXTURE_CUBE_MAP,
+                gl::TEXTURE_WRAP_T,
+                gl::CLAMP_TO_EDGE as i32,
+            );
+            gl::TexParameteri(
+                gl::TEXTURE_CUBE_MAP,
+                gl::TEXTURE_WRAP_R,
+                gl::CLAMP_TO_EDGE as i32,
+            );
+            if mipmaps {
+                gl::GenerateMipmap(gl::TEXTURE_CUBE_MAP);
+            }
+        }
+        cubemap
+    }
+    pub fn bind(&self, texture_unit: u32) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + texture_unit);
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, self.id);
+        }
+    }
+    pub fn size(&self) -> u32 {
+        self.size as u32
+    }
+}
+
+impl Drop for Cubemap {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.id);
+        }
+    }
+}
+
+/// Image based lighting maps baked from an HDR environment.
+#[derive(Debug, PartialEq)]
+pub struct Environment {
+    pub intensity: f32,
+    pub show_background: bool,
+    /// Blurs the background by sampling a rougher level of the prefiltered map, in [0, 1].
+    pub background_blur: f32,
+    environment_map: Cubemap,
+    irradiance_map: Cubemap,
+    prefiltered_map: Cubemap,
+    brdf_lut: u32,
+}
+
+impl Environment {
+    /// Binds the lighting maps and sets the matching uniforms of a PBR shader.
+    pub fn send_to_shader(&self, shader: &mut ShaderProgram) {
+        self.irradiance_map.bind(1);
+        self.prefiltered_map.bind(2);
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE3);
+            gl::BindTexture(gl::TEXTURE_2D, self.brdf_lut);
+        }
+        shader.set_uniform_bool(String::from("u_use_environment"), true);
+        shader.set_uniform_int(String::from("u_irradiance_map"), 1);
+        shader.set_uniform_int(String::from("u_prefiltered_map"), 2);
+        shader.set_uniform_int(String::from("u_brdf_lut"), 3);
+        shader.set_uniform_float(
+            String::from("u_prefiltered_max_lod"),
+            (PREFILTERED_MIP_LEVELS - 1) as f32,
+        );
+        shader.set_uniform_float(String::from("u_environment_intensity"), self.intensity);
+    }
+}
+
+impl Drop for Environment {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.brdf_lut);
+        }
+    }
+}
+
+pub struct EnvironmentShaders {
+    pub equirectangular_to_cubemap: ShaderProgram,
+    pub irradiance: ShaderProgram,
+    pub prefilter: ShaderProgram,
+    pub brdf: ShaderProgram,
+}
+
+/// Converts equirectangular HDR images to the maps used for image based lighting.
+pub struct EnvironmentBaker {
+    shaders: EnvironmentShaders,
+    cube: SubMesh,
+    fullscreen_vertex_array: VertexArray,
+}
+
+impl EnvironmentBaker {
+    pub fn new(shaders: EnvironmentShaders) -> Self {
+        EnvironmentBaker {
+            shaders,
+            cube: unit_cube(),
+            fullscreen_vertex_array: VertexArray::new(),
+        }
+    }
+    /// Bakes the environment, the viewport and framebuffer bindings are not restored.
+    pub fn bake(&mut self, image: &HdrImage) -> Environment {
+        let mut capture_framebuffer = 0;
+        let mut capture_renderbuffer = 0;
+        let mut equirectangular_texture = 0;
+        unsafe {
+            gl::Enable(gl::TEXTURE_CUBE_MAP_SEAMLESS);
+            gl::Disable(gl::CULL_FACE);
+
+            gl::GenFramebuffers(1, &mut capture_framebuffer);
+            gl::GenRenderbuffers(1, &mut capture_renderbuffer);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, capture_framebuffer);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, capture_renderbuffer);
+            gl::FramebufferRenderbuffer(
+                gl::FRAMEBUFFER,
+                gl::DEPTH_ATTACHMENT,
+                gl::RENDERBUFFER,
+                capture_renderbuffer,
+            );
+
+            gl::GenTextures(1, &mut equirectangular_texture);
+            gl::BindTexture(gl::TEXTURE_2D, equirectangular_texture);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGB16F as i32,
+                image.width as i32,
+                image.height as i32,
+                0,
+                gl::RGB,
+                gl::FLOAT,
+                image.pixels.as_ptr() as *const c_void,
+            );
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+        }
+
+        let environment_map = Cubemap::new(ENVIRONMENT_SIZE, true);
+        let shader = &mut self.shaders.equirectangular_to_cubemap;
+        shader.bind();
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D, equirectangular_texture);
+        }
+        shader.set_uniform_int(String::from("u_equirectangular_map"), 0);
+        render_cube_faces(shader, &self.cube, &environment_map, 0);
+        environment_map.bind(0);
+        unsafe {
+            gl::GenerateMipmap(gl::TEXTURE_CUBE_MAP);
+        }
+
+        let irradiance_map = Cubemap::new(IRRADIANCE_SIZE, false);
+        let shader = &mut self.shaders.irradiance;
+        shader.bind();
+        environment_map.bind(0);
+        shader.set_uniform_int(String::from("u_environment_map"), 0);
+        render_cube_faces(shader, &self.cube, &irradiance_map, 0);
+
+        let prefiltered_map = Cubemap::new(PREFILTERED_SIZE, true);
+        let shader = &mut self.shaders.prefilter;
+        shader.bind();
+        environment_map.bind(0);
+        shader.set_uniform_int(String::from("u_environment_map"), 0);
+        shader.set_uniform_float(
+            String::from("u_environment_resolution"),
+            ENVIRONMENT_SIZE as f32,
+        );
+        for mip in 0..PREFILTERED_MIP_LEVELS {
+            let roughness = mip as f32 / (PREFILTERED_MIP_LEVELS - 1) as f32;
+            shader.set_uniform_float(String::from("u_roughness"), roughness);
+            render_cube_faces(shader, &self.cube, &prefiltered_map, mip);
+        }
+
+        let mut brdf_lut = 0;
+        unsafe {
+            gl::GenTextures(1, &mut brdf_lut);
+            gl::BindTexture(gl::TEXTURE_2D, brdf_lut);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RG16F as i32,
+                BRDF_LUT_SIZE,
+                BRDF_LUT_SIZE,
+                0,
+                gl::RG,
+                gl::FLOAT,
+                std::ptr::null(),
+            );
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+
+            gl::RenderbufferStorage(
+                gl::RENDERBUFFER,
+                gl::DEPTH_COMPONENT24,
+                BRDF_LUT_SIZE,
+                BRDF_LUT_SIZE,
+            );
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::TEXTURE_2D,
+                brdf_lut,
+                0,
+            );
+            gl::Viewport(0, 0, BRDF_LUT_SIZE, BRDF_LUT_SIZE);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        self.shaders.brdf.bind();
+        self.fullscreen_vertex_array.bind();
+        draw_fullscreen_triangle();
+
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::DeleteRenderbuffers(1, &capture_renderbuffer);
+            gl::DeleteFramebuffers(1, &capture_framebuffer);
+            gl::DeleteTextures(1, &equirectangular_texture);
+            gl::Enable(gl::CULL_FACE);
+        }
+
+        Environment {
+            intensity: 1.0,
+            show_background: true,
+            background_blur: 0.0,
+            environment_map,
+            irradiance_map,
+            prefiltered_map,
+            brdf_lut,
+        }
+    }
+}
+
+/// Draws an environment behind everything that has been rendered so far.
+pub struct Skybox {
+    shader: ShaderProgram,
+    cube: SubMesh,
+}
+
+impl Skybox {
+    pub fn new(shader: ShaderProgram) -> Self {
+        Skybox {
+            shader,
+            cube: unit_cube(),
+        }
+    }
+    pub fn draw<A: TCamera>(&mut self, environment: &Environment, camera: &A) {
+        let mut view = camera.matrix();
+        view.w = Vector4::new(0.0, 0.0, 0.0, 1.0);
+
+        self.shader.bind();
+        if environment.background_blur > 0.0 {
+            environment.prefiltered_map.bind(0);
+            self.shader.set_uniform_float(
+                String::from("u_lod"),
+                environment.background_blur * (PREFILTERED_MIP_LEVELS - 1) as f32,
+            );
+        } else {
+            environment.environment_map.bind(0);
+            self.shader.set_uniform_float(String::from("u_lod"), 0.0);
+        }
+        self.shader
+            .set_uniform_int(String::from("u_environment_map"), 0);
+        self.shader
+            .set_uniform_float(String::from("u_intensity"), environment.intensity);
+        self.shader
+            .set_uniform_matrix4(String::from("u_view"), &view);
+        self.shader
+            .set_uniform_matrix4(String::from("u_projection"), &camera.projection_matrix());
+
+        unsafe {
+            gl::DepthFunc(gl::LEQUAL);
+            gl::Disable(gl::CULL_FACE);
+        }
+        draw_submesh(&self.cube);
+        unsafe {
+            gl::Enable(gl::CULL_FACE);
+            gl::DepthFunc(gl::LESS);
+        }
+    }
+}
+
+fn render_cube_faces(shader: &mut ShaderProgram, cube: &SubMesh, target: &Cubemap, mip: i32) {
+    let size = (target.size >> mip).max(1);
+    let projection: Matrix4<f32> = PerspectiveFov {
+        fovy: Rad::from(Deg(90.0)),
+        aspect: 1.0,
+        near: 0.1,
+        far: 10.0,
+    }
+    .to_perspective()
+    .into();
+    let origin = Point3::new(0.0, 0.0, 0.0);
+    let faces = [
+        (Vector3::unit_x(), -Vector3::unit_y()),
+        (-Vector3::unit_x(), -Vector3::unit_y()),
+        (Vector3::unit_y(), Vector3::unit_z()),
+        (-Vector3::unit_y(), -Vector3::unit_z()),
+        (Vector3::unit_z(), -Vector3::unit_y()),
+        (-Vector3::unit_z(), -Vector3::unit_y()),
+    ];
+
+    shader.set_uniform_matrix4(String::from("u_projection"), &projection);
+    unsafe {
+        gl::RenderbufferStorage(gl::RENDERBUFFER, gl::DEPTH_COMPONENT24, size, size);
+        gl::Viewport(0, 0, size, size);
+    }
+    for (face, (direction, up)) in faces.iter().enumerate() {
+        let view = Matrix4::look_at(origin, origin + direction, *up);
+        shader.set_uniform_matrix4(String::from("u_view"), &view);
+        unsafe {
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::TEXTURE_CUBE_MAP_POSITIVE_X + face as u32,
+                target.id,
+                mip,
+            );
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        draw_submesh(cube);
+    }
+}
+
+pub(crate) fn draw_submesh(submesh: &SubMesh) {
+    submesh.vertex_array().bind();
+    submesh.index_buffer().bind();
+    unsafe {
+        gl::DrawElements(
+            gl::TRIANGLES,
+            submesh.index_buffer().count as i32,
+            submesh.index_buffer().gl_type(),
+            std::ptr::null(),
+        );
+    }
+}
+
+fn unit_cube() -> SubMesh {
+    let mut vertices = Vec::new();
+    for i in 0..8 {
+        let position = Vector3::new(
+            if i & 1 == 0 { -1.0 } else { 1.0 },
+            if i & 2 == 0 { -1.0 } else { 1.0 },
+            if i & 4 == 0 { -1.0 } else { 1.0 },
+        );
+        vertices.push(Vertex {
+            position,
+            normal: position.normalize(),
+            uv: Vector2::new(0.0, 0.0),
+        });
+    }
+    let indices = vec![
+        0, 2, 1, 1, 2, 3, // -z
+        4, 5, 6, 5, 7, 6, // +z
+        0, 1, 4, 1, 5, 4, // -y
+        2, 6, 3, 3, 6, 7, // +y
+        0, 4, 2, 2, 4, 6, // -x
+        1, 3, 5, 3, 7, 5, // +x
+    ];
+    SubMesh::new(vertices, indices, None)
+}
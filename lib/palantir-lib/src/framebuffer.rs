@@ -0,0 +1,231 @@
+use image::{ImageBuffer, Rgb, RgbaImage};
+use std::os::raw::c_void;
+
+#[derive(Debug)]
+pub enum FramebufferError {
+    Incomplete { status: u32 },
+}
+
+impl std::fmt::Display for FramebufferError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            FramebufferError::Incomplete { status } => {
+                write!(f, "Framebuffer is incomplete (status 0x{:X}).", status)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FramebufferError {}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FramebufferFormat {
+    Rgba8,
+    /// Floating point color, for values outside of the [0, 1] range.
+    Rgba16F,
+}
+
+impl FramebufferFormat {
+    fn gl_internal_format(self) -> u32 {
+        match self {
+            FramebufferFormat::Rgba8 => gl::RGBA8,
+            FramebufferFormat::Rgba16F => gl::RGBA16F,
+        }
+    }
+    fn gl_type(self) -> u32 {
+        match self {
+            FramebufferFormat::Rgba8 => gl::UNSIGNED_BYTE,
+            FramebufferFormat::Rgba16F => gl::FLOAT,
+        }
+    }
+}
+
+/// Offscreen render target with a color texture and a depth renderbuffer.
+#[derive(Debug, PartialEq)]
+pub struct Framebuffer {
+    id: u32,
+    color_texture: u32,
+    depth_renderbuffer: u32,
+    width: i32,
+    height: i32,
+    format: FramebufferFormat,
+}
+
+impl Framebuffer {
+    pub fn new(width: u32, height: u32) -> Result<Self, FramebufferError> {
+        Framebuffer::with_format(width, height, FramebufferFormat::Rgba8)
+    }
+    pub fn with_format(
+        width: u32,
+        height: u32,
+        format: FramebufferFormat,
+    ) -> Result<Self, FramebufferError> {
+        let mut framebuffer = Framebuffer {
+            id: 0,
+            color_texture: 0,
+            depth_renderbuffer: 0,
+            width: width.max(1) as i32,
+            height: height.max(1) as i32,
+            format,
+        };
+        unsafe {
+            gl::GenFramebuffers(1, &mut framebuffer.id);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer.id);
+
+            gl::GenTextures(1, &mut framebuffer.color_texture);
+            gl::BindTexture(gl::TEXTURE_2D, framebuffer.color_texture);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                format.gl_internal_format() as i32,
+                framebuffer.width,
+                framebuffer.height,
+                0,
+                gl::RGBA,
+                format.gl_type(),
+                std::ptr::null(),
+            );
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::TEXTURE_2D,
+                framebuffer.color_texture,
+                0,
+            );
+
+            gl::GenRenderbuffers(1, &mut framebuffer.depth_renderbuffer);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, framebuffer.depth_renderbuffer);
+            gl::RenderbufferStorage(
+                gl::RENDERBUFFER,
+                gl::DEPTH24_STENCIL8,
+                framebuffer.width,
+                framebuffer.height,
+            );
+            gl::FramebufferRenderbuffer(
+                gl::FRAMEBUFFER,
+                gl::DEPTH_STENCIL_ATTACHMENT,
+                gl::RENDERBUFFER,
+                framebuffer.depth_renderbuffer,
+            );
+
+            let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            if status != gl::FRAMEBUFFER_COMPLETE {
+                return Err(FramebufferError::Incomplete { status });
+            }
+        }
+        Ok(framebuffer)
+    }
+    /// Binds the framebuffer as the render target and sets the viewport to cover it.
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.id);
+            gl::Viewport(0, 0, self.width, self.height);
+        }
+    }
+    /// Restores the window's default framebuffer as the render target.
+    pub fn unbind() {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+    }
+    pub fn width(&self) -> u32 {
+        self.width as u32
+    }
+    pub fn height(&self) -> u32 {
+        self.height as u32
+    }
+    pub fn format(&self) -> FramebufferFormat {
+        self.format
+    }
+    pub fn color_texture(&self) -> u32 {
+        self.color_texture
+    }
+    /// Binds the color attachment for sampling from a shader.
+    pub fn bind_color_texture(&self, texture_unit: u32) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + texture_unit);
+            gl::BindTexture(gl::TEXTURE_2D, self.color_texture);
+        }
+    }
+    /// Reads the color attachment back into an image, top row first.
+    pub fn read_pixels(&self) -> RgbaImage {
+        read_pixels(self.id, self.width as u32, self.height as u32)
+    }
+    /// Reads the color attachment back as floating point values, top row first.
+    pub fn read_pixels_hdr(&self) -> ImageBuffer<Rgb<f32>, Vec<f32>> {
+        read_pixels_hdr(self.id, self.width as u32, self.height as u32)
+    }
+    /// Reads what has been drawn to the window's back buffer so far.
+    pub fn read_window_pixels(width: u32, height: u32) -> RgbaImage {
+        read_pixels(0, width, height)
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteRenderbuffers(1, &self.depth_renderbuffer);
+            gl::DeleteTextures(1, &self.color_texture);
+            gl::DeleteFramebuffers(1, &self.id);
+        }
+    }
+}
+
+fn read_pixels(framebuffer: u32, width: u32, height: u32) -> RgbaImage {
+    let mut data = vec![0u8; (width * height * 4) as usize];
+    read_framebuffer(
+        framebuffer,
+        width,
+        height,
+        gl::RGBA,
+        gl::UNSIGNED_BYTE,
+        data.as_mut_ptr(),
+    );
+    let image = RgbaImage::from_raw(width, height, data)
+        .expect("Pixel buffer does not match the framebuffer size.");
+    image::imageops::flip_vertical(&image)
+}
+
+fn read_pixels_hdr(framebuffer: u32, width: u32, height: u32) -> ImageBuffer<Rgb<f32>, Vec<f32>> {
+    let mut data = vec![0f32; (width * height * 3) as usize];
+    read_framebuffer(
+        framebuffer,
+        width,
+        height,
+        gl::RGB,
+        gl::FLOAT,
+        data.as_mut_ptr(),
+    );
+    let image = ImageBuffer::from_raw(width, height, data)
+        .expect("Pixel buffer does not match the framebuffer size.");
+    image::imageops::flip_vertical(&image)
+}
+
+fn read_framebuffer<T>(
+    framebuffer: u32,
+    width: u32,
+    height: u32,
+    format: u32,
+    gl_type: u32,
+    data: *mut T,
+) {
+    unsafe {
+        gl::BindFramebuffer(gl::READ_FRAMEBUFFER, framebuffer);
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl::ReadPixels(
+            0,
+            0,
+            width as i32,
+            height as i32,
+            format,
+            gl_type,
+            data as *mut c_void,
+        );
+        gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+    }
+}
@@ -0,0 +1,118 @@
+use crate::{Aabb, BoundingSphere};
+use cgmath::prelude::*;
+use cgmath::{Matrix4, Vector3, Vector4};
+
+/// The six planes bounding what a camera sees, normals pointing inward.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frustum {
+    /// Normal in xyz and offset in w, a point is inside when `dot(normal, point) + w >= 0`.
+    planes: [Vector4<f32>; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes of a projection times view matrix, in world space.
+    pub fn from_matrix(view_projection: &Matrix4<f32>) -> Self {
+        let m = view_projection.transpose();
+        let planes = [
+            m.w + m.x, // Left
+            m.w - m.x, // Right
+            m.w + m.y, // Bottom
+            m.w - m.y, // Top
+            m.w + m.z, // Near
+            m.w - m.z, // Far
+        ];
+        let mut frustum = Frustum { planes };
+        for plane in &mut frustum.planes {
+            let length = plane.truncate().magnitude();
+            if length > 0.0 {
+                *plane /= length;
+            }
+        }
+        frustum
+    }
+    fn distance(plane: &Vector4<f32>, point: Vector3<f32>) -> f32 {
+        plane.truncate().dot(point) + plane.w
+    }
+    pub fn intersects_sphere(&self, sphere: &BoundingSphere) -> bool {
+        !sphere.is_empty()
+            && self
+                .planes
+                .iter()
+                .all(|plane| Frustum::distance(plane, sphere.center) >= -sphere.radius)
+    }
+    /// Conservative, some boxes near the corners pass while being outside.
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        if aabb.is_empty() {
+            return false;
+        }
+        self.planes.iter().all(|plane| {
+            // Corner furthest along the normal.
+            let corner = Vector3::new(
+                if plane.x >= 0.0 {
+                    aabb.max.x
+                } else {
+                    aabb.min.x
+                },
+                if plane.y >= 0.0 {
+                    aabb.max.y
+                } else {
+                    aabb.min.y
+                },
+                if plane.z >= 0.0 {
+                    aabb.max.z
+                } else {
+                    aabb.min.z
+                },
+            );
+            Frustum::distance(plane, corner) >= 0.0
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{perspective, Deg, Point3};
+
+    fn cube(center: Vector3<f32>, half: f32) -> Aabb {
+        let half = Vector3::new(half, half, half);
+        Aabb::new(center - half, center + half)
+    }
+
+    #[test]
+    fn clip_space_boxes() {
+        // The identity keeps the clip space cube from -1 to 1.
+        let frustum = Frustum::from_matrix(&Matrix4::identity());
+        assert!(frustum.intersects_aabb(&cube(Vector3::zero(), 0.5)));
+        assert!(!frustum.intersects_aabb(&cube(Vector3::new(3.0, 0.0, 0.0), 0.5)));
+        assert!(!frustum.intersects_aabb(&cube(Vector3::new(0.0, 0.0, -3.0), 0.5)));
+        // Straddling one plane.
+        assert!(frustum.intersects_aabb(&cube(Vector3::new(1.0, 0.0, 0.0), 0.5)));
+        assert!(frustum.intersects_aabb(&cube(Vector3::new(0.0, -1.2, 0.0), 0.5)));
+        assert!(!frustum.intersects_aabb(&Aabb::empty()));
+    }
+
+    #[test]
+    fn camera_boxes_and_spheres() {
+        let view = Matrix4::look_at(
+            Point3::new(0.0, 0.0, 10.0),
+            Point3::new(0.0, 0.0, 0.0),
+            Vector3::unit_y(),
+        );
+        let frustum = Frustum::from_matrix(&(perspective(Deg(90.0), 1.0, 1.0, 100.0) * view));
+
+        assert!(frustum.intersects_aabb(&cube(Vector3::zero(), 1.0)));
+        // Behind the camera, beyond the far plane and to the side.
+        assert!(!frustum.intersects_aabb(&cube(Vector3::new(0.0, 0.0, 20.0), 1.0)));
+        assert!(!frustum.intersects_aabb(&cube(Vector3::new(0.0, 0.0, -200.0), 1.0)));
+        assert!(!frustum.intersects_aabb(&cube(Vector3::new(30.0, 0.0, 0.0), 1.0)));
+        // Straddling the near and the left planes.
+        assert!(frustum.intersects_aabb(&cube(Vector3::new(0.0, 0.0, 9.0), 1.0)));
+        assert!(frustum.intersects_aabb(&cube(Vector3::new(-10.0, 0.0, 0.0), 1.0)));
+
+        assert!(frustum.intersects_sphere(&BoundingSphere::new(Vector3::zero(), 1.0)));
+        assert!(frustum.intersects_sphere(&BoundingSphere::new(Vector3::new(-10.5, 0.0, 0.0), 1.0)));
+        assert!(!frustum.intersects_sphere(&BoundingSphere::new(Vector3::new(30.0, 0.0, 0.0), 1.0)));
+        assert!(!frustum.intersects_sphere(&BoundingSphere::empty()));
+    }
+}
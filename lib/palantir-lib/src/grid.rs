@@ -0,0 +1,46 @@
+use crate::{draw_fullscreen_triangle, ShaderProgram, TCamera, VertexArray};
+
+/// Infinite reference grid on the XZ plane, fading out with the distance.
+pub struct Grid {
+    pub cell_size: f32,
+    pub fade_distance: f32,
+    shader: ShaderProgram,
+    vertex_array: VertexArray,
+}
+
+impl Grid {
+    pub fn new(shader: ShaderProgram) -> Self {
+        Grid {
+            cell_size: 1.0,
+            fade_distance: 50.0,
+            shader,
+            vertex_array: VertexArray::new(),
+        }
+    }
+    pub fn draw<A: TCamera>(&mut self, camera: &A) {
+        self.shader.bind();
+        self.shader
+            .set_uniform_matrix4(String::from("u_view"), &camera.matrix());
+        self.shader
+            .set_uniform_matrix4(String::from("u_projection"), &camera.projection_matrix());
+        self.shader
+            .set_uniform_float(String::from("u_cell_size"), self.cell_size);
+        self.shader
+            .set_uniform_float(String::from("u_fade_distance"), self.fade_distance);
+
+        self.vertex_array.bind();
+        unsafe {
+            gl::Enable(gl::BLEND);
+            gl::BlendFuncSeparate(
+                gl::SRC_ALPHA,
+                gl::ONE_MINUS_SRC_ALPHA,
+                gl::ONE,
+                gl::ONE_MINUS_SRC_ALPHA,
+            );
+        }
+        draw_fullscreen_triangle();
+        unsafe {
+            gl::Disable(gl::BLEND);
+        }
+    }
+}
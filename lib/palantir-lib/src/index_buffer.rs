@@ -1,34 +1,181 @@
-use gl;
+use crate::BufferUsage;
 
-#[derive(Clone, Debug, PartialEq)]
+/// Size of the indices of an index buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IndexFormat {
+    /// Half the memory, for up to 65536 vertices.
+    U16,
+    U32,
+}
+
+impl IndexFormat {
+    /// Smallest format holding the indices.
+    pub fn fitting(indices: &[u32]) -> Self {
+        if indices.iter().all(|index| *index <= u32::from(u16::MAX)) {
+            IndexFormat::U16
+        } else {
+            IndexFormat::U32
+        }
+    }
+    /// Whether the format can hold every one of the indices.
+    pub fn holds(self, indices: &[u32]) -> bool {
+        self == IndexFormat::U32 || IndexFormat::fitting(indices) == IndexFormat::U16
+    }
+    /// In bytes.
+    pub fn size(self) -> usize {
+        match self {
+            IndexFormat::U16 => std::mem::size_of::<u16>(),
+            IndexFormat::U32 => std::mem::size_of::<u32>(),
+        }
+    }
+    pub(crate) fn gl_type(self) -> u32 {
+        match self {
+            IndexFormat::U16 => gl::UNSIGNED_SHORT,
+            IndexFormat::U32 => gl::UNSIGNED_INT,
+        }
+    }
+}
+
+/// Owns its GL buffer, deleted on drop, so it cannot be cloned.
+#[derive(Debug, PartialEq)]
 pub struct IndexBuffer {
     pub count: u32,
     id: u32,
+    usage: BufferUsage,
+    format: IndexFormat,
 }
 
 impl IndexBuffer {
     pub fn new(indices: Vec<u32>) -> Self {
+        IndexBuffer::with_usage(&indices, BufferUsage::Static)
+    }
+    /// Buffer in the smallest format holding the indices.
+    pub fn with_usage(indices: &[u32], usage: BufferUsage) -> Self {
         let mut buffer = IndexBuffer {
-            count: indices.len() as u32,
+            count: 0,
             id: 0,
+            usage,
+            format: IndexFormat::U32,
         };
         unsafe {
             gl::GenBuffers(1, &mut buffer.id);
-            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, buffer.id);
+        }
+        buffer.update(indices);
+        buffer
+    }
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.id);
+        }
+    }
+    pub fn usage(&self) -> BufferUsage {
+        self.usage
+    }
+    /// Used from the next `update` on.
+    pub fn set_usage(&mut self, usage: BufferUsage) {
+        self.usage = usage;
+    }
+    pub fn format(&self) -> IndexFormat {
+        self.format
+    }
+    /// Replaces the indices, in the smallest format holding them. The previous storage is
+    /// orphaned, so draws still reading it do not stall the upload.
+    pub fn update(&mut self, indices: &[u32]) {
+        self.format = IndexFormat::fitting(indices);
+        self.count = indices.len() as u32;
+        let data = index_bytes(indices, self.format);
+        unsafe {
+            // The element buffer binding is part of the vertex array state, this one must not
+            // replace the binding of the vertex array last drawn.
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.id);
             gl::BufferData(
                 gl::ELEMENT_ARRAY_BUFFER,
-                (indices.len() * std::mem::size_of::<u32>()) as gl::types::GLsizeiptr,
-                indices.as_ptr() as *const gl::types::GLvoid,
-                gl::STATIC_DRAW,
+                data.len() as gl::types::GLsizeiptr,
+                data.as_ptr() as *const gl::types::GLvoid,
+                self.usage.gl_usage(),
             );
         }
-        buffer
     }
-    pub fn bind(&self) {
+    /// Overwrites the indices from `first` on, the range must be inside of the buffer. Indices
+    /// too large for 16 bits widen the buffer, which reads it back and uploads it again.
+    pub fn update_range(&mut self, first: usize, indices: &[u32]) {
+        assert!(
+            first + indices.len() <= self.count as usize,
+            "Updated range is past the end of the index buffer."
+        );
+        if !self.format.holds(indices) {
+            self.update(&with_range(self.read(), first, indices));
+            return;
+        }
+        let data = index_bytes(indices, self.format);
         unsafe {
+            gl::BindVertexArray(0);
             gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.id);
+            gl::BufferSubData(
+                gl::ELEMENT_ARRAY_BUFFER,
+                (first * self.format.size()) as gl::types::GLintptr,
+                data.len() as gl::types::GLsizeiptr,
+                data.as_ptr() as *const gl::types::GLvoid,
+            );
         }
     }
+    /// Reads the indices back from the GPU.
+    pub fn read(&self) -> Vec<u32> {
+        let count = self.count as usize;
+        unsafe {
+            // The element buffer binding is part of the vertex array state, this one is not
+            // attached to any.
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.id);
+            match self.format {
+                IndexFormat::U16 => read_indices::<u16>(count)
+                    .into_iter()
+                    .map(u32::from)
+                    .collect(),
+                IndexFormat::U32 => read_indices::<u32>(count),
+            }
+        }
+    }
+    pub(crate) fn gl_type(&self) -> u32 {
+        self.format.gl_type()
+    }
+}
+
+/// Indices as uploaded in a format, which must hold them.
+fn index_bytes(indices: &[u32], format: IndexFormat) -> Vec<u8> {
+    match format {
+        IndexFormat::U16 => indices
+            .iter()
+            .flat_map(|index| (*index as u16).to_ne_bytes().to_vec())
+            .collect(),
+        IndexFormat::U32 => indices
+            .iter()
+            .flat_map(|index| index.to_ne_bytes().to_vec())
+            .collect(),
+    }
+}
+
+/// All the indices of a buffer once `update_range` replaced the ones from `first` on.
+fn with_range(mut all: Vec<u32>, first: usize, indices: &[u32]) -> Vec<u32> {
+    all[first..first + indices.len()].copy_from_slice(indices);
+    all
+}
+
+/// Reads up to `count` indices of the bound element buffer, fewer when it holds less.
+unsafe fn read_indices<T>(count: usize) -> Vec<T> {
+    let mut size = 0;
+    gl::GetBufferParameteriv(gl::ELEMENT_ARRAY_BUFFER, gl::BUFFER_SIZE, &mut size);
+    let count = count.min(size.max(0) as usize / std::mem::size_of::<T>());
+    let mut indices = Vec::with_capacity(count);
+    gl::GetBufferSubData(
+        gl::ELEMENT_ARRAY_BUFFER,
+        0,
+        (count * std::mem::size_of::<T>()) as gl::types::GLsizeiptr,
+        indices.as_mut_ptr() as *mut gl::types::GLvoid,
+    );
+    indices.set_len(count);
+    indices
 }
 
 impl Drop for IndexBuffer {
@@ -38,3 +185,39 @@ impl Drop for IndexBuffer {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fitting_format() {
+        assert_eq!(IndexFormat::fitting(&[]), IndexFormat::U16);
+        assert_eq!(IndexFormat::fitting(&[0, 65535]), IndexFormat::U16);
+        assert_eq!(IndexFormat::fitting(&[0, 65536]), IndexFormat::U32);
+        assert!(IndexFormat::U16.holds(&[1, 2, 65535]));
+        assert!(!IndexFormat::U16.holds(&[1, 70000]));
+        assert!(IndexFormat::U32.holds(&[1, 70000]));
+    }
+
+    #[test]
+    fn bytes_of_each_format() {
+        let indices = [1, 2, 65535];
+        let bytes = index_bytes(&indices, IndexFormat::U16);
+        assert_eq!(bytes.len(), indices.len() * IndexFormat::U16.size());
+        assert_eq!(&bytes[4..], &65535u16.to_ne_bytes());
+        let bytes = index_bytes(&indices, IndexFormat::U32);
+        assert_eq!(bytes.len(), indices.len() * IndexFormat::U32.size());
+        assert_eq!(&bytes[8..], &65535u32.to_ne_bytes());
+    }
+
+    #[test]
+    fn widening_range_update() {
+        let current = vec![0, 1, 2, 2, 1, 3];
+        let indices = [70000, 70001];
+        assert!(!IndexFormat::fitting(&current).holds(&indices));
+        let all = with_range(current, 3, &indices);
+        assert_eq!(all, vec![0, 1, 2, 70000, 70001, 3]);
+        assert_eq!(IndexFormat::fitting(&all), IndexFormat::U32);
+    }
+}
@@ -1,21 +1,47 @@
+mod bounds;
+mod environment;
+mod framebuffer;
+mod frustum;
+mod grid;
 mod index_buffer;
+mod line_renderer;
 mod material;
 mod mesh;
+mod mesh_data;
+mod mesh_processing;
+mod morph_target;
 mod objects;
+mod post_process;
 mod renderer;
 mod shader;
+mod shading;
+mod simplify;
 mod texture;
+mod transparency;
 mod vertex;
 mod vertex_array;
 mod vertex_buffer;
 
+pub use self::bounds::*;
+pub use self::environment::*;
+pub use self::framebuffer::*;
+pub use self::frustum::*;
+pub use self::grid::*;
 pub use self::index_buffer::*;
+pub use self::line_renderer::*;
 pub use self::material::*;
 pub use self::mesh::*;
+pub use self::mesh_data::*;
+pub use self::mesh_processing::*;
+pub use self::morph_target::*;
 pub use self::objects::*;
+pub use self::post_process::*;
 pub use self::renderer::*;
 pub use self::shader::*;
+pub use self::shading::*;
+pub use self::simplify::*;
 pub use self::texture::*;
+pub use self::transparency::*;
 pub use self::vertex::*;
 pub use self::vertex_array::*;
 pub use self::vertex_buffer::*;
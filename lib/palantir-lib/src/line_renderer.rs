@@ -0,0 +1,89 @@
+use crate::{
+    Aabb, BufferUsage, ShaderProgram, TCamera, VertexArray, VertexBuffer, VertexBufferLayout,
+};
+use cgmath::Vector3;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LineVertex {
+    pub position: Vector3<f32>,
+    pub color: Vector3<f32>,
+}
+
+/// Batches colored line segments and draws them all at once.
+pub struct LineRenderer {
+    shader: ShaderProgram,
+    vertices: Vec<LineVertex>,
+    vertex_array: VertexArray,
+    /// Replaced by every draw.
+    vertex_buffer: VertexBuffer,
+}
+
+impl LineRenderer {
+    pub fn new(shader: ShaderProgram) -> Self {
+        let mut layout = VertexBufferLayout::new();
+        layout.push::<f32>(3); // Position
+        layout.push::<f32>(3); // Color
+        let vertex_buffer = VertexBuffer::with_usage::<LineVertex>(&[], BufferUsage::Stream);
+        let vertex_array = VertexArray::new();
+        vertex_array.add_buffer(&vertex_buffer, &layout);
+        LineRenderer {
+            shader,
+            vertices: Vec::new(),
+            vertex_array,
+            vertex_buffer,
+        }
+    }
+    pub fn push_line(&mut self, start: Vector3<f32>, end: Vector3<f32>, color: Vector3<f32>) {
+        self.vertices.push(LineVertex {
+            position: start,
+            color,
+        });
+        self.vertices.push(LineVertex {
+            position: end,
+            color,
+        });
+    }
+    /// Pushes the twelve edges of the box.
+    pub fn push_box(&mut self, aabb: &Aabb, color: Vector3<f32>) {
+        let corners = aabb.corners();
+        // Corner indices are the x, y and z bits of the corner.
+        for (start, end) in [
+            (0, 1),
+            (2, 3),
+            (4, 5),
+            (6, 7),
+            (0, 2),
+            (1, 3),
+            (4, 6),
+            (5, 7),
+            (0, 4),
+            (1, 5),
+            (2, 6),
+            (3, 7),
+        ]
+        .iter()
+        {
+            self.push_line(corners[*start], corners[*end], color);
+        }
+    }
+    /// Draws the lines pushed since the last call, in world space.
+    pub fn draw<A: TCamera>(&mut self, camera: &A) {
+        if self.vertices.is_empty() {
+            return;
+        }
+        let count = self.vertices.len() as i32;
+        self.vertex_buffer.update(&self.vertices);
+        self.vertices.clear();
+        self.vertex_array.bind();
+
+        self.shader.bind();
+        self.shader
+            .set_uniform_matrix4(String::from("u_view"), &camera.matrix());
+        self.shader
+            .set_uniform_matrix4(String::from("u_projection"), &camera.projection_matrix());
+        unsafe {
+            gl::DrawArrays(gl::LINES, 0, count);
+        }
+    }
+}
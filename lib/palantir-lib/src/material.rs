@@ -1,10 +1,44 @@
 use crate::{ShaderProgram, Texture};
 use cgmath::Vector3;
 
+/// How the alpha of a material, its opacity times the alpha of its texture, is used.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlendMode {
+    Opaque,
+    /// Fragments under the alpha cutoff are discarded, for foliage or fences.
+    Masked,
+    /// Blended over what is behind, drawn after the opaque geometry.
+    Blended,
+}
+
+impl BlendMode {
+    pub const ALL: [BlendMode; 3] = [BlendMode::Opaque, BlendMode::Masked, BlendMode::Blended];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BlendMode::Opaque => "Opaque",
+            BlendMode::Masked => "Masked",
+            BlendMode::Blended => "Blended",
+        }
+    }
+    fn shader_index(self) -> i32 {
+        match self {
+            BlendMode::Opaque => 0,
+            BlendMode::Masked => 1,
+            BlendMode::Blended => 2,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Material {
     diffuse: Vector3<f32>,
     diffuse_texture: Option<Texture>,
+    metallic: f32,
+    roughness: f32,
+    opacity: f32,
+    alpha_cutoff: f32,
+    blend_mode: BlendMode,
 }
 
 impl Material {
@@ -12,10 +46,70 @@ impl Material {
         Material {
             diffuse,
             diffuse_texture,
+            metallic: 0.0,
+            roughness: 0.5,
+            opacity: 1.0,
+            alpha_cutoff: 0.5,
+            blend_mode: BlendMode::Opaque,
         }
     }
+    pub fn diffuse(&self) -> Vector3<f32> {
+        self.diffuse
+    }
+    pub fn set_diffuse(&mut self, diffuse: Vector3<f32>) {
+        self.diffuse = diffuse;
+    }
+    pub fn diffuse_texture(&self) -> Option<Texture> {
+        self.diffuse_texture
+    }
+    pub fn set_diffuse_texture(&mut self, diffuse_texture: Option<Texture>) {
+        self.diffuse_texture = diffuse_texture;
+    }
+    pub fn metallic(&self) -> f32 {
+        self.metallic
+    }
+    pub fn set_metallic(&mut self, metallic: f32) {
+        self.metallic = metallic.clamp(0.0, 1.0);
+    }
+    pub fn roughness(&self) -> f32 {
+        self.roughness
+    }
+    pub fn set_roughness(&mut self, roughness: f32) {
+        self.roughness = roughness.clamp(0.0, 1.0);
+    }
+    pub fn opacity(&self) -> f32 {
+        self.opacity
+    }
+    pub fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity.clamp(0.0, 1.0);
+    }
+    /// Alpha under which the fragments of a `BlendMode::Masked` material are discarded.
+    pub fn alpha_cutoff(&self) -> f32 {
+        self.alpha_cutoff
+    }
+    pub fn set_alpha_cutoff(&mut self, alpha_cutoff: f32) {
+        self.alpha_cutoff = alpha_cutoff.clamp(0.0, 1.0);
+    }
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        self.blend_mode = blend_mode;
+    }
+    /// Whether the material is drawn in the transparent pass of the renderer.
+    pub fn is_transparent(&self) -> bool {
+        self.blend_mode == BlendMode::Blended
+    }
     pub fn send_to_shader(&self, shader: &mut ShaderProgram) {
         shader.set_uniform_vector3(String::from("material.diffuse"), &self.diffuse);
+        shader.set_uniform_float(String::from("material.metallic"), self.metallic);
+        shader.set_uniform_float(String::from("material.roughness"), self.roughness);
+        shader.set_uniform_float(String::from("material.opacity"), self.opacity);
+        shader.set_uniform_float(String::from("material.alpha_cutoff"), self.alpha_cutoff);
+        shader.set_uniform_int(
+            String::from("material.blend_mode"),
+            self.blend_mode.shader_index(),
+        );
         match &self.diffuse_texture {
             Some(texture) => {
                 texture.bind(0);
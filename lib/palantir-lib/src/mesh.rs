@@ -1,9 +1,25 @@
-use crate::{IndexBuffer, Material, Vertex, VertexArray, VertexBuffer, VertexBufferLayout};
+use crate::{
+    Aabb, BoundingSphere, BufferUsage, IndexBuffer, JointWeights, Material, MeshData, MorphTarget,
+    MorphTargetBuffer, SkinnedVertex, Vertex, VertexArray, VertexBuffer, VertexBufferLayout,
+};
+use cgmath::Vector3;
+use std::cmp::Ordering;
 use std::sync::Arc;
 
-#[derive(Clone, Debug, PartialEq)]
+/// Geometry uploaded to the GPU. It owns its buffers and is not `Clone`, a copy would
+/// delete them twice.
+#[derive(Debug, PartialEq)]
 pub struct SubMesh {
     pub material_index: Option<usize>,
+    bounds: Aabb,
+    bounding_sphere: BoundingSphere,
+    skinned: bool,
+    vertex_count: usize,
+    morph_targets: Option<MorphTargetBuffer>,
+    /// Index of the weight of the first morph target in the weights of the mesh.
+    morph_weight_offset: usize,
+    /// Copy of the uploaded geometry, when kept.
+    data: Option<MeshData>,
     vertex_buffer: VertexBuffer,
     layout: VertexBufferLayout,
     index_buffer: IndexBuffer,
@@ -12,9 +28,68 @@ pub struct SubMesh {
 
 impl SubMesh {
     pub fn new(vertices: Vec<Vertex>, indices: Vec<u32>, material_index: Option<usize>) -> Self {
+        let positions: Vec<_> = vertices.iter().map(|vertex| vertex.position).collect();
+        SubMesh::from_buffer(
+            &positions,
+            VertexBuffer::new(vertices),
+            false,
+            indices,
+            material_index,
+        )
+    }
+    /// Submesh deformed by the joint matrices of the renderer, its bounds are the ones of the
+    /// rest pose.
+    pub fn new_skinned(
+        vertices: Vec<SkinnedVertex>,
+        indices: Vec<u32>,
+        material_index: Option<usize>,
+    ) -> Self {
+        let positions: Vec<_> = vertices
+            .iter()
+            .map(|vertex| vertex.vertex.position)
+            .collect();
+        SubMesh::from_buffer(
+            &positions,
+            VertexBuffer::new(vertices),
+            true,
+            indices,
+            material_index,
+        )
+    }
+    /// Submesh keeping a copy of its geometry, skinned when the data has a skin.
+    pub fn from_data(data: MeshData, material_index: Option<usize>) -> Self {
+        let mut submesh = SubMesh::upload(&data, material_index);
+        submesh.data = Some(data);
+        submesh
+    }
+    fn upload(data: &MeshData, material_index: Option<usize>) -> Self {
+        let positions: Vec<_> = data.vertices.iter().map(|vertex| vertex.position).collect();
+        let indices = data.indices.clone();
+        if data.is_skinned() {
+            let vertex_buffer = VertexBuffer::new(data.skinned_vertices());
+            SubMesh::from_buffer(&positions, vertex_buffer, true, indices, material_index)
+        } else {
+            let vertex_buffer = VertexBuffer::new(data.vertices.clone());
+            SubMesh::from_buffer(&positions, vertex_buffer, false, indices, material_index)
+        }
+    }
+    fn from_buffer(
+        positions: &[Vector3<f32>],
+        vertex_buffer: VertexBuffer,
+        skinned: bool,
+        indices: Vec<u32>,
+        material_index: Option<usize>,
+    ) -> Self {
         let mut submesh = SubMesh {
             material_index,
-            vertex_buffer: VertexBuffer::new(vertices),
+            bounds: Aabb::from_points(positions.iter().copied()),
+            bounding_sphere: BoundingSphere::from_points(positions.iter().copied()),
+            skinned,
+            vertex_count: positions.len(),
+            morph_targets: None,
+            morph_weight_offset: 0,
+            data: None,
+            vertex_buffer,
             layout: VertexBufferLayout::new(),
             index_buffer: IndexBuffer::new(indices),
             vertex_array: VertexArray::new(),
@@ -23,12 +98,115 @@ impl SubMesh {
         submesh.layout.push::<f32>(3); // Position
         submesh.layout.push::<f32>(3); // Normal
         submesh.layout.push::<f32>(2); // UV coordinates
+        if skinned {
+            submesh.layout.push::<u32>(4); // Joint indices
+            submesh.layout.push::<f32>(4); // Joint weights
+        }
 
         submesh
             .vertex_array
             .add_buffer(&submesh.vertex_buffer, &submesh.layout);
         submesh
     }
+    pub fn is_skinned(&self) -> bool {
+        self.skinned
+    }
+    /// Uploads blend shapes of the submesh. Their weights are the ones given to
+    /// `Renderer::set_morph_weights` from `weight_offset` on, so that submeshes can share or
+    /// split the weights of their mesh. The bounds stay the ones of the base shape.
+    pub fn set_morph_targets(&mut self, targets: &[MorphTarget], weight_offset: usize) {
+        self.morph_targets = if targets.is_empty() {
+            None
+        } else {
+            Some(MorphTargetBuffer::new(targets, self.vertex_count))
+        };
+        self.morph_weight_offset = weight_offset;
+    }
+    pub fn morph_targets(&self) -> Option<&MorphTargetBuffer> {
+        self.morph_targets.as_ref()
+    }
+    pub fn morph_weight_offset(&self) -> usize {
+        self.morph_weight_offset
+    }
+    /// Bounds of the vertices, in the mesh space.
+    pub fn bounds(&self) -> Aabb {
+        self.bounds
+    }
+    /// Sphere around the vertices, in the mesh space.
+    pub fn bounding_sphere(&self) -> BoundingSphere {
+        self.bounding_sphere
+    }
+    /// Geometry kept in memory, if any.
+    pub fn data(&self) -> Option<&MeshData> {
+        self.data.as_ref()
+    }
+    /// Geometry kept in memory, or else read back from the GPU.
+    pub fn to_data(&self) -> MeshData {
+        if let Some(data) = &self.data {
+            return data.clone();
+        }
+        let indices = self.index_buffer.read();
+        // The vertex buffer holds skinned vertices exactly when the submesh is skinned.
+        if self.skinned {
+            let vertices: Vec<SkinnedVertex> =
+                unsafe { self.vertex_buffer.read(self.vertex_count) };
+            MeshData {
+                vertices: vertices.iter().map(|vertex| vertex.vertex).collect(),
+                skin: vertices
+                    .iter()
+                    .map(|vertex| JointWeights {
+                        joints: vertex.joints,
+                        weights: vertex.weights,
+                    })
+                    .collect(),
+                indices,
+            }
+        } else {
+            MeshData::new(
+                unsafe { self.vertex_buffer.read(self.vertex_count) },
+                indices,
+            )
+        }
+    }
+    /// Replaces the geometry, updating the buffers in place unless it gains or loses its skin,
+    /// and keeps it in memory. The morph targets are dropped when the number of vertices
+    /// changes.
+    pub fn set_data(&mut self, data: MeshData) {
+        if data.is_skinned() != self.skinned {
+            let mut submesh = SubMesh::upload(&data, self.material_index);
+            submesh.data = Some(data);
+            *self = submesh;
+            return;
+        }
+        // Edited geometry is likely to be edited again.
+        self.vertex_buffer.set_usage(BufferUsage::Dynamic);
+        self.index_buffer.set_usage(BufferUsage::Dynamic);
+        if self.skinned {
+            self.vertex_buffer.update(&data.skinned_vertices());
+        } else {
+            self.vertex_buffer.update(&data.vertices);
+        }
+        self.index_buffer.update(&data.indices);
+        if data.vertices.len() != self.vertex_count {
+            self.morph_targets = None;
+        }
+        self.vertex_count = data.vertices.len();
+        self.bounds = data.bounds();
+        self.bounding_sphere = data.bounding_sphere();
+        self.data = Some(data);
+    }
+    /// Frees the copy of the geometry, the GPU buffers stay.
+    pub fn release_data(&mut self) {
+        self.data = None;
+    }
+    /// Distance along the ray to the closest triangle hit, against the bounds when the
+    /// geometry is not kept. In mesh space, the rest pose for skinned and morphed submeshes.
+    pub fn intersect_ray(&self, origin: Vector3<f32>, direction: Vector3<f32>) -> Option<f32> {
+        match &self.data {
+            Some(data) => data.intersect_ray(origin, direction),
+            None => self.bounds.intersect_ray(origin, direction),
+        }
+    }
     pub fn index_buffer(&self) -> &IndexBuffer {
         &self.index_buffer
     }
@@ -37,10 +215,21 @@ impl SubMesh {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+/// Lower detail version of a mesh, sharing its materials.
+#[derive(Debug, PartialEq)]
+pub struct MeshLod {
+    pub submeshes: Vec<SubMesh>,
+    /// Used while the mesh is smaller on screen than this fraction of the view height.
+    pub screen_size: f32,
+}
+
+#[derive(Debug, PartialEq)]
 pub struct Mesh {
+    /// Full detail geometry, level 0.
     pub submeshes: Vec<SubMesh>,
     pub materials: Vec<Arc<Material>>,
+    /// Levels 1 and up, by decreasing `screen_size`.
+    pub lods: Vec<MeshLod>,
 }
 
 impl Mesh {
@@ -48,6 +237,55 @@ impl Mesh {
         Mesh {
             submeshes,
             materials: Vec::new(),
+            lods: Vec::new(),
         }
     }
+    /// Number of levels, the full detail one included.
+    pub fn lod_count(&self) -> usize {
+        self.lods.len() + 1
+    }
+    /// Submeshes of a level, the coarsest one for levels past the last.
+    pub fn lod_submeshes(&self, level: usize) -> &[SubMesh] {
+        match level.min(self.lods.len()) {
+            0 => &self.submeshes,
+            level => &self.lods[level - 1].submeshes,
+        }
+    }
+    /// Level to draw when the mesh covers `screen_size` of the view height.
+    pub fn select_lod(&self, screen_size: f32) -> usize {
+        self.lods
+            .iter()
+            .take_while(|lod| screen_size < lod.screen_size)
+            .count()
+    }
+    /// Whether a submesh of the level has a material drawn in the transparent pass.
+    pub fn has_transparent_submeshes(&self, level: usize) -> bool {
+        self.lod_submeshes(level).iter().any(|submesh| {
+            match submesh.material_index.and_then(|i| self.materials.get(i)) {
+                Some(material) => material.is_transparent(),
+                None => false,
+            }
+        })
+    }
+    pub fn bounds(&self) -> Aabb {
+        self.submeshes
+            .iter()
+            .fold(Aabb::empty(), |bounds, submesh| {
+                bounds.union(&submesh.bounds())
+            })
+    }
+    pub fn bounding_sphere(&self) -> BoundingSphere {
+        self.submeshes
+            .iter()
+            .fold(BoundingSphere::empty(), |sphere, submesh| {
+                sphere.union(&submesh.bounding_sphere())
+            })
+    }
+    /// Closest hit of the ray with the full detail submeshes, see `SubMesh::intersect_ray`.
+    pub fn intersect_ray(&self, origin: Vector3<f32>, direction: Vector3<f32>) -> Option<f32> {
+        self.submeshes
+            .iter()
+            .filter_map(|submesh| submesh.intersect_ray(origin, direction))
+            .min_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+    }
 }
@@ -0,0 +1,98 @@
+use crate::{Aabb, BoundingSphere, JointWeights, SkinnedVertex, Vertex};
+use cgmath::prelude::*;
+use cgmath::Vector3;
+use std::cmp::Ordering;
+
+/// Geometry of a submesh kept in memory, to query and edit it without reading the GPU buffers
+/// back.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MeshData {
+    pub vertices: Vec<Vertex>,
+    /// One per vertex for skinned submeshes, empty otherwise.
+    pub skin: Vec<JointWeights>,
+    /// Three per triangle.
+    pub indices: Vec<u32>,
+}
+
+impl MeshData {
+    pub fn new(vertices: Vec<Vertex>, indices: Vec<u32>) -> Self {
+        MeshData {
+            vertices,
+            skin: Vec::new(),
+            indices,
+        }
+    }
+    pub fn is_skinned(&self) -> bool {
+        !self.skin.is_empty()
+    }
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+    /// Positions of the corners of each triangle, skipping the ones with invalid indices.
+    pub fn triangles(&self) -> impl Iterator<Item = [Vector3<f32>; 3]> + '_ {
+        self.indices.chunks_exact(3).filter_map(move |triangle| {
+            let position = |index: u32| self.vertices.get(index as usize).map(|v| v.position);
+            Some([
+                position(triangle[0])?,
+                position(triangle[1])?,
+                position(triangle[2])?,
+            ])
+        })
+    }
+    pub fn bounds(&self) -> Aabb {
+        Aabb::from_points(self.vertices.iter().map(|vertex| vertex.position))
+    }
+    pub fn bounding_sphere(&self) -> BoundingSphere {
+        BoundingSphere::from_points(self.vertices.iter().map(|vertex| vertex.position))
+    }
+    /// Distance along the ray to the closest triangle it hits from either side, in units of
+    /// `direction`.
+    pub fn intersect_ray(&self, origin: Vector3<f32>, direction: Vector3<f32>) -> Option<f32> {
+        self.triangles()
+            .filter_map(|triangle| intersect_triangle(origin, direction, &triangle))
+            .min_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+    }
+    pub(crate) fn skinned_vertices(&self) -> Vec<SkinnedVertex> {
+        self.vertices
+            .iter()
+            .zip(&self.skin)
+            .map(|(vertex, skin)| SkinnedVertex {
+                vertex: *vertex,
+                joints: skin.joints,
+                weights: skin.weights,
+            })
+            .collect()
+    }
+}
+
+/// Möller-Trumbore intersection.
+fn intersect_triangle(
+    origin: Vector3<f32>,
+    direction: Vector3<f32>,
+    [a, b, c]: &[Vector3<f32>; 3],
+) -> Option<f32> {
+    let edge1 = b - a;
+    let edge2 = c - a;
+    let p = direction.cross(edge2);
+    let determinant = edge1.dot(p);
+    if determinant.abs() < 1e-12 {
+        return None;
+    }
+    let inverse = 1.0 / determinant;
+    let to_origin = origin - a;
+    let u = to_origin.dot(p) * inverse;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = to_origin.cross(edge1);
+    let v = direction.dot(q) * inverse;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let distance = edge2.dot(q) * inverse;
+    // Also skips the NaN coming from invalid positions.
+    if !(distance >= 0.0 && distance.is_finite()) {
+        return None;
+    }
+    Some(distance)
+}
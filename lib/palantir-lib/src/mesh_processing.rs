@@ -0,0 +1,571 @@
+use crate::{Mesh, MeshData, SubMesh, Vertex};
+use cgmath::prelude::*;
+use cgmath::{Matrix3, Matrix4, Vector3};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::f32::consts::PI;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MeshError {
+    IndexOutOfRange { index: u32, vertex_count: usize },
+}
+
+impl std::fmt::Display for MeshError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            MeshError::IndexOutOfRange {
+                index,
+                vertex_count,
+            } => write!(
+                f,
+                "Index {} is out of range of the {} vertices.",
+                index, vertex_count
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MeshError {}
+
+/// Exact bits of a position, for finding the vertices split at the same point.
+pub fn position_key(position: Vector3<f32>) -> [u32; 3] {
+    [
+        position.x.to_bits(),
+        position.y.to_bits(),
+        position.z.to_bits(),
+    ]
+}
+
+/// Maps every vertex to the first vertex sharing its position.
+///
+/// Vertices are split wherever the normal or UV changes, welding them back is needed to find
+/// the real topology.
+pub fn weld_positions(vertices: &[Vertex]) -> Vec<u32> {
+    let mut first_vertex = HashMap::new();
+    vertices
+        .iter()
+        .enumerate()
+        .map(|(i, vertex)| {
+            *first_vertex
+                .entry(position_key(vertex.position))
+                .or_insert(i as u32)
+        })
+        .collect()
+}
+
+impl MeshData {
+    /// Replaces the normals by averages of the faces around each position. Faces meeting at
+    /// more than `angle_threshold` radians keep separate normals, splitting the vertices along
+    /// those hard edges: 0 gives flat shading and PI smooths everything, keeping the vertices
+    /// as they are.
+    pub fn recompute_normals(&mut self, angle_threshold: f32) -> Result<(), MeshError> {
+        // Every corner of a position then gets the same normal, whatever the rounding of the
+        // angles between its faces, so no vertex is split.
+        let smooth_all = angle_threshold >= PI;
+        let min_cos = angle_threshold.cos();
+        let vertex_count = self.vertices.len();
+        if let Some(&index) = self
+            .indices
+            .iter()
+            .find(|&&index| index as usize >= vertex_count)
+        {
+            return Err(MeshError::IndexOutOfRange {
+                index,
+                vertex_count,
+            });
+        }
+        // A trailing incomplete triangle is dropped.
+        let corners = &self.indices[..self.indices.len() / 3 * 3];
+        // Not normalized, larger faces weigh more in the average.
+        let face_normals: Vec<Vector3<f32>> = corners
+            .chunks_exact(3)
+            .map(|triangle| {
+                let [p0, p1, p2] = [
+                    self.vertices[triangle[0] as usize].position,
+                    self.vertices[triangle[1] as usize].position,
+                    self.vertices[triangle[2] as usize].position,
+                ];
+                (p1 - p0).cross(p2 - p0)
+            })
+            .collect();
+        let unit = |normal: Vector3<f32>| {
+            if normal.magnitude2() > 0.0 {
+                normal.normalize()
+            } else {
+                Vector3::zero()
+            }
+        };
+        // Corners sharing a position, whatever their normal and UV.
+        let mut corners_at: HashMap<[u32; 3], Vec<usize>> = HashMap::new();
+        for (corner, index) in corners.iter().enumerate() {
+            let position = self.vertices[*index as usize].position;
+            corners_at
+                .entry(position_key(position))
+                .or_default()
+                .push(corner);
+        }
+
+        // The vertices keep their order, the first corner of each one sets its normal and the
+        // corners needing another normal get a copy appended.
+        let mut vertices = self.vertices.clone();
+        let mut skin = self.skin.clone();
+        let mut indices = Vec::with_capacity(corners.len());
+        let mut normal_set = vec![false; self.vertices.len()];
+        let mut copies = HashMap::new();
+        for (corner, index) in corners.iter().enumerate() {
+            let original = self.vertices[*index as usize];
+            let face = unit(face_normals[corner / 3]);
+            let normal = corners_at[&position_key(original.position)]
+                .iter()
+                .map(|other| face_normals[other / 3])
+                .filter(|other| smooth_all || unit(*other).dot(face) >= min_cos)
+                .fold(Vector3::zero(), |sum, other| sum + other);
+            let normal = if normal.magnitude2() > 0.0 {
+                normal.normalize()
+            } else if face.magnitude2() > 0.0 && !smooth_all {
+                face
+            } else {
+                original.normal
+            };
+            let first = *index as usize;
+            let vertex = if !normal_set[first] {
+                normal_set[first] = true;
+                vertices[first].normal = normal;
+                *index
+            } else if position_key(vertices[first].normal) == position_key(normal) {
+                *index
+            } else {
+                *copies
+                    .entry((*index, position_key(normal)))
+                    .or_insert_with(|| {
+                        vertices.push(Vertex { normal, ..original });
+                        if let Some(weights) = self.skin.get(first) {
+                            skin.push(*weights);
+                        }
+                        vertices.len() as u32 - 1
+                    })
+            };
+            indices.push(vertex);
+        }
+        self.vertices = vertices;
+        self.skin = skin;
+        self.indices = indices;
+        Ok(())
+    }
+    /// Merges the vertices closer than `distance` that share their UV and normal, snapping the
+    /// others to a common position so seams stay closed, and drops the triangles collapsed by
+    /// it. Returns how many vertices were removed.
+    pub fn weld(&mut self, distance: f32) -> usize {
+        let cell_size = distance.max(f32::EPSILON);
+        let cell = |position: Vector3<f32>| {
+            [
+                (position.x / cell_size).floor() as i32,
+                (position.y / cell_size).floor() as i32,
+                (position.z / cell_size).floor() as i32,
+            ]
+        };
+        // First position of each group of close positions.
+        let mut points: Vec<Vector3<f32>> = Vec::new();
+        let mut points_in_cell: HashMap<[i32; 3], Vec<usize>> = HashMap::new();
+        let mut point_of_vertex = Vec::with_capacity(self.vertices.len());
+        for vertex in &self.vertices {
+            let position = vertex.position;
+            let [x, y, z] = cell(position);
+            let mut found = None;
+            'search: for dx in -1..=1 {
+                for dy in -1..=1 {
+                    for dz in -1..=1 {
+                        let neighbours = match points_in_cell.get(&[x + dx, y + dy, z + dz]) {
+                            Some(neighbours) => neighbours,
+                            None => continue,
+                        };
+                        for point in neighbours {
+                            if points[*point].distance(position) <= distance {
+                                found = Some(*point);
+                                break 'search;
+                            }
+                        }
+                    }
+                }
+            }
+            let point = found.unwrap_or_else(|| {
+                points.push(position);
+                points_in_cell
+                    .entry([x, y, z])
+                    .or_default()
+                    .push(points.len() - 1);
+                points.len() - 1
+            });
+            point_of_vertex.push(point);
+        }
+
+        let mut vertices = Vec::new();
+        let mut skin = Vec::new();
+        let mut vertex_of = HashMap::new();
+        let remap: Vec<u32> = self
+            .vertices
+            .iter()
+            .enumerate()
+            .map(|(index, vertex)| {
+                let point = point_of_vertex[index];
+                let key = (
+                    point,
+                    [vertex.uv.x.to_bits(), vertex.uv.y.to_bits()],
+                    position_key(vertex.normal),
+                );
+                *vertex_of.entry(key).or_insert_with(|| {
+                    vertices.push(Vertex {
+                        position: points[point],
+                        ..*vertex
+                    });
+                    if let Some(weights) = self.skin.get(index) {
+                        skin.push(*weights);
+                    }
+                    vertices.len() as u32 - 1
+                })
+            })
+            .collect();
+        let mut indices = Vec::with_capacity(self.indices.len());
+        for triangle in self.indices.chunks_exact(3) {
+            let corners: Option<Vec<u32>> = triangle
+                .iter()
+                .map(|index| remap.get(*index as usize).copied())
+                .collect();
+            let corners = match corners {
+                Some(corners) => corners,
+                None => continue,
+            };
+            let [a, b, c] = [
+                point_of_vertex[triangle[0] as usize],
+                point_of_vertex[triangle[1] as usize],
+                point_of_vertex[triangle[2] as usize],
+            ];
+            if a != b && b != c && a != c {
+                indices.extend(corners);
+            }
+        }
+        let removed = self.vertices.len() - vertices.len();
+        self.vertices = vertices;
+        self.skin = skin;
+        self.indices = indices;
+        removed
+    }
+    /// Turns the surface inside out, reversing the normals and the winding.
+    pub fn flip_normals(&mut self) {
+        for vertex in &mut self.vertices {
+            vertex.normal = -vertex.normal;
+        }
+        for triangle in self.indices.chunks_exact_mut(3) {
+            triangle.swap(1, 2);
+        }
+    }
+    /// Moves the vertices by a matrix, keeping the normals perpendicular to the surface and
+    /// the triangles facing out when it mirrors them.
+    pub fn transform(&mut self, matrix: &Matrix4<f32>) {
+        let linear = Matrix3::from_cols(
+            matrix.x.truncate(),
+            matrix.y.truncate(),
+            matrix.z.truncate(),
+        );
+        let normal_matrix = linear
+            .invert()
+            .map(|inverse| inverse.transpose())
+            .unwrap_or(linear);
+        for vertex in &mut self.vertices {
+            vertex.position = (matrix * vertex.position.extend(1.0)).truncate();
+            let normal = normal_matrix * vertex.normal;
+            if normal.magnitude2() > 0.0 {
+                vertex.normal = normal.normalize();
+            }
+        }
+        if linear.determinant() < 0.0 {
+            for triangle in self.indices.chunks_exact_mut(3) {
+                triangle.swap(1, 2);
+            }
+        }
+    }
+    /// Adds the triangles of another submesh, the skin is dropped unless both have one.
+    pub fn append(&mut self, other: &MeshData) {
+        let offset = self.vertices.len() as u32;
+        if self.is_skinned() && other.is_skinned() {
+            self.skin.extend_from_slice(&other.skin);
+        } else {
+            self.skin.clear();
+        }
+        self.vertices.extend_from_slice(&other.vertices);
+        self.indices
+            .extend(other.indices.iter().map(|index| index + offset));
+    }
+}
+
+impl Mesh {
+    /// Whether the mesh is deformed by a skeleton or blend shapes, which the operations moving
+    /// or merging vertices do not update.
+    pub fn is_deformable(&self) -> bool {
+        self.has_morph_targets() || self.submeshes.iter().any(SubMesh::is_skinned)
+    }
+    /// Whether a submesh has blend shapes, which only follow edits keeping the vertex order.
+    pub fn has_morph_targets(&self) -> bool {
+        self.submeshes
+            .iter()
+            .any(|submesh| submesh.morph_targets().is_some())
+    }
+    /// Applies an edit to the geometry of every level. The full detail submeshes keep the
+    /// result in memory, the levels of detail only keep it on the GPU.
+    pub fn edit<F: FnMut(&mut MeshData)>(&mut self, mut edit: F) {
+        let edited: Result<(), Infallible> = self.try_edit(|data| {
+            edit(data);
+            Ok(())
+        });
+        edited.unwrap_or(())
+    }
+    /// Same as `edit` for edits that may fail, the mesh is left as it was when one does.
+    pub fn try_edit<E, F>(&mut self, mut edit: F) -> Result<(), E>
+    where
+        F: FnMut(&mut MeshData) -> Result<(), E>,
+    {
+        let mut edited = Vec::new();
+        let levels = std::iter::once(self.submeshes.as_slice())
+            .chain(self.lods.iter().map(|lod| lod.submeshes.as_slice()));
+        for submeshes in levels {
+            for submesh in submeshes {
+                let mut data = submesh.to_data();
+                edit(&mut data)?;
+                edited.push(data);
+            }
+        }
+        let mut edited = edited.into_iter();
+        for (submesh, data) in self.submeshes.iter_mut().zip(&mut edited) {
+            submesh.set_data(data);
+        }
+        for lod in &mut self.lods {
+            for (submesh, data) in lod.submeshes.iter_mut().zip(&mut edited) {
+                submesh.set_data(data);
+                submesh.release_data();
+            }
+        }
+        Ok(())
+    }
+    /// Adds the submeshes and materials of another mesh, moved by `matrix`. Submeshes with the
+    /// same material are joined, and the levels of detail are dropped as they no longer match.
+    pub fn merge(&mut self, other: Mesh, matrix: &Matrix4<f32>) {
+        let material_offset = self.materials.len();
+        for submesh in &other.submeshes {
+            let mut data = submesh.to_data();
+            data.transform(matrix);
+            let material_index = submesh
+                .material_index
+                .filter(|index| *index < other.materials.len())
+                .map(|index| index + material_offset);
+            match self
+                .submeshes
+                .iter_mut()
+                .find(|submesh| submesh.material_index == material_index)
+            {
+                Some(target) => {
+                    let mut joined = target.to_data();
+                    joined.append(&data);
+                    target.set_data(joined);
+                }
+                None => self
+                    .submeshes
+                    .push(SubMesh::from_data(data, material_index)),
+            }
+        }
+        self.materials.extend(other.materials);
+        self.lods.clear();
+    }
+    /// Moves the submeshes of each material to a mesh of their own, with only that material.
+    /// Returns the material index of each new mesh, `None` for the submeshes without one.
+    pub fn split_by_material(self) -> Vec<(Option<usize>, Mesh)> {
+        let Mesh {
+            submeshes,
+            materials,
+            ..
+        } = self;
+        let mut meshes: Vec<(Option<usize>, Mesh)> = Vec::new();
+        for mut submesh in submeshes {
+            let material_index = submesh
+                .material_index
+                .filter(|index| *index < materials.len());
+            submesh.material_index = material_index.map(|_| 0);
+            match meshes
+                .iter_mut()
+                .find(|(index, _)| *index == material_index)
+            {
+                Some((_, mesh)) => mesh.submeshes.push(submesh),
+                None => {
+                    let mut mesh = Mesh::new(vec![submesh]);
+                    mesh.materials
+                        .extend(material_index.map(|index| materials[index].clone()));
+                    meshes.push((material_index, mesh));
+                }
+            }
+        }
+        meshes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{Deg, Vector2};
+
+    fn vertex(x: f32, y: f32, z: f32) -> Vertex {
+        Vertex {
+            position: Vector3::new(x, y, z),
+            normal: Vector3::unit_x(),
+            uv: Vector2::new(0.0, 0.0),
+        }
+    }
+
+    /// Two triangles sharing their edge from (1, 0, 0) to (1, 0, 1), folded at a right angle.
+    fn folded() -> MeshData {
+        MeshData::new(
+            vec![
+                vertex(0.0, 0.0, 0.0),
+                vertex(1.0, 0.0, 1.0),
+                vertex(1.0, 0.0, 0.0),
+                vertex(1.0, 1.0, 1.0),
+            ],
+            vec![0, 1, 2, 2, 1, 3],
+        )
+    }
+
+    fn close(a: Vector3<f32>, b: Vector3<f32>) -> bool {
+        (a - b).magnitude() < 0.0001
+    }
+
+    #[test]
+    fn smooth_normals_keep_the_vertices() {
+        let mut data = folded();
+        let positions: Vec<_> = data.vertices.iter().map(|vertex| vertex.position).collect();
+        data.recompute_normals(PI).unwrap();
+        assert_eq!(data.vertices.len(), 4);
+        assert_eq!(data.indices, vec![0, 1, 2, 2, 1, 3]);
+        for (vertex, position) in data.vertices.iter().zip(&positions) {
+            assert_eq!(vertex.position, *position);
+        }
+        assert!(close(data.vertices[0].normal, Vector3::unit_y()));
+        assert!(close(data.vertices[3].normal, -Vector3::unit_x()));
+        let shared = Vector3::new(-1.0, 1.0, 0.0).normalize();
+        assert!(close(data.vertices[1].normal, shared));
+        assert!(close(data.vertices[2].normal, shared));
+    }
+
+    #[test]
+    fn smooth_normals_keep_two_sided_vertices() {
+        // The same triangle facing both ways, its normals cancel out.
+        let mut data = MeshData::new(
+            vec![
+                vertex(0.0, 0.0, 0.0),
+                vertex(1.0, 0.0, 0.0),
+                vertex(0.0, 1.0, 0.0),
+            ],
+            vec![0, 1, 2, 0, 2, 1],
+        );
+        data.recompute_normals(PI).unwrap();
+        assert_eq!(data.vertices.len(), 3);
+        assert_eq!(data.indices, vec![0, 1, 2, 0, 2, 1]);
+        assert!(data
+            .vertices
+            .iter()
+            .all(|vertex| vertex.normal == Vector3::unit_x()));
+    }
+
+    #[test]
+    fn normals_of_invalid_indices() {
+        let mut data = folded();
+        data.indices[4] = 9;
+        let before = data.clone();
+        assert_eq!(
+            data.recompute_normals(PI),
+            Err(MeshError::IndexOutOfRange {
+                index: 9,
+                vertex_count: 4,
+            })
+        );
+        assert_eq!(data, before);
+    }
+
+    #[test]
+    fn weld_positions_maps_to_first() {
+        let mut data = folded();
+        data.vertices.push(vertex(1.0, 0.0, 1.0));
+        assert_eq!(weld_positions(&data.vertices), vec![0, 1, 2, 3, 1]);
+    }
+
+    #[test]
+    fn flat_normals_append_split_vertices() {
+        let mut data = folded();
+        data.recompute_normals(0.0).unwrap();
+        assert_eq!(data.vertices.len(), 6);
+        // The first triangle keeps the original vertices, the copies come after them.
+        assert_eq!(&data.indices[..4], &[0, 1, 2, 4]);
+        assert_eq!(data.indices[5], 3);
+        assert_eq!(data.vertices[4].position, data.vertices[2].position);
+        for index in &data.indices[..3] {
+            assert!(close(
+                data.vertices[*index as usize].normal,
+                Vector3::unit_y()
+            ));
+        }
+        for index in &data.indices[3..] {
+            assert!(close(
+                data.vertices[*index as usize].normal,
+                -Vector3::unit_x()
+            ));
+        }
+    }
+
+    #[test]
+    fn weld_merges_close_vertices() {
+        let mut data = MeshData::new(
+            vec![
+                vertex(0.0, 0.0, 0.0),
+                vertex(1.0, 0.0, 0.0),
+                vertex(0.0, 0.0, 1.0),
+                vertex(1.0, 0.0, 0.0001),
+                vertex(1.0, 0.0, 1.0),
+                vertex(0.0, 0.0, 1.0),
+            ],
+            vec![0, 1, 2, 3, 4, 5],
+        );
+        assert_eq!(data.weld(0.001), 2);
+        assert_eq!(data.vertices.len(), 4);
+        assert_eq!(data.indices, vec![0, 1, 2, 1, 3, 2]);
+
+        // Merging whole edges collapses the triangles, the far corner stays apart.
+        assert_eq!(data.weld(1.0), 2);
+        assert!(data.indices.is_empty());
+    }
+
+    #[test]
+    fn weld_keeps_seams() {
+        let mut seam = vertex(1.0, 0.0, 0.0001);
+        seam.uv = Vector2::new(0.5, 0.0);
+        let mut data = MeshData::new(vec![vertex(1.0, 0.0, 0.0), seam], vec![0, 1, 0]);
+        assert_eq!(data.weld(0.001), 0);
+        assert_eq!(data.vertices[0].position, data.vertices[1].position);
+    }
+
+    #[test]
+    fn transform_mirrored() {
+        let mut data = folded();
+        data.transform(&Matrix4::from_nonuniform_scale(-2.0, 1.0, 1.0));
+        assert_eq!(data.vertices[1].position, Vector3::new(-2.0, 0.0, 1.0));
+        assert_eq!(data.indices, vec![0, 2, 1, 2, 3, 1]);
+        assert!(close(data.vertices[0].normal, -Vector3::unit_x()));
+
+        let mut data = folded();
+        data.transform(
+            &(Matrix4::from_translation(Vector3::unit_y()) * Matrix4::from_angle_z(Deg(90.0))),
+        );
+        assert!(close(
+            data.vertices[1].position,
+            Vector3::new(0.0, 2.0, 1.0)
+        ));
+        assert!(close(data.vertices[0].normal, Vector3::unit_y()));
+        assert_eq!(data.indices, vec![0, 1, 2, 2, 1, 3]);
+    }
+}
@@ -0,0 +1,82 @@
+use cgmath::Vector3;
+use std::os::raw::c_void;
+
+/// Offsets of the vertices of a submesh at full weight of a blend shape.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MorphTarget {
+    pub position_deltas: Vec<Vector3<f32>>,
+    /// Empty when the target leaves the normals unchanged.
+    pub normal_deltas: Vec<Vector3<f32>>,
+}
+
+/// Deltas of every morph target of a submesh, in a buffer texture read by the vertex shader.
+/// Each vertex of each target takes two texels, its position delta and its normal delta.
+#[derive(Debug, PartialEq)]
+pub struct MorphTargetBuffer {
+    buffer: u32,
+    texture: u32,
+    target_count: usize,
+    vertex_count: usize,
+}
+
+impl MorphTargetBuffer {
+    /// Deltas missing from a target, or past `vertex_count`, are zero.
+    pub fn new(targets: &[MorphTarget], vertex_count: usize) -> Self {
+        let mut texels = vec![[0.0f32; 4]; targets.len() * vertex_count * 2];
+        for (index, target) in targets.iter().enumerate() {
+            let first = index * vertex_count * 2;
+            let positions = target.position_deltas.iter().take(vertex_count);
+            for (vertex, delta) in positions.enumerate() {
+                texels[first + vertex * 2] = [delta.x, delta.y, delta.z, 0.0];
+            }
+            let normals = target.normal_deltas.iter().take(vertex_count);
+            for (vertex, delta) in normals.enumerate() {
+                texels[first + vertex * 2 + 1] = [delta.x, delta.y, delta.z, 0.0];
+            }
+        }
+
+        let mut morph_buffer = MorphTargetBuffer {
+            buffer: 0,
+            texture: 0,
+            target_count: targets.len(),
+            vertex_count,
+        };
+        unsafe {
+            gl::GenBuffers(1, &mut morph_buffer.buffer);
+            gl::BindBuffer(gl::TEXTURE_BUFFER, morph_buffer.buffer);
+            gl::BufferData(
+                gl::TEXTURE_BUFFER,
+                (texels.len() * std::mem::size_of::<[f32; 4]>()) as gl::types::GLsizeiptr,
+                texels.as_ptr() as *const c_void,
+                gl::STATIC_DRAW,
+            );
+            gl::GenTextures(1, &mut morph_buffer.texture);
+            gl::BindTexture(gl::TEXTURE_BUFFER, morph_buffer.texture);
+            // RGB32F buffer textures need OpenGL 4.0, the fourth channel is padding.
+            gl::TexBuffer(gl::TEXTURE_BUFFER, gl::RGBA32F, morph_buffer.buffer);
+            gl::BindBuffer(gl::TEXTURE_BUFFER, 0);
+        }
+        morph_buffer
+    }
+    pub fn target_count(&self) -> usize {
+        self.target_count
+    }
+    pub fn vertex_count(&self) -> usize {
+        self.vertex_count
+    }
+    pub fn bind(&self, texture_unit: u32) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + texture_unit);
+            gl::BindTexture(gl::TEXTURE_BUFFER, self.texture);
+        }
+    }
+}
+
+impl Drop for MorphTargetBuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.texture);
+            gl::DeleteBuffers(1, &self.buffer);
+        }
+    }
+}
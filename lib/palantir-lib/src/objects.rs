@@ -1,8 +1,26 @@
+use crate::{BoundingSphere, Frustum};
 use cgmath::{Matrix4, Vector3};
 
 pub trait TCamera {
     fn matrix(&self) -> Matrix4<f32>;
     fn projection_matrix(&self) -> Matrix4<f32>;
+    /// World space volume seen by the camera.
+    fn frustum(&self) -> Frustum {
+        Frustum::from_matrix(&(self.projection_matrix() * self.matrix()))
+    }
+    /// Height of a world space sphere on screen, as a fraction of the view height.
+    /// Infinite when the camera is inside the sphere.
+    fn screen_size(&self, sphere: &BoundingSphere) -> f32 {
+        let projection = self.projection_matrix();
+        let center = self.matrix() * sphere.center.extend(1.0);
+        // Distance along the view axis for perspective projections, 1 for orthographic ones.
+        let w = (projection * center).w;
+        let perspective = projection.w.w == 0.0;
+        if perspective && w <= sphere.radius {
+            return f32::INFINITY;
+        }
+        sphere.radius * projection.y.y / w
+    }
 }
 
 pub trait TLight {
@@ -0,0 +1,292 @@
+use crate::{Framebuffer, FramebufferError, FramebufferFormat, ShaderProgram, VertexArray};
+use cgmath::Vector2;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Tonemapping {
+    None,
+    Reinhard,
+    Aces,
+    Filmic,
+}
+
+impl Tonemapping {
+    pub const ALL: [Tonemapping; 4] = [
+        Tonemapping::None,
+        Tonemapping::Reinhard,
+        Tonemapping::Aces,
+        Tonemapping::Filmic,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Tonemapping::None => "None",
+            Tonemapping::Reinhard => "Reinhard",
+            Tonemapping::Aces => "ACES",
+            Tonemapping::Filmic => "Filmic",
+        }
+    }
+    fn shader_index(self) -> i32 {
+        match self {
+            Tonemapping::None => 0,
+            Tonemapping::Reinhard => 1,
+            Tonemapping::Aces => 2,
+            Tonemapping::Filmic => 3,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BloomSettings {
+    pub enabled: bool,
+    pub threshold: f32,
+    pub intensity: f32,
+    pub iterations: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TonemappingSettings {
+    pub enabled: bool,
+    pub exposure: f32,
+    pub operator: Tonemapping,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VignetteSettings {
+    pub enabled: bool,
+    pub intensity: f32,
+    pub smoothness: f32,
+}
+
+/// Settings of every effect of the stack, in the order they are applied.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PostProcessSettings {
+    pub bloom: BloomSettings,
+    pub tonemapping: TonemappingSettings,
+    pub vignette: VignetteSettings,
+    pub srgb_output: bool,
+    pub fxaa: bool,
+}
+
+impl Default for PostProcessSettings {
+    fn default() -> Self {
+        PostProcessSettings {
+            bloom: BloomSettings {
+                enabled: false,
+                threshold: 1.0,
+                intensity: 0.5,
+                iterations: 5,
+            },
+            tonemapping: TonemappingSettings {
+                enabled: true,
+                exposure: 1.0,
+                operator: Tonemapping::Aces,
+            },
+            vignette: VignetteSettings {
+                enabled: false,
+                intensity: 0.3,
+                smoothness: 0.5,
+            },
+            srgb_output: true,
+            fxaa: true,
+        }
+    }
+}
+
+pub struct PostProcessShaders {
+    pub bloom_extract: ShaderProgram,
+    pub blur: ShaderProgram,
+    pub composite: ShaderProgram,
+    pub fxaa: ShaderProgram,
+}
+
+/// Renders the scene to an HDR target and resolves it through the post-process effects.
+///
+/// The scene is drawn between `begin` and `end`.
+pub struct PostProcessStack {
+    pub settings: PostProcessSettings,
+    shaders: PostProcessShaders,
+    hdr_target: Framebuffer,
+    ldr_target: Framebuffer,
+    bloom_targets: [Framebuffer; 2],
+    fullscreen_vertex_array: VertexArray,
+}
+
+impl PostProcessStack {
+    pub fn new(
+        width: u32,
+        height: u32,
+        shaders: PostProcessShaders,
+    ) -> Result<Self, FramebufferError> {
+        let (hdr_target, ldr_target, bloom_targets) = create_targets(width, height)?;
+        Ok(PostProcessStack {
+            settings: PostProcessSettings::default(),
+            shaders,
+            hdr_target,
+            ldr_target,
+            bloom_targets,
+            fullscreen_vertex_array: VertexArray::new(),
+        })
+    }
+    pub fn resize(&mut self, width: u32, height: u32) -> Result<(), FramebufferError> {
+        if width == self.hdr_target.width() && height == self.hdr_target.height() {
+            return Ok(());
+        }
+        let (hdr_target, ldr_target, bloom_targets) = create_targets(width, height)?;
+        self.hdr_target = hdr_target;
+        self.ldr_target = ldr_target;
+        self.bloom_targets = bloom_targets;
+        Ok(())
+    }
+    pub fn width(&self) -> u32 {
+        self.hdr_target.width()
+    }
+    pub fn height(&self) -> u32 {
+        self.hdr_target.height()
+    }
+    /// The linear HDR image of the last rendered frame, before any effect is applied.
+    pub fn hdr_target(&self) -> &Framebuffer {
+        &self.hdr_target
+    }
+    /// Binds the HDR target, the scene should be drawn after this call.
+    pub fn begin(&self) {
+        self.hdr_target.bind();
+    }
+    /// Applies the effects and writes the result to `output`, or to the window when `None`.
+    pub fn end(&mut self, output: Option<&Framebuffer>) {
+        unsafe {
+            gl::Disable(gl::DEPTH_TEST);
+        }
+        self.fullscreen_vertex_array.bind();
+
+        let bloom = self.settings.bloom;
+        if bloom.enabled {
+            self.render_bloom();
+        }
+
+        if self.settings.fxaa {
+            self.ldr_target.bind();
+        } else {
+            bind_output(output, self.width(), self.height());
+        }
+        self.render_composite();
+
+        if self.settings.fxaa {
+            bind_output(output, self.width(), self.height());
+            let shader = &mut self.shaders.fxaa;
+            shader.bind();
+            self.ldr_target.bind_color_texture(0);
+            shader.set_uniform_int(String::from("u_image"), 0);
+            shader.set_uniform_vector2(String::from("u_texel_size"), &texel_size(&self.ldr_target));
+            draw_fullscreen_triangle();
+        }
+
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+        }
+    }
+    fn render_bloom(&mut self) {
+        let bloom = self.settings.bloom;
+
+        self.bloom_targets[0].bind();
+        let shader = &mut self.shaders.bloom_extract;
+        shader.bind();
+        self.hdr_target.bind_color_texture(0);
+        shader.set_uniform_int(String::from("u_image"), 0);
+        shader.set_uniform_float(String::from("u_threshold"), bloom.threshold);
+        draw_fullscreen_triangle();
+
+        let shader = &mut self.shaders.blur;
+        shader.bind();
+        shader.set_uniform_int(String::from("u_image"), 0);
+        let texel_size = texel_size(&self.bloom_targets[0]);
+        for _ in 0..bloom.iterations {
+            for (source, direction) in [(0, Vector2::new(1.0, 0.0)), (1, Vector2::new(0.0, 1.0))]
+                .iter()
+                .cloned()
+            {
+                self.bloom_targets[1 - source].bind();
+                self.bloom_targets[source].bind_color_texture(0);
+                shader.set_uniform_vector2(
+                    String::from("u_direction"),
+                    &Vector2::new(direction.x * texel_size.x, direction.y * texel_size.y),
+                );
+                draw_fullscreen_triangle();
+            }
+        }
+    }
+    fn render_composite(&mut self) {
+        let settings = self.settings;
+        let shader = &mut self.shaders.composite;
+        shader.bind();
+
+        self.hdr_target.bind_color_texture(0);
+        shader.set_uniform_int(String::from("u_image"), 0);
+        self.bloom_targets[0].bind_color_texture(1);
+        shader.set_uniform_int(String::from("u_bloom"), 1);
+        let bloom_intensity = if settings.bloom.enabled {
+            settings.bloom.intensity
+        } else {
+            0.0
+        };
+        shader.set_uniform_float(String::from("u_bloom_intensity"), bloom_intensity);
+
+        let tonemapping = settings.tonemapping;
+        let (exposure, operator) = if tonemapping.enabled {
+            (tonemapping.exposure, tonemapping.operator)
+        } else {
+            (1.0, Tonemapping::None)
+        };
+        shader.set_uniform_float(String::from("u_exposure"), exposure);
+        shader.set_uniform_int(String::from("u_tonemapping"), operator.shader_index());
+
+        let vignette_intensity = if settings.vignette.enabled {
+            settings.vignette.intensity
+        } else {
+            0.0
+        };
+        shader.set_uniform_float(String::from("u_vignette_intensity"), vignette_intensity);
+        shader.set_uniform_float(
+            String::from("u_vignette_smoothness"),
+            settings.vignette.smoothness,
+        );
+        shader.set_uniform_bool(String::from("u_srgb_output"), settings.srgb_output);
+
+        draw_fullscreen_triangle();
+    }
+}
+
+type Targets = (Framebuffer, Framebuffer, [Framebuffer; 2]);
+
+fn create_targets(width: u32, height: u32) -> Result<Targets, FramebufferError> {
+    let hdr_target = Framebuffer::with_format(width, height, FramebufferFormat::Rgba16F)?;
+    let ldr_target = Framebuffer::with_format(width, height, FramebufferFormat::Rgba8)?;
+    let bloom_targets = [
+        Framebuffer::with_format(width / 2, height / 2, FramebufferFormat::Rgba16F)?,
+        Framebuffer::with_format(width / 2, height / 2, FramebufferFormat::Rgba16F)?,
+    ];
+    Ok((hdr_target, ldr_target, bloom_targets))
+}
+
+fn bind_output(output: Option<&Framebuffer>, width: u32, height: u32) {
+    match output {
+        Some(framebuffer) => framebuffer.bind(),
+        None => unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::Viewport(0, 0, width as i32, height as i32);
+        },
+    }
+}
+
+fn texel_size(framebuffer: &Framebuffer) -> Vector2<f32> {
+    Vector2::new(
+        1.0 / framebuffer.width() as f32,
+        1.0 / framebuffer.height() as f32,
+    )
+}
+
+/// Draws a triangle covering the whole viewport, its vertices are generated in the vertex shader.
+pub fn draw_fullscreen_triangle() {
+    unsafe {
+        gl::DrawArrays(gl::TRIANGLES, 0, 3);
+    }
+}
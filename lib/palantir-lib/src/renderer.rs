@@ -1,22 +1,175 @@
-use crate::{Material, Mesh, ShaderProgram, TCamera, TLight};
+use crate::transparency::WeightedBlended;
+use crate::{
+    Environment, Material, Mesh, ShaderProgram, Shading, ShadingMode, ShadingPass, Skybox, TCamera,
+    TLight, TransparencyMode,
+};
+use cgmath::prelude::*;
 use cgmath::{Matrix4, Vector3};
 use std::sync::Arc;
 
+/// Geometry of one level of detail of a mesh.
+#[derive(Clone, Copy)]
+struct MeshLevel<'a> {
+    mesh: &'a Mesh,
+    level: usize,
+}
+
+/// Submeshes drawn by the next draw calls, by the blend mode of their material.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MaterialPass {
+    All,
+    Opaque,
+    Transparent,
+}
+
+/// Size of the joint matrix array of the skinned shader variants.
+pub const MAX_JOINTS: usize = 128;
+/// Size of the morph weight array of the mesh shaders.
+pub const MAX_MORPH_TARGETS: usize = 64;
+/// Texture unit of the morph target deltas, after the ones of the material and environment.
+const MORPH_TEXTURE_UNIT: u32 = 4;
+
 pub struct Renderer {
     shader: ShaderProgram,
+    /// Variant of `shader` compiled with `SKINNED` defined.
+    skinned_shader: Option<ShaderProgram>,
+    joint_matrices: Vec<Matrix4<f32>>,
+    morph_weights: Vec<f32>,
     default_material: Arc<Material>,
+    environment: Option<Environment>,
+    skybox: Option<Skybox>,
+    shading: Option<Shading>,
+    material_pass: MaterialPass,
+    transparency_mode: TransparencyMode,
+    active_transparency_mode: TransparencyMode,
+    weighted_blended: Option<WeightedBlended>,
 }
 
 impl Renderer {
     pub fn new(shader: ShaderProgram) -> Self {
         Renderer {
             shader,
+            skinned_shader: None,
+            joint_matrices: Vec::new(),
+            morph_weights: Vec::new(),
             default_material: Arc::new(Material::new(Vector3::new(1.0, 0.0, 1.0), None)),
+            environment: None,
+            skybox: None,
+            shading: None,
+            material_pass: MaterialPass::All,
+            transparency_mode: TransparencyMode::Sorted,
+            active_transparency_mode: TransparencyMode::Sorted,
+            weighted_blended: None,
+        }
+    }
+    pub fn environment(&self) -> Option<&Environment> {
+        self.environment.as_ref()
+    }
+    pub fn environment_mut(&mut self) -> Option<&mut Environment> {
+        self.environment.as_mut()
+    }
+    pub fn set_environment(&mut self, environment: Option<Environment>) {
+        self.environment = environment;
+    }
+    pub fn set_skybox(&mut self, skybox: Skybox) {
+        self.skybox = Some(skybox);
+    }
+    /// Draws the environment as background, should be called after the opaque geometry.
+    pub fn draw_skybox<A: TCamera>(&mut self, camera: &A) {
+        if let (Some(environment), Some(skybox)) = (&self.environment, &mut self.skybox) {
+            if environment.show_background {
+                skybox.draw(environment, camera);
+            }
+        }
+    }
+    pub fn shading_mut(&mut self) -> Option<&mut Shading> {
+        self.shading.as_mut()
+    }
+    /// Enables the shading modes other than `ShadingMode::Solid`.
+    pub fn set_shading(&mut self, shading: Shading) {
+        self.shading = Some(shading);
+    }
+    /// Enables skinning, skinned submeshes are drawn in their rest pose without it.
+    pub fn set_skinned_shader(&mut self, shader: ShaderProgram) {
+        self.skinned_shader = Some(shader);
+    }
+    /// Skinning matrices of the joints, used by the next skinned submeshes drawn.
+    pub fn set_joint_matrices(&mut self, matrices: &[Matrix4<f32>]) {
+        self.joint_matrices.clear();
+        self.joint_matrices
+            .extend_from_slice(&matrices[..matrices.len().min(MAX_JOINTS)]);
+    }
+    /// Weights of the morph targets of the next meshes drawn, each submesh reads them from
+    /// its `morph_weight_offset`.
+    pub fn set_morph_weights(&mut self, weights: &[f32]) {
+        self.morph_weights.clear();
+        self.morph_weights.extend_from_slice(weights);
+    }
+    /// Enables `TransparencyMode::WeightedBlended`, with the shader compositing its result.
+    pub fn set_weighted_blended_shader(&mut self, composite_shader: ShaderProgram) {
+        self.weighted_blended = Some(WeightedBlended::new(composite_shader));
+    }
+    pub fn transparency_mode(&self) -> TransparencyMode {
+        self.transparency_mode
+    }
+    pub fn set_transparency_mode(&mut self, transparency_mode: TransparencyMode) {
+        self.transparency_mode = transparency_mode;
+    }
+    /// Mode of the last transparent pass, `TransparencyMode::Sorted` when weighted blended
+    /// could not share the depth of the framebuffer, the window's one for instance.
+    pub fn active_transparency_mode(&self) -> TransparencyMode {
+        self.active_transparency_mode
+    }
+    /// Only draws the submeshes with an opaque or masked material from now on.
+    pub fn begin_opaque_pass(&mut self) {
+        self.material_pass = MaterialPass::Opaque;
+    }
+    /// Only draws the submeshes with a blended material from now on, over the opaque ones and
+    /// without writing depth. With `TransparencyMode::Sorted` the meshes should be drawn back
+    /// to front. The other shading modes draw nothing in this pass.
+    pub fn begin_transparent_pass(&mut self) {
+        self.material_pass = MaterialPass::Transparent;
+        unsafe {
+            gl::Enable(gl::BLEND);
+            gl::DepthMask(gl::FALSE);
+        }
+        let weighted_blended = match (self.transparency_mode, &mut self.weighted_blended) {
+            (TransparencyMode::WeightedBlended, Some(weighted_blended)) => weighted_blended.begin(),
+            _ => false,
+        };
+        self.active_transparency_mode = if weighted_blended {
+            TransparencyMode::WeightedBlended
+        } else {
+            TransparencyMode::Sorted
+        };
+        if !weighted_blended {
+            unsafe {
+                gl::BlendFuncSeparate(
+                    gl::SRC_ALPHA,
+                    gl::ONE_MINUS_SRC_ALPHA,
+                    gl::ONE,
+                    gl::ONE_MINUS_SRC_ALPHA,
+                );
+            }
+        }
+    }
+    /// Composites the weighted blended surfaces and draws every submesh again from now on.
+    pub fn end_transparent_pass(&mut self) {
+        if let Some(weighted_blended) = &mut self.weighted_blended {
+            weighted_blended.end();
+        }
+        unsafe {
+            gl::DepthMask(gl::TRUE);
+            gl::Disable(gl::BLEND);
         }
+        self.material_pass = MaterialPass::All;
     }
     pub fn clear(&self, r: f32, g: f32, b: f32) {
+        self.clear_with_alpha(r, g, b, 1.0);
+    }
+    pub fn clear_with_alpha(&self, r: f32, g: f32, b: f32, a: f32) {
         unsafe {
-            gl::ClearColor(r, g, b, 1.0);
+            gl::ClearColor(r, g, b, a);
             gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
         }
     }
@@ -28,36 +181,184 @@ impl Renderer {
         light: &B,
         draw_type: u32,
     ) {
-        for submesh in &mesh.submeshes {
-            let material;
-            if mesh.materials.is_empty() {
-                material = Arc::clone(&self.default_material);
-            } else {
-                match submesh.material_index {
-                    Some(i) => material = Arc::clone(&mesh.materials[i]),
-                    None => material = Arc::clone(&self.default_material),
+        let geometry = MeshLevel { mesh, level: 0 };
+        self.draw_submeshes(None, matrix, geometry, camera, light, draw_type);
+    }
+    /// Draws a level of detail of the mesh in the given shading mode, falls back to solid
+    /// shading when `set_shading` has not been called.
+    pub fn draw_mesh_shaded<A: TCamera, B: TLight>(
+        &mut self,
+        matrix: &Matrix4<f32>,
+        mesh: &Mesh,
+        level: usize,
+        camera: &A,
+        light: &B,
+        mode: ShadingMode,
+    ) {
+        let geometry = MeshLevel { mesh, level };
+        let triangles = gl::TRIANGLES;
+        if self.shading.is_none() {
+            self.draw_submeshes(None, matrix, geometry, camera, light, triangles);
+            return;
+        }
+        match mode {
+            ShadingMode::Solid => {
+                self.draw_submeshes(None, matrix, geometry, camera, light, triangles)
+            }
+            ShadingMode::Wireframe => self.draw_wireframe(matrix, geometry, camera, light),
+            ShadingMode::WireframeOnShaded => {
+                self.draw_submeshes(None, matrix, geometry, camera, light, triangles);
+                self.draw_wireframe(matrix, geometry, camera, light);
+            }
+            ShadingMode::VertexNormals => {
+                self.draw_submeshes(None, matrix, geometry, camera, light, triangles);
+                let pass = Some(ShadingPass::Normals);
+                self.draw_submeshes(pass, matrix, geometry, camera, light, triangles);
+            }
+            _ => {
+                let pass = mode.debug_index().map(ShadingPass::Debug);
+                self.draw_submeshes(pass, matrix, geometry, camera, light, triangles);
+            }
+        }
+    }
+    fn draw_wireframe<A: TCamera, B: TLight>(
+        &mut self,
+        matrix: &Matrix4<f32>,
+        geometry: MeshLevel,
+        camera: &A,
+        light: &B,
+    ) {
+        unsafe {
+            gl::PolygonMode(gl::FRONT_AND_BACK, gl::LINE);
+            // Pulls the lines in front of the shaded surface they are drawn over.
+            gl::Enable(gl::POLYGON_OFFSET_LINE);
+            gl::PolygonOffset(-1.0, -1.0);
+        }
+        let pass = Some(ShadingPass::Wireframe);
+        self.draw_submeshes(pass, matrix, geometry, camera, light, gl::TRIANGLES);
+        unsafe {
+            gl::Disable(gl::POLYGON_OFFSET_LINE);
+            gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL);
+        }
+    }
+    /// Draws every submesh with the lit shader, or with the shader of `pass`.
+    fn draw_submeshes<A: TCamera, B: TLight>(
+        &mut self,
+        pass: Option<ShadingPass>,
+        matrix: &Matrix4<f32>,
+        geometry: MeshLevel,
+        camera: &A,
+        light: &B,
+        draw_type: u32,
+    ) {
+        let Renderer {
+            shader: lit_shader,
+            skinned_shader,
+            joint_matrices,
+            morph_weights,
+            default_material,
+            environment,
+            shading,
+            material_pass,
+            weighted_blended,
+            ..
+        } = self;
+        if pass.is_some() && shading.is_none() {
+            return;
+        }
+
+        let weighted_blended = matches!(weighted_blended, Some(target) if target.is_active());
+
+        let mesh = geometry.mesh;
+        for submesh in mesh.lod_submeshes(geometry.level) {
+            let material = match submesh.material_index {
+                Some(i) if i < mesh.materials.len() => Arc::clone(&mesh.materials[i]),
+                _ => Arc::clone(default_material),
+            };
+            // The shading passes draw every surface as opaque.
+            let transparent = pass.is_none() && material.is_transparent();
+            let skipped = match material_pass {
+                MaterialPass::All => false,
+                MaterialPass::Opaque => transparent,
+                MaterialPass::Transparent => !transparent,
+            };
+            if skipped {
+                continue;
+            }
+            let skinned = submesh.is_skinned() && !joint_matrices.is_empty();
+            let shader: &mut ShaderProgram = match (pass, shading.as_mut()) {
+                (Some(pass), Some(shading)) => {
+                    let shader = shading.bind_pass(pass, skinned);
+                    if let ShadingPass::Debug(_) = pass {
+                        shader.set_uniform_int(String::from("u_lod_level"), geometry.level as i32);
+                    }
+                    shader
                 }
+                _ => match skinned_shader.as_mut() {
+                    Some(skinned_shader) if skinned => {
+                        skinned_shader.bind();
+                        skinned_shader
+                    }
+                    _ => {
+                        lit_shader.bind();
+                        lit_shader
+                    }
+                },
+            };
+            if skinned {
+                shader.set_uniform_matrix4_array(String::from("u_joint_matrices"), joint_matrices);
             }
-            self.shader.bind();
-            material.send_to_shader(&mut self.shader);
+            material.send_to_shader(shader);
 
-            self.shader
-                .set_uniform_matrix4(String::from("u_model"), &matrix);
-            self.shader
-                .set_uniform_matrix4(String::from("u_view"), &camera.matrix());
-            self.shader
-                .set_uniform_matrix4(String::from("u_projection"), &camera.projection_matrix());
+            shader.set_uniform_matrix4(String::from("u_model"), matrix);
+            shader.set_uniform_matrix4(String::from("u_view"), &camera.matrix());
+            shader.set_uniform_matrix4(String::from("u_projection"), &camera.projection_matrix());
+            if let Some(camera_matrix) = camera.matrix().inverse_transform() {
+                shader.set_uniform_vector3(
+                    String::from("u_camera_position"),
+                    &camera_matrix.w.truncate(),
+                );
+            }
+            if pass.is_none() {
+                match environment {
+                    Some(environment) => environment.send_to_shader(shader),
+                    None => shader.set_uniform_bool(String::from("u_use_environment"), false),
+                }
+                shader.set_uniform_bool(String::from("u_weighted_blended"), weighted_blended);
+            }
+            // Set even without targets, samplers of different types cannot share a unit.
+            shader.set_uniform_int(String::from("u_morph_deltas"), MORPH_TEXTURE_UNIT as i32);
+            let weights = morph_weights
+                .get(submesh.morph_weight_offset()..)
+                .unwrap_or_default();
+            let target_count = match submesh.morph_targets() {
+                Some(targets) if !weights.is_empty() => {
+                    let count = targets
+                        .target_count()
+                        .min(weights.len())
+                        .min(MAX_MORPH_TARGETS);
+                    targets.bind(MORPH_TEXTURE_UNIT);
+                    shader.set_uniform_int(
+                        String::from("u_morph_vertex_count"),
+                        targets.vertex_count() as i32,
+                    );
+                    shader.set_uniform_float_array(
+                        String::from("u_morph_weights"),
+                        &weights[..count],
+                    );
+                    count
+                }
+                _ => 0,
+            };
+            shader.set_uniform_int(String::from("u_morph_target_count"), target_count as i32);
 
-            self.shader
-                .set_uniform_vector3(String::from("u_light_direction"), &light.direction());
-            self.shader
-                .set_uniform_vector3(String::from("u_light_color"), &light.color());
-            self.shader.set_uniform_float(
+            shader.set_uniform_vector3(String::from("u_light_direction"), &light.direction());
+            shader.set_uniform_vector3(String::from("u_light_color"), &light.color());
+            shader.set_uniform_float(
                 String::from("u_light_ambient_strength"),
                 light.ambient_strength(),
             );
-            self.shader
-                .set_uniform_float(String::from("u_light_power"), light.power());
+            shader.set_uniform_float(String::from("u_light_power"), light.power());
 
             submesh.vertex_array().bind();
             submesh.index_buffer().bind();
@@ -65,7 +366,7 @@ impl Renderer {
                 gl::DrawElements(
                     draw_type,
                     submesh.index_buffer().count as i32,
-                    gl::UNSIGNED_INT,
+                    submesh.index_buffer().gl_type(),
                     std::ptr::null_mut(),
                 )
             }
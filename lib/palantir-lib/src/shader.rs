@@ -1,5 +1,5 @@
 use cgmath::prelude::*;
-use cgmath::{Matrix4, Vector3};
+use cgmath::{Matrix4, Vector2, Vector3};
 use std::collections::HashMap;
 use std::ffi::{CStr, CString};
 use std::fs::File;
@@ -18,13 +18,13 @@ impl std::fmt::Display for ShaderError {
             ShaderError::CompileError { path, message } => write!(
                 f,
                 "Shader {} failed to compile: {}",
-                path.as_path().display().to_string(),
+                path.as_path().display(),
                 message
             ),
             ShaderError::LinkError { path, message } => write!(
                 f,
                 "Program {} failed to link shaders: {}",
-                path.as_path().display().to_string(),
+                path.as_path().display(),
                 message
             ),
         }
@@ -63,26 +63,48 @@ pub struct ShaderProgram {
 
 impl ShaderProgram {
     pub fn from_path(path: PathBuf) -> Result<Self, ShaderError> {
+        ShaderProgram::from_path_with_defines(path, &[])
+    }
+    /// Compiles a variant of the shader, with `#define` lines for `defines` added after the
+    /// `#version` line of every stage.
+    ///
+    /// `#include "file"` lines are replaced by the content of the file, relative to the shader.
+    pub fn from_path_with_defines(path: PathBuf, defines: &[&str]) -> Result<Self, ShaderError> {
         let mut shader_sources = HashMap::new();
         shader_sources.insert("vertex", String::new());
         shader_sources.insert("fragment", String::new());
+        shader_sources.insert("geometry", String::new());
         let file = File::open(path.clone()).expect("Couldn't open the file");
 
         let mut shader_type = None;
-        for line in BufReader::new(file).lines() {
-            if let Ok(content) = line {
-                if content == "#vertex" {
-                    shader_type = Some("vertex")
-                } else if content == "#fragment" {
-                    shader_type = Some("fragment")
-                } else {
-                    match shader_type {
-                        Some(value) => {
-                            let current_content = shader_sources.get_mut(&value).unwrap();
-                            current_content.push_str(&content[..]);
-                            current_content.push_str("\n");
+        for content in BufReader::new(file).lines().map_while(Result::ok) {
+            if content == "#vertex" {
+                shader_type = Some("vertex")
+            } else if content == "#fragment" {
+                shader_type = Some("fragment")
+            } else if content == "#geometry" {
+                shader_type = Some("geometry")
+            } else if let Some(value) = shader_type {
+                let current_content = shader_sources.get_mut(&value).unwrap();
+                if let Some(name) = content.strip_prefix("#include ") {
+                    // Relative to the including file, spliced in place.
+                    let name = name.trim().trim_matches('"');
+                    let include_path = path.with_file_name(name);
+                    let included = std::fs::read_to_string(&include_path).map_err(|err| {
+                        ShaderError::CompileError {
+                            path: path.clone(),
+                            message: format!("Cannot include {}: {}", include_path.display(), err),
                         }
-                        None => (),
+                    })?;
+                    current_content.push_str(&included);
+                    current_content.push('\n');
+                    continue;
+                }
+                current_content.push_str(&content[..]);
+                current_content.push('\n');
+                if content.starts_with("#version") {
+                    for define in defines {
+                        current_content.push_str(&format!("#define {}\n", define));
                     }
                 }
             }
@@ -95,11 +117,16 @@ impl ShaderProgram {
         let fragment_cstr = CString::new(&fragment_source[..]).unwrap();
         let fragment_shader = Shader::from_source(&fragment_cstr, gl::FRAGMENT_SHADER).unwrap();
 
-        let shaders = [vertex_shader, fragment_shader];
-        ShaderProgram::from_shaders(&shaders).map_err(|message| ShaderError::LinkError {
-            path: path,
-            message,
-        })
+        let mut shaders = vec![vertex_shader, fragment_shader];
+        // The geometry stage is optional.
+        let geometry_source = shader_sources.get(&"geometry").unwrap();
+        if !geometry_source.trim().is_empty() {
+            let geometry_cstr = CString::new(&geometry_source[..]).unwrap();
+            shaders.push(Shader::from_source(&geometry_cstr, gl::GEOMETRY_SHADER).unwrap());
+        }
+
+        ShaderProgram::from_shaders(&shaders)
+            .map_err(|message| ShaderError::LinkError { path, message })
     }
     pub fn from_shaders(shaders: &[Shader]) -> Result<ShaderProgram, String> {
         let program_id = unsafe { gl::CreateProgram() };
@@ -163,18 +190,50 @@ impl ShaderProgram {
             gl::UniformMatrix4fv(name, 1, gl::FALSE, value.as_ptr())
         }
     }
+    /// Sets the elements of a `mat4` array uniform, starting from the first.
+    pub fn set_uniform_matrix4_array(&mut self, name: String, values: &[Matrix4<f32>]) {
+        if values.is_empty() {
+            return;
+        }
+        unsafe {
+            let name = self.get_uniform_location(name);
+            gl::UniformMatrix4fv(name, values.len() as i32, gl::FALSE, values[0].as_ptr())
+        }
+    }
+    /// Sets the elements of a `float` array uniform, starting from the first.
+    pub fn set_uniform_float_array(&mut self, name: String, values: &[f32]) {
+        if values.is_empty() {
+            return;
+        }
+        unsafe {
+            let name = self.get_uniform_location(name);
+            gl::Uniform1fv(name, values.len() as i32, values.as_ptr())
+        }
+    }
     pub fn set_uniform_bool(&mut self, name: String, value: bool) {
         unsafe {
             let name = self.get_uniform_location(name);
             gl::Uniform1ui(name, value as u32)
         }
     }
+    pub fn set_uniform_int(&mut self, name: String, value: i32) {
+        unsafe {
+            let name = self.get_uniform_location(name);
+            gl::Uniform1i(name, value)
+        }
+    }
     pub fn set_uniform_float(&mut self, name: String, value: f32) {
         unsafe {
             let name = self.get_uniform_location(name);
             gl::Uniform1f(name, value)
         }
     }
+    pub fn set_uniform_vector2(&mut self, name: String, value: &Vector2<f32>) {
+        unsafe {
+            let name = self.get_uniform_location(name);
+            gl::Uniform2f(name, value.x, value.y)
+        }
+    }
     pub fn set_uniform_vector3(&mut self, name: String, value: &Vector3<f32>) {
         unsafe {
             let name = self.get_uniform_location(name);
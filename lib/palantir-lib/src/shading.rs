@@ -0,0 +1,145 @@
+use crate::{ShaderProgram, Texture};
+use cgmath::Vector3;
+
+/// How meshes are drawn in a viewport.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ShadingMode {
+    #[default]
+    Solid,
+    Wireframe,
+    WireframeOnShaded,
+    Flat,
+    VertexNormals,
+    WorldNormals,
+    UvChecker,
+    Matcap,
+    UnlitTexture,
+    /// Colors meshes by the level of detail drawn.
+    LodLevels,
+}
+
+impl ShadingMode {
+    pub const ALL: [ShadingMode; 10] = [
+        ShadingMode::Solid,
+        ShadingMode::Wireframe,
+        ShadingMode::WireframeOnShaded,
+        ShadingMode::Flat,
+        ShadingMode::VertexNormals,
+        ShadingMode::WorldNormals,
+        ShadingMode::UvChecker,
+        ShadingMode::Matcap,
+        ShadingMode::UnlitTexture,
+        ShadingMode::LodLevels,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ShadingMode::Solid => "Solid",
+            ShadingMode::Wireframe => "Wireframe",
+            ShadingMode::WireframeOnShaded => "Wireframe on Shaded",
+            ShadingMode::Flat => "Flat",
+            ShadingMode::VertexNormals => "Vertex Normals",
+            ShadingMode::WorldNormals => "World Normals",
+            ShadingMode::UvChecker => "UV Checker",
+            ShadingMode::Matcap => "Matcap",
+            ShadingMode::UnlitTexture => "Unlit Texture",
+            ShadingMode::LodLevels => "LOD Levels",
+        }
+    }
+    /// Mode of the debug shader drawing this shading, if it uses it.
+    pub(crate) fn debug_index(self) -> Option<i32> {
+        match self {
+            ShadingMode::Flat => Some(0),
+            ShadingMode::WorldNormals => Some(1),
+            ShadingMode::UvChecker => Some(2),
+            ShadingMode::Matcap => Some(3),
+            ShadingMode::UnlitTexture => Some(4),
+            ShadingMode::LodLevels => Some(5),
+            _ => None,
+        }
+    }
+}
+
+pub struct ShadingShaders {
+    /// Constant color shader, drawn with polygons in line mode.
+    pub wireframe: ShaderProgram,
+    pub debug: ShaderProgram,
+    /// Draws the vertex normals as lines from a geometry shader.
+    pub normals: ShaderProgram,
+}
+
+/// Shaders and textures of the shading modes other than `ShadingMode::Solid`.
+pub struct Shading {
+    pub wireframe_color: Vector3<f32>,
+    pub normal_color: Vector3<f32>,
+    pub normal_length: f32,
+    pub uv_checker_scale: f32,
+    shaders: ShadingShaders,
+    /// The same shaders compiled with `SKINNED` defined.
+    skinned_shaders: ShadingShaders,
+    uv_checker: Texture,
+    matcap: Texture,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum ShadingPass {
+    Wireframe,
+    Debug(i32),
+    Normals,
+}
+
+impl Shading {
+    pub fn new(
+        shaders: ShadingShaders,
+        skinned_shaders: ShadingShaders,
+        uv_checker: Texture,
+        matcap: Texture,
+    ) -> Self {
+        Shading {
+            wireframe_color: Vector3::new(0.9, 0.6, 0.2),
+            normal_color: Vector3::new(0.2, 0.6, 0.9),
+            normal_length: 0.05,
+            uv_checker_scale: 1.0,
+            shaders,
+            skinned_shaders,
+            uv_checker,
+            matcap,
+        }
+    }
+    /// Binds the shader of `pass`, its skinned variant for skinned submeshes, and sets the
+    /// uniforms of the pass.
+    pub(crate) fn bind_pass(&mut self, pass: ShadingPass, skinned: bool) -> &mut ShaderProgram {
+        let shaders = if skinned {
+            &mut self.skinned_shaders
+        } else {
+            &mut self.shaders
+        };
+        match pass {
+            ShadingPass::Wireframe => {
+                let shader = &mut shaders.wireframe;
+                shader.bind();
+                shader.set_uniform_vector3(String::from("u_color"), &self.wireframe_color);
+                shader
+            }
+            ShadingPass::Debug(mode) => {
+                let shader = &mut shaders.debug;
+                shader.bind();
+                shader.set_uniform_int(String::from("u_mode"), mode);
+                // After the morph target deltas of the renderer.
+                self.uv_checker.bind(5);
+                shader.set_uniform_int(String::from("u_uv_checker"), 5);
+                shader.set_uniform_float(String::from("u_uv_checker_scale"), self.uv_checker_scale);
+                self.matcap.bind(6);
+                shader.set_uniform_int(String::from("u_matcap"), 6);
+                shader
+            }
+            ShadingPass::Normals => {
+                let shader = &mut shaders.normals;
+                shader.bind();
+                shader.set_uniform_vector3(String::from("u_color"), &self.normal_color);
+                shader.set_uniform_float(String::from("u_normal_length"), self.normal_length);
+                shader
+            }
+        }
+    }
+}
@@ -0,0 +1,427 @@
+use crate::{position_key, Vertex};
+use cgmath::prelude::*;
+use cgmath::Vector3;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+/// Sum of squared distances to a set of planes, stored as the upper triangle of the
+/// symmetric 4x4 matrix of Garland and Heckbert.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    fn from_plane(normal: Vector3<f64>, distance: f64, weight: f64) -> Self {
+        let (a, b, c, d) = (normal.x, normal.y, normal.z, distance);
+        Quadric(
+            [
+                a * a,
+                a * b,
+                a * c,
+                a * d,
+                b * b,
+                b * c,
+                b * d,
+                c * c,
+                c * d,
+                d * d,
+            ]
+            .map(|value| value * weight),
+        )
+    }
+    fn add(&mut self, other: &Quadric) {
+        for (value, other) in self.0.iter_mut().zip(other.0.iter()) {
+            *value += other;
+        }
+    }
+    fn error(&self, p: Vector3<f64>) -> f64 {
+        let q = &self.0;
+        let (x, y, z) = (p.x, p.y, p.z);
+        q[0] * x * x
+            + 2.0 * q[1] * x * y
+            + 2.0 * q[2] * x * z
+            + 2.0 * q[3] * x
+            + q[4] * y * y
+            + 2.0 * q[5] * y * z
+            + 2.0 * q[6] * y
+            + q[7] * z * z
+            + 2.0 * q[8] * z
+            + q[9]
+    }
+}
+
+/// Edge collapse waiting in the queue, stale once either end has changed since.
+struct Collapse {
+    cost: f64,
+    position: Vector3<f64>,
+    from: usize,
+    into: usize,
+    versions: (u32, u32),
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cost == other.cost
+    }
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Collapse {
+    // Reversed, the binary heap pops the cheapest collapse first.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .partial_cmp(&self.cost)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+/// Reduces a triangle list to about `target_triangles` triangles by collapsing the edges
+/// that change the surface the least.
+///
+/// Vertices split along UV or normal seams are collapsed together, so seams stay closed,
+/// and open borders are weighted to keep the silhouette. Kept vertices retain their normal
+/// and UV.
+///
+/// Panics when an index is out of range of `vertices`.
+pub fn simplify(
+    vertices: &[Vertex],
+    indices: &[u32],
+    target_triangles: usize,
+) -> (Vec<Vertex>, Vec<u32>) {
+    const BORDER_WEIGHT: f64 = 100.0;
+    assert!(
+        indices
+            .iter()
+            .all(|&index| (index as usize) < vertices.len()),
+        "Triangle index out of range of the vertices."
+    );
+
+    // Vertices sharing a position are one point of the simplified surface.
+    let mut point_of_position = HashMap::new();
+    let mut positions: Vec<Vector3<f64>> = Vec::new();
+    let vertex_points: Vec<usize> = vertices
+        .iter()
+        .map(|vertex| {
+            let position = vertex.position;
+            *point_of_position
+                .entry(position_key(position))
+                .or_insert_with(|| {
+                    positions.push(position.cast().unwrap());
+                    positions.len() - 1
+                })
+        })
+        .collect();
+
+    let mut triangles: Vec<[usize; 3]> = indices
+        .chunks_exact(3)
+        .map(|triangle| {
+            [
+                vertex_points[triangle[0] as usize],
+                vertex_points[triangle[1] as usize],
+                vertex_points[triangle[2] as usize],
+            ]
+        })
+        .collect();
+    let mut alive: Vec<bool> = triangles
+        .iter()
+        .map(|&[a, b, c]| a != b && b != c && a != c)
+        .collect();
+    let mut alive_count = alive.iter().filter(|&&alive| alive).count();
+
+    let mut quadrics = vec![Quadric::default(); positions.len()];
+    let mut point_triangles = vec![Vec::new(); positions.len()];
+    let mut edge_triangles: HashMap<(usize, usize), u32> = HashMap::new();
+    for (t, triangle) in triangles.iter().enumerate() {
+        if !alive[t] {
+            continue;
+        }
+        let [p0, p1, p2] = triangle.map(|point| positions[point]);
+        let cross = (p1 - p0).cross(p2 - p0);
+        let area = cross.magnitude();
+        if area > 0.0 {
+            let normal = cross / area;
+            let quadric = Quadric::from_plane(normal, -normal.dot(p0), area);
+            for &point in triangle {
+                quadrics[point].add(&quadric);
+            }
+        }
+        for (i, &point) in triangle.iter().enumerate() {
+            point_triangles[point].push(t);
+            let next = triangle[(i + 1) % 3];
+            *edge_triangles
+                .entry((point.min(next), point.max(next)))
+                .or_insert(0) += 1;
+        }
+    }
+    // Planes through the open edges, perpendicular to their face, hold the borders in place.
+    for (t, triangle) in triangles.iter().enumerate() {
+        if !alive[t] {
+            continue;
+        }
+        let [p0, p1, p2] = triangle.map(|point| positions[point]);
+        let face_normal = (p1 - p0).cross(p2 - p0);
+        for i in 0..3 {
+            let (a, b) = (triangle[i], triangle[(i + 1) % 3]);
+            if edge_triangles[&(a.min(b), a.max(b))] != 1 {
+                continue;
+            }
+            let edge = positions[b] - positions[a];
+            let normal = edge.cross(face_normal);
+            if normal.magnitude2() == 0.0 {
+                continue;
+            }
+            let normal = normal.normalize();
+            let quadric = Quadric::from_plane(
+                normal,
+                -normal.dot(positions[a]),
+                edge.magnitude2() * BORDER_WEIGHT,
+            );
+            quadrics[a].add(&quadric);
+            quadrics[b].add(&quadric);
+        }
+    }
+
+    let mut versions = vec![0u32; positions.len()];
+    let mut merged_into: Vec<usize> = (0..positions.len()).collect();
+    let mut queue = BinaryHeap::new();
+    let evaluate = |from: usize,
+                    into: usize,
+                    positions: &[Vector3<f64>],
+                    quadrics: &[Quadric],
+                    versions: &[u32]| {
+        let mut quadric = quadrics[from];
+        quadric.add(&quadrics[into]);
+        let candidates = [
+            positions[into],
+            positions[from],
+            (positions[from] + positions[into]) * 0.5,
+        ];
+        let (position, cost) = candidates
+            .iter()
+            .map(|&position| (position, quadric.error(position)))
+            .fold((candidates[0], f64::INFINITY), |best, candidate| {
+                if candidate.1 < best.1 {
+                    candidate
+                } else {
+                    best
+                }
+            });
+        Collapse {
+            cost,
+            position,
+            from,
+            into,
+            versions: (versions[from], versions[into]),
+        }
+    };
+    for &(a, b) in edge_triangles.keys() {
+        queue.push(evaluate(a, b, &positions, &quadrics, &versions));
+    }
+
+    while alive_count > target_triangles {
+        let collapse = match queue.pop() {
+            Some(collapse) => collapse,
+            None => break,
+        };
+        let (from, into) = (collapse.from, collapse.into);
+        if (versions[from], versions[into]) != collapse.versions {
+            continue;
+        }
+        if flips_triangle(
+            from,
+            into,
+            collapse.position,
+            &positions,
+            &triangles,
+            &alive,
+            &point_triangles,
+        ) {
+            continue;
+        }
+
+        merged_into[from] = into;
+        positions[into] = collapse.position;
+        let from_quadric = quadrics[from];
+        quadrics[into].add(&from_quadric);
+        versions[from] += 1;
+        versions[into] += 1;
+
+        let moved = std::mem::take(&mut point_triangles[from]);
+        for t in moved {
+            if !alive[t] {
+                continue;
+            }
+            if triangles[t].contains(&into) {
+                alive[t] = false;
+                alive_count -= 1;
+                continue;
+            }
+            for point in triangles[t].iter_mut() {
+                if *point == from {
+                    *point = into;
+                }
+            }
+            point_triangles[into].push(t);
+        }
+        point_triangles[into].retain(|&t| alive[t]);
+
+        let mut neighbors: Vec<usize> = point_triangles[into]
+            .iter()
+            .flat_map(|&t| triangles[t].iter().copied())
+            .filter(|&point| point != into)
+            .collect();
+        neighbors.sort_unstable();
+        neighbors.dedup();
+        for neighbor in neighbors {
+            queue.push(evaluate(neighbor, into, &positions, &quadrics, &versions));
+        }
+    }
+
+    // Keeps the vertices of the triangles left, moved to the position of their point.
+    let final_point = |mut point: usize| {
+        while merged_into[point] != point {
+            point = merged_into[point];
+        }
+        point
+    };
+    let mut new_index = vec![None; vertices.len()];
+    let mut new_vertices = Vec::new();
+    let mut new_indices = Vec::with_capacity(alive_count * 3);
+    for (t, triangle) in indices.chunks_exact(3).enumerate() {
+        if !alive[t] {
+            continue;
+        }
+        for &index in triangle {
+            let index = index as usize;
+            let new = *new_index[index].get_or_insert_with(|| {
+                let mut vertex = vertices[index];
+                vertex.position = positions[final_point(vertex_points[index])].cast().unwrap();
+                new_vertices.push(vertex);
+                new_vertices.len() as u32 - 1
+            });
+            new_indices.push(new);
+        }
+    }
+    (new_vertices, new_indices)
+}
+
+/// Whether moving `from` and `into` to `position` would turn a face of theirs around.
+fn flips_triangle(
+    from: usize,
+    into: usize,
+    position: Vector3<f64>,
+    positions: &[Vector3<f64>],
+    triangles: &[[usize; 3]],
+    alive: &[bool],
+    point_triangles: &[Vec<usize>],
+) -> bool {
+    let moved = |point: usize| {
+        if point == from || point == into {
+            position
+        } else {
+            positions[point]
+        }
+    };
+    point_triangles[from]
+        .iter()
+        .chain(point_triangles[into].iter())
+        .filter(|&&t| alive[t])
+        .filter(|&&t| !(triangles[t].contains(&from) && triangles[t].contains(&into)))
+        .any(|&t| {
+            let [p0, p1, p2] = triangles[t].map(|point| positions[point]);
+            let [q0, q1, q2] = triangles[t].map(moved);
+            let before = (p1 - p0).cross(p2 - p0);
+            let after = (q1 - q0).cross(q2 - q0);
+            before.dot(after) <= 0.0
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::Vector2;
+
+    /// Gently bumpy height field of `size` by `size` quads, every face pointing up in z.
+    fn height_field(size: usize) -> (Vec<Vertex>, Vec<u32>) {
+        let mut vertices = Vec::new();
+        for y in 0..=size {
+            for x in 0..=size {
+                let (u, v) = (x as f32 / size as f32, y as f32 / size as f32);
+                vertices.push(Vertex {
+                    position: Vector3::new(u, v, 0.05 * (u * 6.0).sin() * (v * 4.0).cos()),
+                    normal: Vector3::unit_z(),
+                    uv: Vector2::new(u, v),
+                });
+            }
+        }
+        let mut indices = Vec::new();
+        let row = size as u32 + 1;
+        for y in 0..size as u32 {
+            for x in 0..size as u32 {
+                let corner = y * row + x;
+                indices.extend_from_slice(&[corner, corner + 1, corner + row + 1]);
+                indices.extend_from_slice(&[corner, corner + row + 1, corner + row]);
+            }
+        }
+        (vertices, indices)
+    }
+
+    fn face_normal(vertices: &[Vertex], triangle: &[u32]) -> Vector3<f32> {
+        let [a, b, c] = [0, 1, 2].map(|i| vertices[triangle[i] as usize].position);
+        (b - a).cross(c - a)
+    }
+
+    #[test]
+    fn reaches_target_without_flipping() {
+        let (vertices, indices) = height_field(16);
+        assert_eq!(indices.len() / 3, 512);
+        for &target in [256, 100, 20].iter() {
+            let (new_vertices, new_indices) = simplify(&vertices, &indices, target);
+            let triangles = new_indices.len() / 3;
+            // A collapse removes the one or two triangles along its edge.
+            assert!(
+                triangles <= target && triangles + 2 > target,
+                "{}",
+                triangles
+            );
+            assert!(new_indices
+                .iter()
+                .all(|&index| (index as usize) < new_vertices.len()));
+            for triangle in new_indices.chunks_exact(3) {
+                assert!(face_normal(&new_vertices, triangle).z > 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn keeps_meshes_under_target() {
+        let (vertices, indices) = height_field(2);
+        let (new_vertices, new_indices) = simplify(&vertices, &indices, 100);
+        // Vertices are renumbered in the order the triangles use them.
+        let corners = |vertices: &[Vertex], indices: &[u32]| -> Vec<Vertex> {
+            indices
+                .iter()
+                .map(|&index| vertices[index as usize])
+                .collect()
+        };
+        assert_eq!(new_vertices.len(), vertices.len());
+        assert_eq!(
+            corners(&new_vertices, &new_indices),
+            corners(&vertices, &indices)
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn index_out_of_range() {
+        let (vertices, _) = height_field(1);
+        simplify(&vertices, &[0, 1, 4], 0);
+    }
+}
@@ -1,4 +1,4 @@
-use image::GenericImageView;
+use image::{DynamicImage, GenericImageView};
 use std::os::raw::c_void;
 use std::path::PathBuf;
 
@@ -12,7 +12,11 @@ pub struct Texture {
 impl Texture {
     pub fn new(path: PathBuf) -> Self {
         let texture_image = image::open(path).expect("Failed to load texture.").flipv();
-        let data = texture_image.to_bytes();
+        Texture::from_image(&texture_image)
+    }
+    /// Uploads an already decoded image, its rows must be ordered bottom first.
+    pub fn from_image(texture_image: &DynamicImage) -> Self {
+        let data = texture_image.to_rgba8().into_raw();
 
         let mut texture = Texture {
             id: 0,
@@ -34,15 +38,15 @@ impl Texture {
             texture.width = texture_image.width() as i32;
             texture.height = texture_image.height() as i32;
 
-            // store in GPU Memory
+            // store in GPU Memory, color textures are authored in sRGB, alpha is linear
             gl::TexImage2D(
                 gl::TEXTURE_2D,
                 0,
-                gl::RGB as i32,
+                gl::SRGB8_ALPHA8 as i32,
                 texture.width,
                 texture.height,
                 0,
-                gl::RGB,
+                gl::RGBA,
                 gl::UNSIGNED_BYTE,
                 &data[0] as *const u8 as *const c_void,
             );
@@ -50,6 +54,10 @@ impl Texture {
         }
         texture
     }
+    /// Name of the OpenGL texture object.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
     pub fn bind(&self, texture_unit: u32) {
         unsafe {
             gl::ActiveTexture(gl::TEXTURE0 + texture_unit);
@@ -0,0 +1,248 @@
+use crate::{draw_fullscreen_triangle, FramebufferError, ShaderProgram, VertexArray};
+
+/// How the renderer draws the materials with `BlendMode::Blended`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransparencyMode {
+    /// Blended back to front, mesh by mesh. Exact unless the meshes overlap.
+    Sorted,
+    /// Order-independent approximation, for overlapping glass.
+    WeightedBlended,
+}
+
+impl TransparencyMode {
+    pub const ALL: [TransparencyMode; 2] =
+        [TransparencyMode::Sorted, TransparencyMode::WeightedBlended];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TransparencyMode::Sorted => "Sorted",
+            TransparencyMode::WeightedBlended => "Weighted Blended",
+        }
+    }
+}
+
+/// Accumulation and weight textures of weighted blended transparency. The depth buffer is the
+/// one of the framebuffer the opaque geometry was drawn to, so that it hides the surfaces
+/// behind it.
+struct WeightedBlendedTarget {
+    id: u32,
+    accumulation_texture: u32,
+    weight_texture: u32,
+    depth_renderbuffer: u32,
+    width: i32,
+    height: i32,
+}
+
+impl WeightedBlendedTarget {
+    fn new(depth_renderbuffer: u32, width: i32, height: i32) -> Result<Self, FramebufferError> {
+        let mut target = WeightedBlendedTarget {
+            id: 0,
+            accumulation_texture: create_texture(width, height),
+            weight_texture: create_texture(width, height),
+            depth_renderbuffer,
+            width,
+            height,
+        };
+        unsafe {
+            gl::GenFramebuffers(1, &mut target.id);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, target.id);
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::TEXTURE_2D,
+                target.accumulation_texture,
+                0,
+            );
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT1,
+                gl::TEXTURE_2D,
+                target.weight_texture,
+                0,
+            );
+            gl::FramebufferRenderbuffer(
+                gl::FRAMEBUFFER,
+                gl::DEPTH_STENCIL_ATTACHMENT,
+                gl::RENDERBUFFER,
+                depth_renderbuffer,
+            );
+            let draw_buffers = [gl::COLOR_ATTACHMENT0, gl::COLOR_ATTACHMENT1];
+            gl::DrawBuffers(2, draw_buffers.as_ptr());
+
+            let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            if status != gl::FRAMEBUFFER_COMPLETE {
+                return Err(FramebufferError::Incomplete { status });
+            }
+        }
+        Ok(target)
+    }
+    fn bind_and_clear(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.id);
+            gl::Viewport(0, 0, self.width, self.height);
+            // The revealage in the alpha of the accumulation starts at 1, nothing covered yet.
+            gl::ClearBufferfv(gl::COLOR, 0, [0.0, 0.0, 0.0, 1.0].as_ptr());
+            gl::ClearBufferfv(gl::COLOR, 1, [0.0, 0.0, 0.0, 0.0].as_ptr());
+        }
+    }
+}
+
+impl Drop for WeightedBlendedTarget {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.accumulation_texture);
+            gl::DeleteTextures(1, &self.weight_texture);
+            gl::DeleteFramebuffers(1, &self.id);
+        }
+    }
+}
+
+fn create_texture(width: i32, height: i32) -> u32 {
+    let mut texture = 0;
+    unsafe {
+        gl::GenTextures(1, &mut texture);
+        gl::BindTexture(gl::TEXTURE_2D, texture);
+        gl::TexImage2D(
+            gl::TEXTURE_2D,
+            0,
+            gl::RGBA16F as i32,
+            width,
+            height,
+            0,
+            gl::RGBA,
+            gl::FLOAT,
+            std::ptr::null(),
+        );
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+    }
+    texture
+}
+
+/// Framebuffer and viewport the transparent surfaces are composited onto.
+#[derive(Clone, Copy)]
+struct Output {
+    framebuffer: u32,
+    viewport: [i32; 4],
+}
+
+/// Draws the transparent pass to its own targets, then composites them over the opaque image.
+pub(crate) struct WeightedBlended {
+    composite_shader: ShaderProgram,
+    target: Option<WeightedBlendedTarget>,
+    output: Option<Output>,
+    fullscreen_vertex_array: VertexArray,
+}
+
+impl WeightedBlended {
+    pub(crate) fn new(composite_shader: ShaderProgram) -> Self {
+        WeightedBlended {
+            composite_shader,
+            target: None,
+            output: None,
+            fullscreen_vertex_array: VertexArray::new(),
+        }
+    }
+    /// Whether the surfaces drawn now go to the accumulation targets.
+    pub(crate) fn is_active(&self) -> bool {
+        self.output.is_some()
+    }
+    /// Redirects the drawing to the accumulation targets. Returns false when the bound
+    /// framebuffer has no depth renderbuffer to share, the window's one for instance.
+    pub(crate) fn begin(&mut self) -> bool {
+        let mut framebuffer = 0;
+        let mut viewport = [0; 4];
+        let mut depth_type = 0;
+        let mut depth_renderbuffer = 0;
+        unsafe {
+            gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut framebuffer);
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+            if framebuffer == 0 {
+                return false;
+            }
+            gl::GetFramebufferAttachmentParameteriv(
+                gl::DRAW_FRAMEBUFFER,
+                gl::DEPTH_ATTACHMENT,
+                gl::FRAMEBUFFER_ATTACHMENT_OBJECT_TYPE,
+                &mut depth_type,
+            );
+            if depth_type as u32 != gl::RENDERBUFFER {
+                return false;
+            }
+            gl::GetFramebufferAttachmentParameteriv(
+                gl::DRAW_FRAMEBUFFER,
+                gl::DEPTH_ATTACHMENT,
+                gl::FRAMEBUFFER_ATTACHMENT_OBJECT_NAME,
+                &mut depth_renderbuffer,
+            );
+        }
+        let depth_renderbuffer = depth_renderbuffer as u32;
+        let (width, height) = (viewport[2], viewport[3]);
+        let reusable = match &self.target {
+            Some(target) => {
+                target.depth_renderbuffer == depth_renderbuffer
+                    && target.width == width
+                    && target.height == height
+            }
+            None => false,
+        };
+        if !reusable {
+            self.target = None;
+            match WeightedBlendedTarget::new(depth_renderbuffer, width, height) {
+                Ok(target) => self.target = Some(target),
+                Err(_) => {
+                    unsafe { gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer as u32) };
+                    return false;
+                }
+            }
+        }
+        if let Some(target) = &self.target {
+            target.bind_and_clear();
+        }
+        unsafe {
+            // Sums the weighted colors and weights, multiplies the revealage by 1 - alpha.
+            gl::BlendFuncSeparate(gl::ONE, gl::ONE, gl::ZERO, gl::ONE_MINUS_SRC_ALPHA);
+        }
+        self.output = Some(Output {
+            framebuffer: framebuffer as u32,
+            viewport,
+        });
+        true
+    }
+    /// Blends the average of the accumulated colors over the framebuffer bound at `begin`.
+    pub(crate) fn end(&mut self) {
+        let (output, target) = match (self.output.take(), &self.target) {
+            (Some(output), Some(target)) => (output, target),
+            _ => return,
+        };
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, output.framebuffer);
+            let [x, y, width, height] = output.viewport;
+            gl::Viewport(x, y, width, height);
+            gl::Disable(gl::DEPTH_TEST);
+            gl::BlendFuncSeparate(
+                gl::SRC_ALPHA,
+                gl::ONE_MINUS_SRC_ALPHA,
+                gl::ONE,
+                gl::ONE_MINUS_SRC_ALPHA,
+            );
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D, target.accumulation_texture);
+            gl::ActiveTexture(gl::TEXTURE1);
+            gl::BindTexture(gl::TEXTURE_2D, target.weight_texture);
+        }
+        self.composite_shader.bind();
+        self.composite_shader
+            .set_uniform_int(String::from("u_accumulation"), 0);
+        self.composite_shader
+            .set_uniform_int(String::from("u_weight"), 1);
+        self.fullscreen_vertex_array.bind();
+        draw_fullscreen_triangle();
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+        }
+    }
+}
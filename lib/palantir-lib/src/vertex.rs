@@ -1,8 +1,28 @@
 use cgmath::{Vector2, Vector3};
 
 // TODO: Use trait to automatically generate layout
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Vertex {
     pub position: Vector3<f32>,
     pub normal: Vector3<f32>,
     pub uv: Vector2<f32>,
 }
+
+/// Joints influencing a skinned vertex.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct JointWeights {
+    pub joints: [u32; 4],
+    pub weights: [f32; 4],
+}
+
+/// Vertex deformed by up to four joints of a skeleton.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SkinnedVertex {
+    pub vertex: Vertex,
+    /// Indices in the joint matrices given to `Renderer::set_joint_matrices`.
+    pub joints: [u32; 4],
+    /// Influence of each joint, summing to 1.
+    pub weights: [f32; 4],
+}
@@ -1,6 +1,7 @@
 use crate::vertex_buffer::{VertexBuffer, VertexBufferLayout};
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+/// Owns its GL vertex array, deleted on drop.
+#[derive(Debug, PartialEq)]
 pub struct VertexArray {
     id: u32,
 }
@@ -37,3 +38,17 @@ impl VertexArray {
         }
     }
 }
+
+impl Drop for VertexArray {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.id);
+        }
+    }
+}
+
+impl Default for VertexArray {
+    fn default() -> Self {
+        Self::new()
+    }
+}
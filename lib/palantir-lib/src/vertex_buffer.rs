@@ -1,27 +1,126 @@
-use crate::vertex::Vertex;
+/// How often the contents of a buffer change, a hint for where the driver keeps it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BufferUsage {
+    /// Uploaded once and drawn many times.
+    Static,
+    /// Updated now and then, like edited geometry.
+    Dynamic,
+    /// Replaced every frame.
+    Stream,
+}
 
-#[derive(Clone, Debug, PartialEq)]
+impl BufferUsage {
+    pub const ALL: [BufferUsage; 3] = [
+        BufferUsage::Static,
+        BufferUsage::Dynamic,
+        BufferUsage::Stream,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BufferUsage::Static => "Static",
+            BufferUsage::Dynamic => "Dynamic",
+            BufferUsage::Stream => "Stream",
+        }
+    }
+    pub(crate) fn gl_usage(self) -> u32 {
+        match self {
+            BufferUsage::Static => gl::STATIC_DRAW,
+            BufferUsage::Dynamic => gl::DYNAMIC_DRAW,
+            BufferUsage::Stream => gl::STREAM_DRAW,
+        }
+    }
+}
+
+/// Owns its GL buffer, deleted on drop, so it cannot be cloned.
+#[derive(Debug, PartialEq)]
 pub struct VertexBuffer {
     id: u32,
+    usage: BufferUsage,
+    /// In bytes.
+    size: usize,
 }
 
 impl VertexBuffer {
-    pub fn new(vertices: Vec<Vertex>) -> Self {
-        let mut buffer = VertexBuffer { id: 0 };
+    pub fn new<T>(vertices: Vec<T>) -> Self {
+        VertexBuffer::with_usage(&vertices, BufferUsage::Static)
+    }
+    pub fn with_usage<T>(vertices: &[T], usage: BufferUsage) -> Self {
+        let mut buffer = VertexBuffer {
+            id: 0,
+            usage,
+            size: 0,
+        };
         unsafe {
             gl::GenBuffers(1, &mut buffer.id);
-            gl::BindBuffer(gl::ARRAY_BUFFER, buffer.id);
+        }
+        buffer.update(vertices);
+        buffer
+    }
+    pub fn bind(&self) {
+        unsafe { gl::BindBuffer(gl::ARRAY_BUFFER, self.id) }
+    }
+    pub fn usage(&self) -> BufferUsage {
+        self.usage
+    }
+    /// Used from the next `update` on.
+    pub fn set_usage(&mut self, usage: BufferUsage) {
+        self.usage = usage;
+    }
+    /// Size of the contents, in bytes.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+    /// Replaces the contents, which can change size. The previous storage is orphaned, so
+    /// draws still reading it do not stall the upload.
+    pub fn update<T>(&mut self, vertices: &[T]) {
+        self.size = std::mem::size_of_val(vertices);
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.id);
             gl::BufferData(
                 gl::ARRAY_BUFFER,
-                (vertices.len() * std::mem::size_of::<Vertex>()) as gl::types::GLsizeiptr,
+                self.size as gl::types::GLsizeiptr,
                 vertices.as_ptr() as *const gl::types::GLvoid,
-                gl::STATIC_DRAW,
+                self.usage.gl_usage(),
             );
         }
-        buffer
     }
-    pub fn bind(&self) {
-        unsafe { gl::BindBuffer(gl::ARRAY_BUFFER, self.id) }
+    /// Overwrites the vertices from `first` on, without reallocating. `T` must be the uploaded
+    /// type and the range must be inside of the buffer.
+    pub fn update_range<T>(&mut self, first: usize, vertices: &[T]) {
+        let offset = first * std::mem::size_of::<T>();
+        let size = std::mem::size_of_val(vertices);
+        assert!(
+            offset + size <= self.size,
+            "Updated range is past the end of the vertex buffer."
+        );
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.id);
+            gl::BufferSubData(
+                gl::ARRAY_BUFFER,
+                offset as gl::types::GLintptr,
+                size as gl::types::GLsizeiptr,
+                vertices.as_ptr() as *const gl::types::GLvoid,
+            );
+        }
+    }
+    /// Reads up to `count` vertices back from the GPU, fewer when the buffer holds less.
+    ///
+    /// # Safety
+    ///
+    /// `T` must be the uploaded type, any bytes are read back as a `T`.
+    pub unsafe fn read<T: Copy>(&self, count: usize) -> Vec<T> {
+        let count = count.min(self.size / std::mem::size_of::<T>().max(1));
+        let mut vertices = Vec::with_capacity(count);
+        gl::BindBuffer(gl::ARRAY_BUFFER, self.id);
+        gl::GetBufferSubData(
+            gl::ARRAY_BUFFER,
+            0,
+            (count * std::mem::size_of::<T>()) as gl::types::GLsizeiptr,
+            vertices.as_mut_ptr() as *mut gl::types::GLvoid,
+        );
+        vertices.set_len(count);
+        vertices
     }
 }
 
@@ -65,6 +164,12 @@ impl VertexBufferLayout {
     }
 }
 
+impl Default for VertexBufferLayout {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 pub trait GLType {
     fn gl_size() -> u32;
     fn gl_type() -> u32;
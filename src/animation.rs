@@ -0,0 +1,391 @@
+use crate::components::JointPose;
+use cgmath::prelude::*;
+use cgmath::{Quaternion, Vector3};
+
+/// How values change between two keyframes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Holds the value of the previous keyframe.
+    Step,
+    Linear,
+    /// Smoothstep between the keyframes, easing out of and into every one of them.
+    Ease,
+    /// Cubic Bezier leaving and entering the keyframes along their tangents.
+    Bezier,
+}
+
+impl Interpolation {
+    pub const ALL: [Interpolation; 4] = [
+        Interpolation::Step,
+        Interpolation::Linear,
+        Interpolation::Ease,
+        Interpolation::Bezier,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Interpolation::Step => "step",
+            Interpolation::Linear => "linear",
+            Interpolation::Ease => "ease",
+            Interpolation::Bezier => "bezier",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Interpolation::Step => "Step",
+            Interpolation::Linear => "Linear",
+            Interpolation::Ease => "Ease",
+            Interpolation::Bezier => "Bezier",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Interpolation> {
+        Interpolation::ALL
+            .iter()
+            .copied()
+            .find(|interpolation| interpolation.name() == name)
+    }
+}
+
+pub trait Interpolate: Copy {
+    fn interpolate(&self, other: &Self, t: f32) -> Self;
+    /// Tangent of a keyframe the curve leaves flat.
+    fn flat() -> Self;
+    /// Value moved along a tangent for `seconds`.
+    fn offset(&self, tangent: &Self, seconds: f32) -> Self;
+}
+
+impl Interpolate for f32 {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+    fn flat() -> Self {
+        0.0
+    }
+    fn offset(&self, tangent: &Self, seconds: f32) -> Self {
+        self + tangent * seconds
+    }
+}
+
+impl Interpolate for Vector3<f32> {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        self.lerp(*other, t)
+    }
+    fn flat() -> Self {
+        Vector3::zero()
+    }
+    fn offset(&self, tangent: &Self, seconds: f32) -> Self {
+        self + tangent * seconds
+    }
+}
+
+impl Interpolate for Quaternion<f32> {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        let other = if self.dot(*other) < 0.0 {
+            -*other
+        } else {
+            *other
+        };
+        self.slerp(other, t)
+    }
+    fn flat() -> Self {
+        Quaternion::zero()
+    }
+    fn offset(&self, tangent: &Self, seconds: f32) -> Self {
+        (self + tangent * seconds).normalize()
+    }
+}
+
+/// Slopes of the curve entering and leaving a keyframe, in value per second.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tangents<T> {
+    pub incoming: T,
+    pub outgoing: T,
+}
+
+impl<T: Interpolate> Tangents<T> {
+    pub fn flat() -> Self {
+        Tangents {
+            incoming: T::flat(),
+            outgoing: T::flat(),
+        }
+    }
+}
+
+/// Values at increasing times, in seconds, with the tangents used by
+/// `Interpolation::Bezier`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Keyframes<T> {
+    times: Vec<f32>,
+    values: Vec<T>,
+    tangents: Vec<Tangents<T>>,
+    interpolation: Interpolation,
+}
+
+impl<T: Interpolate> Keyframes<T> {
+    /// Keyframes with flat tangents. Extra times or values, when one list is longer than the
+    /// other, are dropped.
+    pub fn new(times: Vec<f32>, values: Vec<T>, interpolation: Interpolation) -> Self {
+        let tangents = vec![Tangents::flat(); values.len()];
+        Keyframes::with_tangents(times, values, tangents, interpolation)
+    }
+    /// Extra times, values or tangents, when a list is longer than the others, are dropped.
+    pub fn with_tangents(
+        mut times: Vec<f32>,
+        mut values: Vec<T>,
+        mut tangents: Vec<Tangents<T>>,
+        interpolation: Interpolation,
+    ) -> Self {
+        let count = times.len().min(values.len()).min(tangents.len());
+        times.truncate(count);
+        values.truncate(count);
+        tangents.truncate(count);
+        Keyframes {
+            times,
+            values,
+            tangents,
+            interpolation,
+        }
+    }
+    pub fn times(&self) -> &[f32] {
+        &self.times
+    }
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+    pub fn tangents(&self) -> &[Tangents<T>] {
+        &self.tangents
+    }
+    pub fn set_tangents(&mut self, index: usize, tangents: Tangents<T>) {
+        self.tangents[index] = tangents;
+    }
+    pub fn interpolation(&self) -> Interpolation {
+        self.interpolation
+    }
+    pub fn set_interpolation(&mut self, interpolation: Interpolation) {
+        self.interpolation = interpolation;
+    }
+    /// Adds a keyframe with flat tangents, or replaces the value of the one already at `time`
+    /// and keeps its tangents. Returns the index of the keyframe.
+    pub fn insert(&mut self, time: f32, value: T) -> usize {
+        const SAME_TIME: f32 = 0.0001;
+
+        let index = self
+            .times
+            .partition_point(|&key_time| key_time < time - SAME_TIME);
+        if index < self.times.len() && self.times[index] <= time + SAME_TIME {
+            self.values[index] = value;
+        } else {
+            self.times.insert(index, time);
+            self.values.insert(index, value);
+            self.tangents.insert(index, Tangents::flat());
+        }
+        index
+    }
+    pub fn remove(&mut self, index: usize) {
+        self.times.remove(index);
+        self.values.remove(index);
+        self.tangents.remove(index);
+    }
+    /// Time of the last keyframe.
+    pub fn duration(&self) -> f32 {
+        self.times.last().copied().unwrap_or(0.0)
+    }
+    /// Value at `time`, held before the first keyframe and after the last one.
+    pub fn sample(&self, time: f32) -> Option<T> {
+        let next = self.times.partition_point(|&key_time| key_time <= time);
+        if next == 0 {
+            return self.values.first().copied();
+        }
+        if next == self.times.len() {
+            return self.values.last().copied();
+        }
+        let previous = next - 1;
+        let value = match self.interpolation {
+            Interpolation::Step => self.values[previous],
+            Interpolation::Linear | Interpolation::Ease => {
+                let span = self.times[next] - self.times[previous];
+                let mut t = (time - self.times[previous]) / span;
+                if self.interpolation == Interpolation::Ease {
+                    t = t * t * (3.0 - 2.0 * t);
+                }
+                self.values[previous].interpolate(&self.values[next], t)
+            }
+            Interpolation::Bezier => {
+                // Control points a third of the span along the tangents, as for a Hermite
+                // spline, evaluated with de Casteljau's algorithm.
+                let span = self.times[next] - self.times[previous];
+                let t = (time - self.times[previous]) / span;
+                let start = self.values[previous];
+                let end = self.values[next];
+                let control_start = start.offset(&self.tangents[previous].outgoing, span / 3.0);
+                let control_end = end.offset(&self.tangents[next].incoming, -span / 3.0);
+                let a = start.interpolate(&control_start, t);
+                let b = control_start.interpolate(&control_end, t);
+                let c = control_end.interpolate(&end, t);
+                let ab = a.interpolate(&b, t);
+                let bc = b.interpolate(&c, t);
+                ab.interpolate(&bc, t)
+            }
+        };
+        Some(value)
+    }
+}
+
+impl Keyframes<f32> {
+    /// Sets the tangents of every keyframe to the slope between its neighbors, the first and
+    /// last keyframes stay flat.
+    pub fn smooth_tangents(&mut self) {
+        for index in 1..self.times.len().saturating_sub(1) {
+            let slope = (self.values[index + 1] - self.values[index - 1])
+                / (self.times[index + 1] - self.times[index - 1]);
+            self.tangents[index] = Tangents {
+                incoming: slope,
+                outgoing: slope,
+            };
+        }
+    }
+}
+
+/// Animated transform of one joint, the properties without keyframes keep their rest value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JointTrack {
+    pub joint: usize,
+    pub translation: Keyframes<Vector3<f32>>,
+    pub rotation: Keyframes<Quaternion<f32>>,
+    pub scale: Keyframes<Vector3<f32>>,
+}
+
+impl JointTrack {
+    pub fn new(joint: usize) -> Self {
+        JointTrack {
+            joint,
+            translation: Keyframes::new(Vec::new(), Vec::new(), Interpolation::Linear),
+            rotation: Keyframes::new(Vec::new(), Vec::new(), Interpolation::Linear),
+            scale: Keyframes::new(Vec::new(), Vec::new(), Interpolation::Linear),
+        }
+    }
+    pub fn duration(&self) -> f32 {
+        self.translation
+            .duration()
+            .max(self.rotation.duration())
+            .max(self.scale.duration())
+    }
+}
+
+/// Named motion of a skeleton, such as a walk cycle.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimationClip {
+    pub name: String,
+    tracks: Vec<JointTrack>,
+    duration: f32,
+}
+
+impl AnimationClip {
+    pub fn new(name: String, tracks: Vec<JointTrack>) -> Self {
+        let duration = tracks.iter().map(JointTrack::duration).fold(0.0, f32::max);
+        AnimationClip {
+            name,
+            tracks,
+            duration,
+        }
+    }
+    pub fn duration(&self) -> f32 {
+        self.duration
+    }
+    /// Pose of the skeleton at `time`, the clip repeats past its end.
+    pub fn sample(&self, time: f32, rest: &[JointPose]) -> Vec<JointPose> {
+        let time = if self.duration > 0.0 {
+            time.rem_euclid(self.duration)
+        } else {
+            0.0
+        };
+        let mut pose = rest.to_vec();
+        for track in &self.tracks {
+            let joint = match pose.get_mut(track.joint) {
+                Some(joint) => joint,
+                None => continue,
+            };
+            if let Some(translation) = track.translation.sample(time) {
+                joint.translation = translation;
+            }
+            if let Some(rotation) = track.rotation.sample(time) {
+                joint.rotation = rotation;
+            }
+            if let Some(scale) = track.scale.sample(time) {
+                joint.scale = scale;
+            }
+        }
+        pose
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bezier(tangents: Vec<Tangents<f32>>) -> Keyframes<f32> {
+        Keyframes::with_tangents(
+            vec![0.0, 3.0],
+            vec![0.0, 3.0],
+            tangents,
+            Interpolation::Bezier,
+        )
+    }
+
+    #[test]
+    fn bezier_follows_tangents() {
+        // Tangents along the line give the line.
+        let straight = Tangents {
+            incoming: 1.0,
+            outgoing: 1.0,
+        };
+        let keyframes = bezier(vec![straight, straight]);
+        for &time in [0.5, 1.0, 2.25].iter() {
+            assert!((keyframes.sample(time).unwrap() - time).abs() < 1e-5);
+        }
+
+        // Flat tangents ease like a smoothstep.
+        let keyframes = bezier(vec![Tangents::flat(), Tangents::flat()]);
+        assert!((keyframes.sample(1.5).unwrap() - 1.5).abs() < 1e-5);
+        assert!(keyframes.sample(0.3).unwrap() < 0.3);
+        assert!(keyframes.sample(2.7).unwrap() > 2.7);
+
+        // A steep start overshoots the line, control points at 2 and 3.
+        let steep = Tangents {
+            incoming: 0.0,
+            outgoing: 2.0,
+        };
+        let keyframes = bezier(vec![steep, Tangents::flat()]);
+        let expected = 3.0 * 0.25 * 0.5 * 2.0 + 3.0 * 0.5 * 0.25 * 3.0 + 0.125 * 3.0;
+        assert!((keyframes.sample(1.5).unwrap() - expected).abs() < 1e-5);
+    }
+
+    #[test]
+    fn tangents_follow_their_keyframe() {
+        let mut keyframes = Keyframes::new(vec![0.0, 2.0], vec![0.0, 2.0], Interpolation::Bezier);
+        let steep = Tangents {
+            incoming: 4.0,
+            outgoing: 4.0,
+        };
+        keyframes.set_tangents(1, steep);
+        assert_eq!(keyframes.insert(1.0, 5.0), 1);
+        assert_eq!(
+            keyframes.tangents(),
+            &[Tangents::flat(), Tangents::flat(), steep]
+        );
+        // Replacing a value keeps the tangents.
+        assert_eq!(keyframes.insert(2.0, 3.0), 2);
+        assert_eq!(keyframes.tangents()[2], steep);
+        keyframes.remove(0);
+        assert_eq!(keyframes.tangents(), &[Tangents::flat(), steep]);
+
+        keyframes.insert(4.0, 3.0);
+        keyframes.smooth_tangents();
+        let tangents = keyframes.tangents();
+        assert_eq!(tangents[0], Tangents::flat());
+        assert_eq!(tangents[1].outgoing, -2.0 / 3.0);
+        assert_eq!(tangents[2], Tangents::flat());
+    }
+}
@@ -14,6 +14,15 @@ pub struct Application {
 
 impl Application {
     pub fn new(width: u32, height: u32) -> Result<Self, Box<dyn Error>> {
+        Application::create(width, height, false)
+    }
+
+    /// Creates the application with a hidden window, for rendering offscreen.
+    pub fn new_headless(width: u32, height: u32) -> Result<Self, Box<dyn Error>> {
+        Application::create(width, height, true)
+    }
+
+    fn create(width: u32, height: u32, hidden: bool) -> Result<Self, Box<dyn Error>> {
         let resources = Resources::from_relative_exe_path(Path::new("assets"))?;
 
         let _sdl = sdl2::init()?;
@@ -26,12 +35,12 @@ impl Application {
         gl_attributes.set_context_version(3, 0);
         gl_attributes.set_multisample_samples(4);
 
-        let window = video
-            .window("Palantir", width, height)
-            .opengl()
-            .allow_highdpi()
-            .resizable()
-            .build()?;
+        let mut window_builder = video.window("Palantir", width, height);
+        window_builder.opengl().allow_highdpi().resizable();
+        if hidden {
+            window_builder.hidden();
+        }
+        let window = window_builder.build()?;
 
         let _context = window.gl_create_context()?;
         gl::load_with(|s| video.gl_get_proc_address(s) as *const std::os::raw::c_void);
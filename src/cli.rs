@@ -0,0 +1,295 @@
+use cgmath::Vector3;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "Usage: palantir [OPTIONS] [MODEL.obj]...
+
+Options:
+    --headless                Render frames to disk without showing a window.
+    --output <DIR>            Directory the frames are written to. [default: renders]
+    --size <WIDTHxHEIGHT>     Size of the rendered frames. [default: 1280x720]
+    --frames <N>              Number of frames of the turntable. [default: 1]
+    --focal-length <MM>       Camera focal length, on a 36mm sensor. [default: 50]
+    --distance <UNITS>        Distance from the camera to the model. [default: 3]
+    --elevation <DEGREES>     Camera elevation above the horizon. [default: 15]
+    --light-direction <X,Y,Z> Direction the light is pointing to. [default: 1,-1,-1]
+    --light-color <R,G,B>     Color of the light. [default: 1,1,1]
+    --light-power <POWER>     Power of the light. [default: 1]
+    --ambient <STRENGTH>      Strength of the ambient light. [default: 0.25]
+    --background <R,G,B>      Background color. [default: 0.1,0.1,0.1]
+    --environment <FILE.hdr>  Equirectangular HDR image used for image based lighting.
+    --help                    Print this message.
+
+The headless window is hidden; set SDL_VIDEODRIVER to pick a display-less video
+driver on machines without a display server.";
+
+#[derive(Debug)]
+pub enum CliError {
+    MissingValue(String),
+    InvalidValue { option: String, value: String },
+    UnknownOption(String),
+}
+
+impl std::fmt::Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CliError::MissingValue(option) => write!(f, "Option {} requires a value.", option),
+            CliError::InvalidValue { option, value } => {
+                write!(f, "Invalid value {:?} for option {}.", value, option)
+            }
+            CliError::UnknownOption(option) => write!(f, "Unknown option {}.", option),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Options {
+    pub help: bool,
+    pub headless: bool,
+    pub models: Vec<PathBuf>,
+    pub output: PathBuf,
+    pub width: u32,
+    pub height: u32,
+    pub frames: u32,
+    pub focal_length: f32,
+    pub distance: f32,
+    pub elevation: f32,
+    pub light_direction: Vector3<f32>,
+    pub light_color: Vector3<f32>,
+    pub light_power: f32,
+    pub ambient_strength: f32,
+    pub background: Vector3<f32>,
+    pub environment: Option<PathBuf>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            help: false,
+            headless: false,
+            models: Vec::new(),
+            output: PathBuf::from("renders"),
+            width: 1280,
+            height: 720,
+            frames: 1,
+            focal_length: 50.0,
+            distance: 3.0,
+            elevation: 15.0,
+            light_direction: Vector3::new(1.0, -1.0, -1.0),
+            light_color: Vector3::new(1.0, 1.0, 1.0),
+            light_power: 1.0,
+            ambient_strength: 0.25,
+            background: Vector3::new(0.1, 0.1, 0.1),
+            environment: None,
+        }
+    }
+}
+
+impl Options {
+    pub fn from_args<I: Iterator<Item = String>>(mut args: I) -> Result<Self, CliError> {
+        let mut options = Options::default();
+
+        while let Some(arg) = args.next() {
+            if !arg.starts_with("--") {
+                options.models.push(PathBuf::from(arg));
+                continue;
+            }
+            match arg.as_str() {
+                "--help" => options.help = true,
+                "--headless" => options.headless = true,
+                "--output" => options.output = PathBuf::from(value(&arg, &mut args)?),
+                "--size" => {
+                    let size = value(&arg, &mut args)?;
+                    let (width, height) = parse_size(&size).ok_or(CliError::InvalidValue {
+                        option: arg,
+                        value: size,
+                    })?;
+                    options.width = width;
+                    options.height = height;
+                }
+                "--frames" => options.frames = parse(&arg, &mut args)?,
+                "--focal-length" => options.focal_length = parse(&arg, &mut args)?,
+                "--distance" => options.distance = parse(&arg, &mut args)?,
+                "--elevation" => options.elevation = parse(&arg, &mut args)?,
+                "--light-direction" => options.light_direction = parse_vector(&arg, &mut args)?,
+                "--light-color" => options.light_color = parse_vector(&arg, &mut args)?,
+                "--light-power" => options.light_power = parse(&arg, &mut args)?,
+                "--ambient" => options.ambient_strength = parse(&arg, &mut args)?,
+                "--background" => options.background = parse_vector(&arg, &mut args)?,
+                "--environment" => {
+                    options.environment = Some(PathBuf::from(value(&arg, &mut args)?))
+                }
+                _ => return Err(CliError::UnknownOption(arg)),
+            }
+        }
+
+        if options.frames == 0 {
+            return Err(CliError::InvalidValue {
+                option: String::from("--frames"),
+                value: String::from("0"),
+            });
+        }
+
+        Ok(options)
+    }
+}
+
+fn value<I: Iterator<Item = String>>(option: &str, args: &mut I) -> Result<String, CliError> {
+    args.next()
+        .ok_or_else(|| CliError::MissingValue(String::from(option)))
+}
+
+fn parse<T, I>(option: &str, args: &mut I) -> Result<T, CliError>
+where
+    T: std::str::FromStr,
+    I: Iterator<Item = String>,
+{
+    let value = value(option, args)?;
+    value.parse().map_err(|_| CliError::InvalidValue {
+        option: String::from(option),
+        value,
+    })
+}
+
+fn parse_vector<I: Iterator<Item = String>>(
+    option: &str,
+    args: &mut I,
+) -> Result<Vector3<f32>, CliError> {
+    let value = value(option, args)?;
+    let components: Option<Vec<f32>> = value
+        .split(',')
+        .map(|component| component.trim().parse().ok())
+        .collect();
+    match components {
+        Some(components) if components.len() == 3 => {
+            Ok(Vector3::new(components[0], components[1], components[2]))
+        }
+        _ => Err(CliError::InvalidValue {
+            option: String::from(option),
+            value,
+        }),
+    }
+}
+
+fn parse_size(value: &str) -> Option<(u32, u32)> {
+    let mut parts = value.split('x');
+    let width = parts.next()?.parse().ok()?;
+    let height = parts.next()?.parse().ok()?;
+    if parts.next().is_some() || width == 0 || height == 0 {
+        return None;
+    }
+    Some((width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(args: &[&str]) -> Result<Options, CliError> {
+        Options::from_args(args.iter().map(|arg| String::from(*arg)))
+    }
+
+    #[test]
+    fn defaults_and_models() {
+        let parsed = options(&["a.obj", "b.gltf"]).unwrap();
+        assert_eq!(
+            parsed,
+            Options {
+                models: vec![PathBuf::from("a.obj"), PathBuf::from("b.gltf")],
+                ..Options::default()
+            }
+        );
+    }
+
+    #[test]
+    fn every_option() {
+        let parsed = options(&[
+            "--headless",
+            "--output",
+            "out",
+            "--size",
+            "640x480",
+            "--frames",
+            "36",
+            "--focal-length",
+            "35",
+            "--distance",
+            "4.5",
+            "--elevation",
+            "-10",
+            "--light-direction",
+            "0, -1, 1",
+            "--light-color",
+            "1,0.5,0.25",
+            "--light-power",
+            "2",
+            "--ambient",
+            "0",
+            "--background",
+            "0,0,0",
+            "--environment",
+            "sky.hdr",
+            "model.obj",
+        ])
+        .unwrap();
+        assert!(parsed.headless && !parsed.help);
+        assert_eq!(parsed.output, PathBuf::from("out"));
+        assert_eq!((parsed.width, parsed.height), (640, 480));
+        assert_eq!(parsed.frames, 36);
+        assert_eq!(parsed.focal_length, 35.0);
+        assert_eq!(parsed.distance, 4.5);
+        assert_eq!(parsed.elevation, -10.0);
+        assert_eq!(parsed.light_direction, Vector3::new(0.0, -1.0, 1.0));
+        assert_eq!(parsed.light_color, Vector3::new(1.0, 0.5, 0.25));
+        assert_eq!(parsed.light_power, 2.0);
+        assert_eq!(parsed.ambient_strength, 0.0);
+        assert_eq!(parsed.background, Vector3::new(0.0, 0.0, 0.0));
+        assert_eq!(parsed.environment, Some(PathBuf::from("sky.hdr")));
+        assert_eq!(parsed.models, vec![PathBuf::from("model.obj")]);
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("1920x1080"), Some((1920, 1080)));
+        assert_eq!(parse_size("1920"), None);
+        assert_eq!(parse_size("0x1080"), None);
+        assert_eq!(parse_size("1920x1080x2"), None);
+        assert_eq!(parse_size("wide x tall"), None);
+    }
+
+    #[test]
+    fn vectors() {
+        let parse = |value: &str| {
+            let mut args = vec![String::from(value)].into_iter();
+            parse_vector("--background", &mut args).ok()
+        };
+        assert_eq!(parse("1,2,3"), Some(Vector3::new(1.0, 2.0, 3.0)));
+        assert_eq!(parse(" 1 , 2 , 3 "), Some(Vector3::new(1.0, 2.0, 3.0)));
+        assert_eq!(parse("1,2"), None);
+        assert_eq!(parse("1,2,3,4"), None);
+        assert_eq!(parse("1,two,3"), None);
+    }
+
+    #[test]
+    fn errors() {
+        match options(&["--size"]) {
+            Err(CliError::MissingValue(option)) => assert_eq!(option, "--size"),
+            other => panic!("Unexpected result {:?}.", other),
+        }
+        match options(&["--frames", "0"]) {
+            Err(CliError::InvalidValue { option, .. }) => assert_eq!(option, "--frames"),
+            other => panic!("Unexpected result {:?}.", other),
+        }
+        match options(&["--distance", "far"]) {
+            Err(CliError::InvalidValue { option, value }) => {
+                assert_eq!((option.as_str(), value.as_str()), ("--distance", "far"))
+            }
+            other => panic!("Unexpected result {:?}.", other),
+        }
+        match options(&["--fullscreen"]) {
+            Err(CliError::UnknownOption(option)) => assert_eq!(option, "--fullscreen"),
+            other => panic!("Unexpected result {:?}.", other),
+        }
+    }
+}
@@ -1,9 +1,17 @@
+mod animator;
 mod camera;
+mod keyframes;
 mod light;
 mod mesh;
+mod morph_weights;
+mod skeleton;
 mod transform;
 
+pub use self::animator::*;
 pub use self::camera::*;
+pub use self::keyframes::*;
 pub use self::light::*;
 pub use self::mesh::*;
+pub use self::morph_weights::*;
+pub use self::skeleton::*;
 pub use self::transform::*;
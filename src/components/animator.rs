@@ -0,0 +1,69 @@
+use crate::animation::AnimationClip;
+use crate::components::JointPose;
+
+/// Clips of the entity's skeleton and the one playing, cross-faded when it changes.
+///
+/// Clips are sampled at the time of the timeline, fades start at the time `play` was called.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimatorComponent {
+    pub clips: Vec<AnimationClip>,
+    current: Option<usize>,
+    previous: Option<usize>,
+    fade_start: f32,
+    /// Seconds taken to blend from the previous clip to the current one.
+    pub fade_duration: f32,
+}
+
+impl AnimatorComponent {
+    /// Plays the first clip, if any.
+    pub fn new(clips: Vec<AnimationClip>) -> Self {
+        let current = if clips.is_empty() { None } else { Some(0) };
+        AnimatorComponent {
+            clips,
+            current,
+            previous: None,
+            fade_start: 0.0,
+            fade_duration: 0.25,
+        }
+    }
+    pub fn current(&self) -> Option<usize> {
+        self.current
+    }
+    /// Fades to another clip, or to the rest pose for `None`, starting at `time`.
+    pub fn play(&mut self, clip: Option<usize>, time: f32) {
+        if clip == self.current {
+            return;
+        }
+        self.previous = self.current;
+        self.current = clip.filter(|&index| index < self.clips.len());
+        self.fade_start = time;
+    }
+    /// Longest clip, in seconds.
+    pub fn duration(&self) -> f32 {
+        self.clips
+            .iter()
+            .map(AnimationClip::duration)
+            .fold(0.0, f32::max)
+    }
+    /// Pose of the skeleton at `time`.
+    pub fn pose(&self, time: f32, rest: &[JointPose]) -> Vec<JointPose> {
+        let sample = |clip: Option<usize>| match clip {
+            Some(index) => self.clips[index].sample(time, rest),
+            None => rest.to_vec(),
+        };
+        let pose = sample(self.current);
+        let weight = if self.fade_duration > 0.0 {
+            ((time - self.fade_start) / self.fade_duration).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        if weight >= 1.0 || self.previous == self.current {
+            return pose;
+        }
+        sample(self.previous)
+            .iter()
+            .zip(pose.iter())
+            .map(|(from, to)| from.blend(to, weight))
+            .collect()
+    }
+}
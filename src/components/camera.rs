@@ -1,32 +1,83 @@
 use cgmath::prelude::*;
-use cgmath::{Matrix4, PerspectiveFov, Rad, Vector3, Vector4};
-use palantir_lib::TCamera;
+use cgmath::{Matrix4, PerspectiveFov, Rad, Vector3};
+use palantir_lib::{Aabb, TCamera};
+use std::f32::consts::{FRAC_PI_2, PI};
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CameraMode {
+    /// Turns around a target point, `distance` away from it.
+    Orbit,
+    /// Moves freely, rotations turn around the camera itself.
+    Fly,
+}
+
+impl CameraMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            CameraMode::Orbit => "Orbit",
+            CameraMode::Fly => "Fly",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Projection {
+    Perspective,
+    /// Parallel projection, the height of the view is the one of the perspective view at the
+    /// orbit target.
+    Orthographic,
+}
+
+impl Projection {
+    pub const ALL: [Projection; 2] = [Projection::Perspective, Projection::Orthographic];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Projection::Perspective => "Perspective",
+            Projection::Orthographic => "Orthographic",
+        }
+    }
+}
+
+/// Looks at `target` from `distance` away, turned by `yaw` around the world up axis and then
+/// by `pitch` around its own horizontal axis. Angles are in radians.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Camera {
     fov: f32,
+    /// In millimeters, relates the field of view to a focal length.
+    sensor_size: f32,
+    projection: Projection,
     near_clip: f32,
     far_clip: f32,
-    target_matrix: Matrix4<f32>,
-    zoom_matrix: Matrix4<f32>,
-    rotatey_matrix: Matrix4<f32>,
-    rotatex_matrix: Matrix4<f32>,
     aspect: f32,
+    /// Orbit target, the position of the camera itself when flying.
+    target: Vector3<f32>,
+    yaw: f32,
+    /// Clamped so the camera never goes over the poles.
+    pitch: f32,
     distance: f32,
+    mode: CameraMode,
+    /// Units per second when flying.
+    fly_speed: f32,
 }
 
+const MIN_DISTANCE: f32 = 0.01;
+
 impl Camera {
     pub fn new(fov: f32, near_clip: f32, far_clip: f32, aspect: f32) -> Self {
         let mut camera = Camera {
             fov,
+            sensor_size: 36.0,
+            projection: Projection::Perspective,
             near_clip,
             far_clip,
             aspect,
-            target_matrix: Matrix4::<f32>::identity(),
-            zoom_matrix: Matrix4::<f32>::identity(),
-            rotatey_matrix: Matrix4::<f32>::identity(),
-            rotatex_matrix: Matrix4::<f32>::identity(),
+            target: Vector3::zero(),
+            yaw: 0.0,
+            pitch: 0.0,
             distance: 1.0,
+            mode: CameraMode::Orbit,
+            fly_speed: 2.0,
         };
 
         camera.zoom(-3.0);
@@ -45,55 +96,246 @@ impl Camera {
         camera
     }
     pub fn pan(&mut self, x: f32, y: f32) {
-        let mut vector = Vector3::new(x, -y, 0.0);
-        vector *= self.distance * 0.1;
-        let translation_matrix = Matrix4::from_translation(vector);
-        let orientation_matrix: Matrix4<f32> = self.rotatex_matrix * self.rotatey_matrix;
-        self.target_matrix = self.target_matrix
-            * orientation_matrix.inverse_transform().unwrap()
-            * translation_matrix
-            * orientation_matrix;
+        let offset = Vector3::new(-x, y, 0.0) * self.distance * 0.1;
+        self.target += self.view_to_world(offset);
     }
     pub fn zoom(&mut self, amount: f32) {
         let compensated_amount = amount * self.distance * 0.1;
-        let translation = Vector3::new(0.0, 0.0, compensated_amount);
-        self.zoom_matrix = self.zoom_matrix * Matrix4::from_translation(translation);
-        self.distance = f32::abs(self.distance - compensated_amount).max(0.01);
-    }
-    pub fn rotate(&mut self, axis: Vector3<f32>, angle: f32) {
-        let rotation = Matrix4::from_axis_angle(axis, Rad(angle));
-
-        if axis == Vector3::<f32>::unit_y() {
-            self.rotatey_matrix = self.rotatey_matrix * rotation;
-        } else {
-            self.rotatex_matrix = self.rotatex_matrix * rotation;
+        if self.mode == CameraMode::Fly {
+            self.fly(Vector3::new(0.0, 0.0, -compensated_amount));
+            return;
         }
+        self.distance = (self.distance - compensated_amount).max(MIN_DISTANCE);
+    }
+    pub fn distance(&self) -> f32 {
+        self.distance
+    }
+    pub fn set_distance(&mut self, distance: f32) {
+        self.distance = distance.max(MIN_DISTANCE);
+    }
+    /// Turns the camera around its target, or around itself when flying.
+    pub fn rotate(&mut self, yaw: f32, pitch: f32) {
+        self.set_orientation(self.pitch + pitch, self.yaw + yaw);
+    }
+    /// Replaces the orbit rotation, pitch is clamped to straight up or down.
+    pub fn set_orientation(&mut self, pitch: f32, yaw: f32) {
+        self.pitch = pitch.clamp(-FRAC_PI_2, FRAC_PI_2);
+        self.yaw = yaw % (2.0 * PI);
+    }
+    pub fn pitch(&self) -> f32 {
+        self.pitch
+    }
+    pub fn yaw(&self) -> f32 {
+        self.yaw
+    }
+    /// Rotation part of the view matrix.
+    pub fn orientation(&self) -> Matrix4<f32> {
+        Matrix4::from_angle_x(Rad(self.pitch)) * Matrix4::from_angle_y(Rad(self.yaw))
     }
     pub fn focus(&mut self) {
-        //TODO: refacto to focus on selection
-        self.target_matrix.w = Vector4::new(0.0, 0.0, 0.0, 1.0);
+        self.focus_on(Vector3::zero());
+    }
+    /// Moves the orbit target to a world space point, leaving fly mode.
+    pub fn focus_on(&mut self, point: Vector3<f32>) {
+        self.set_mode(CameraMode::Orbit);
+        self.target = point;
+    }
+    /// Orbits around the center of the bounds, from the distance at which their bounding
+    /// sphere fits the view.
+    pub fn frame_bounds(&mut self, bounds: &Aabb) {
+        if bounds.is_empty() {
+            return;
+        }
+        self.focus_on(bounds.center());
+        let radius = (bounds.size().magnitude() * 0.5).max(MIN_DISTANCE);
+        let half_fov_y = self.fov * 0.5;
+        let half_fov_x = f32::atan(f32::tan(half_fov_y) * self.aspect);
+        let half_fov = half_fov_y.min(half_fov_x);
+        let distance = match self.projection {
+            Projection::Perspective => radius / half_fov.sin(),
+            Projection::Orthographic => radius / half_fov.tan(),
+        };
+        self.set_distance(distance);
+    }
+    pub fn mode(&self) -> CameraMode {
+        self.mode
+    }
+    /// Switches between orbit and fly modes without moving the view. Going back to orbit
+    /// turns around the point `distance` in front of the camera.
+    pub fn set_mode(&mut self, mode: CameraMode) {
+        if mode == self.mode {
+            return;
+        }
+        let position = self.position();
+        self.target = match mode {
+            CameraMode::Fly => position,
+            CameraMode::Orbit => position + self.forward() * self.distance,
+        };
+        self.mode = mode;
+    }
+    /// World space point the camera orbits around, its own position when flying.
+    pub fn target(&self) -> Vector3<f32> {
+        self.target
+    }
+    /// World space position of the camera.
+    pub fn position(&self) -> Vector3<f32> {
+        match self.mode {
+            CameraMode::Orbit => self.target - self.forward() * self.distance,
+            CameraMode::Fly => self.target,
+        }
+    }
+    /// World space direction the camera looks at.
+    pub fn forward(&self) -> Vector3<f32> {
+        self.view_to_world(-Vector3::unit_z())
+    }
+    /// Moves the camera by an offset in its own space, x to the right, y up and z backward.
+    pub fn fly(&mut self, offset: Vector3<f32>) {
+        self.target += self.view_to_world(offset);
+    }
+    fn view_to_world(&self, vector: Vector3<f32>) -> Vector3<f32> {
+        // The inverse of a rotation is its transpose.
+        self.orientation().transpose().transform_vector(vector)
+    }
+    pub fn fly_speed(&self) -> f32 {
+        self.fly_speed
+    }
+    pub fn set_fly_speed(&mut self, speed: f32) {
+        self.fly_speed = speed.max(0.01);
     }
     pub fn set_focal_length(&mut self, focal_length: f32, sensor_size: f32) {
         self.fov = 2.0 * f32::atan(sensor_size * 0.5 / focal_length);
+        self.sensor_size = sensor_size;
+    }
+    pub fn focal_length(&self) -> f32 {
+        self.sensor_size * 0.5 / f32::tan(self.fov * 0.5)
+    }
+    pub fn sensor_size(&self) -> f32 {
+        self.sensor_size
+    }
+    pub fn near_clip(&self) -> f32 {
+        self.near_clip
+    }
+    pub fn far_clip(&self) -> f32 {
+        self.far_clip
+    }
+    pub fn set_clip(&mut self, near_clip: f32, far_clip: f32) {
+        self.near_clip = near_clip.max(0.0001);
+        self.far_clip = far_clip.max(self.near_clip + 0.0001);
+    }
+    pub fn projection(&self) -> Projection {
+        self.projection
+    }
+    pub fn set_projection(&mut self, projection: Projection) {
+        self.projection = projection;
+    }
+    pub fn aspect_ratio(&self) -> f32 {
+        self.aspect
     }
     pub fn set_aspect_ratio(&mut self, aspect: f32) {
         self.aspect = aspect;
     }
+    /// View between this camera at `t = 0` and `other` at `t = 1`, both seen as orbiting
+    /// cameras. Yaw takes the shortest way around.
+    pub fn interpolate(&self, other: &Camera, t: f32) -> Camera {
+        let mut from = *self;
+        from.set_mode(CameraMode::Orbit);
+        let mut to = *other;
+        to.set_mode(CameraMode::Orbit);
+
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+        let mut yaw_delta = (to.yaw - from.yaw) % (2.0 * PI);
+        if yaw_delta > PI {
+            yaw_delta -= 2.0 * PI;
+        } else if yaw_delta < -PI {
+            yaw_delta += 2.0 * PI;
+        }
+
+        let mut camera = to;
+        camera.fov = lerp(from.fov, to.fov);
+        camera.sensor_size = lerp(from.sensor_size, to.sensor_size);
+        camera.near_clip = lerp(from.near_clip, to.near_clip);
+        camera.far_clip = lerp(from.far_clip, to.far_clip);
+        camera.aspect = lerp(from.aspect, to.aspect);
+        camera.target = from.target.lerp(to.target, t);
+        camera.distance = lerp(from.distance, to.distance);
+        camera.set_orientation(lerp(from.pitch, to.pitch), from.yaw + yaw_delta * t);
+        camera
+    }
 }
 
 impl TCamera for Camera {
     fn matrix(&self) -> Matrix4<f32> {
-        self.zoom_matrix * self.rotatex_matrix * self.rotatey_matrix * self.target_matrix
+        let distance = match self.mode {
+            CameraMode::Orbit => self.distance,
+            CameraMode::Fly => 0.0,
+        };
+        Matrix4::from_translation(Vector3::new(0.0, 0.0, -distance))
+            * self.orientation()
+            * Matrix4::from_translation(-self.target)
     }
 
     fn projection_matrix(&self) -> Matrix4<f32> {
-        PerspectiveFov {
-            fovy: Rad(self.fov),
-            aspect: self.aspect,
-            near: self.near_clip,
-            far: self.far_clip,
+        match self.projection {
+            Projection::Perspective => PerspectiveFov {
+                fovy: Rad(self.fov),
+                aspect: self.aspect,
+                near: self.near_clip,
+                far: self.far_clip,
+            }
+            .to_perspective()
+            .into(),
+            Projection::Orthographic => {
+                let half_height = self.distance * f32::tan(self.fov * 0.5);
+                let half_width = half_height * self.aspect;
+                cgmath::ortho(
+                    -half_width,
+                    half_width,
+                    -half_height,
+                    half_height,
+                    self.near_clip,
+                    self.far_clip,
+                )
+            }
+        }
+    }
+}
+
+/// A named camera saved in the world, that the viewport can look through.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CameraComponent {
+    pub name: String,
+    pub camera: Camera,
+}
+
+/// Animates the viewport camera from one view to another.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraTransition {
+    from: Camera,
+    to: Camera,
+    elapsed: f32,
+    duration: f32,
+}
+
+impl CameraTransition {
+    pub fn new(from: Camera, to: Camera, duration: f32) -> Self {
+        CameraTransition {
+            from,
+            to,
+            elapsed: 0.0,
+            duration,
+        }
+    }
+    /// Moves the animation forward, returns the camera to show.
+    pub fn advance(&mut self, delta_s: f32) -> Camera {
+        self.elapsed += delta_s;
+        if self.is_finished() {
+            return self.to;
         }
-        .to_perspective()
-        .into()
+        let t = self.elapsed / self.duration;
+        // Smoothstep, starts and ends slowly.
+        self.from.interpolate(&self.to, t * t * (3.0 - 2.0 * t))
+    }
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
     }
 }
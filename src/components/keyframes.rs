@@ -0,0 +1,7 @@
+use crate::curves::CurveSet;
+
+/// Keyframed fields of the entity's transform or camera, written when the timeline moves.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct KeyframesComponent {
+    pub curves: CurveSet,
+}
@@ -1,6 +1,8 @@
 use palantir_lib::Mesh;
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Debug, PartialEq)]
 pub struct MeshComponent {
+    /// Name of the model file, animations refer to the mesh by it.
+    pub name: String,
     pub mesh: Mesh,
 }
@@ -0,0 +1,6 @@
+/// Weights of the blend shapes of the entity's mesh, one per morph target.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MorphWeightsComponent {
+    pub names: Vec<String>,
+    pub weights: Vec<f32>,
+}
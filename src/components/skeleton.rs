@@ -0,0 +1,99 @@
+use cgmath::prelude::*;
+use cgmath::{Matrix4, Quaternion, Vector3};
+
+/// Local transform of a joint, relative to its parent.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct JointPose {
+    pub translation: Vector3<f32>,
+    pub rotation: Quaternion<f32>,
+    pub scale: Vector3<f32>,
+}
+
+impl Default for JointPose {
+    fn default() -> Self {
+        JointPose {
+            translation: Vector3::zero(),
+            rotation: Quaternion::one(),
+            scale: Vector3::new(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+impl JointPose {
+    pub fn matrix(&self) -> Matrix4<f32> {
+        Matrix4::from_translation(self.translation)
+            * Matrix4::from(self.rotation)
+            * Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z)
+    }
+    /// Pose between this one at `t = 0` and `other` at `t = 1`.
+    pub fn blend(&self, other: &JointPose, t: f32) -> JointPose {
+        // Opposite quaternions are the same rotation, the closest one interpolates the short way.
+        let rotation = if self.rotation.dot(other.rotation) < 0.0 {
+            -other.rotation
+        } else {
+            other.rotation
+        };
+        JointPose {
+            translation: self.translation.lerp(other.translation, t),
+            rotation: self.rotation.nlerp(rotation, t),
+            scale: self.scale.lerp(other.scale, t),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Joint {
+    pub name: String,
+    /// Index of the parent joint, which comes before this one in the skeleton.
+    pub parent: Option<usize>,
+    /// Transform of the nodes between the parent joint, or the model root, and this joint.
+    pub offset: Matrix4<f32>,
+    /// From the mesh space to the joint space of the rest pose.
+    pub inverse_bind_matrix: Matrix4<f32>,
+    pub rest: JointPose,
+}
+
+/// Joints deforming the skinned submeshes of the entity's mesh, and their current pose.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SkeletonComponent {
+    joints: Vec<Joint>,
+    pose: Vec<JointPose>,
+    skinning_matrices: Vec<Matrix4<f32>>,
+}
+
+impl SkeletonComponent {
+    /// The joints must be sorted so that parents come before their children.
+    pub fn new(joints: Vec<Joint>) -> Self {
+        let pose = joints.iter().map(|joint| joint.rest).collect();
+        let mut skeleton = SkeletonComponent {
+            joints,
+            pose: Vec::new(),
+            skinning_matrices: Vec::new(),
+        };
+        skeleton.set_pose(pose);
+        skeleton
+    }
+    pub fn rest_pose(&self) -> Vec<JointPose> {
+        self.joints.iter().map(|joint| joint.rest).collect()
+    }
+    /// Moves the joints, `pose` has one element per joint.
+    pub fn set_pose(&mut self, pose: Vec<JointPose>) {
+        let mut globals: Vec<Matrix4<f32>> = Vec::with_capacity(self.joints.len());
+        for (joint, local) in self.joints.iter().zip(pose.iter()) {
+            let parent = joint
+                .parent
+                .map_or_else(Matrix4::identity, |parent| globals[parent]);
+            globals.push(parent * joint.offset * local.matrix());
+        }
+        self.skinning_matrices = globals
+            .iter()
+            .zip(self.joints.iter())
+            .map(|(global, joint)| global * joint.inverse_bind_matrix)
+            .collect();
+        self.pose = pose;
+    }
+    /// Matrices moving the vertices from the rest pose to the current one, in the mesh space.
+    pub fn skinning_matrices(&self) -> &[Matrix4<f32>] {
+        &self.skinning_matrices
+    }
+}
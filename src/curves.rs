@@ -0,0 +1,609 @@
+use crate::animation::{Interpolation, Keyframes, Tangents};
+use crate::components::{Camera, Light};
+use cgmath::prelude::*;
+use cgmath::{Deg, Euler, Matrix3, Matrix4, Quaternion, Rad, Vector3};
+use palantir_lib::TLight;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Component field animated by a curve.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Field {
+    TranslationX,
+    TranslationY,
+    TranslationZ,
+    /// Euler angles in degrees, they may go past a full turn.
+    RotationX,
+    RotationY,
+    RotationZ,
+    ScaleX,
+    ScaleY,
+    ScaleZ,
+    TargetX,
+    TargetY,
+    TargetZ,
+    /// In degrees.
+    Yaw,
+    /// In degrees.
+    Pitch,
+    Distance,
+    FocalLength,
+    LightRed,
+    LightGreen,
+    LightBlue,
+    LightPower,
+}
+
+impl Field {
+    pub const TRANSFORM: [Field; 9] = [
+        Field::TranslationX,
+        Field::TranslationY,
+        Field::TranslationZ,
+        Field::RotationX,
+        Field::RotationY,
+        Field::RotationZ,
+        Field::ScaleX,
+        Field::ScaleY,
+        Field::ScaleZ,
+    ];
+    pub const CAMERA: [Field; 7] = [
+        Field::TargetX,
+        Field::TargetY,
+        Field::TargetZ,
+        Field::Yaw,
+        Field::Pitch,
+        Field::Distance,
+        Field::FocalLength,
+    ];
+    pub const LIGHT: [Field; 4] = [
+        Field::LightRed,
+        Field::LightGreen,
+        Field::LightBlue,
+        Field::LightPower,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Field::TranslationX => "translation_x",
+            Field::TranslationY => "translation_y",
+            Field::TranslationZ => "translation_z",
+            Field::RotationX => "rotation_x",
+            Field::RotationY => "rotation_y",
+            Field::RotationZ => "rotation_z",
+            Field::ScaleX => "scale_x",
+            Field::ScaleY => "scale_y",
+            Field::ScaleZ => "scale_z",
+            Field::TargetX => "target_x",
+            Field::TargetY => "target_y",
+            Field::TargetZ => "target_z",
+            Field::Yaw => "yaw",
+            Field::Pitch => "pitch",
+            Field::Distance => "distance",
+            Field::FocalLength => "focal_length",
+            Field::LightRed => "light_red",
+            Field::LightGreen => "light_green",
+            Field::LightBlue => "light_blue",
+            Field::LightPower => "light_power",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Field::TranslationX => "Translation X",
+            Field::TranslationY => "Translation Y",
+            Field::TranslationZ => "Translation Z",
+            Field::RotationX => "Rotation X",
+            Field::RotationY => "Rotation Y",
+            Field::RotationZ => "Rotation Z",
+            Field::ScaleX => "Scale X",
+            Field::ScaleY => "Scale Y",
+            Field::ScaleZ => "Scale Z",
+            Field::TargetX => "Target X",
+            Field::TargetY => "Target Y",
+            Field::TargetZ => "Target Z",
+            Field::Yaw => "Yaw",
+            Field::Pitch => "Pitch",
+            Field::Distance => "Distance",
+            Field::FocalLength => "Focal Length",
+            Field::LightRed => "Light Red",
+            Field::LightGreen => "Light Green",
+            Field::LightBlue => "Light Blue",
+            Field::LightPower => "Light Power",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Field> {
+        Field::TRANSFORM
+            .iter()
+            .chain(Field::CAMERA.iter())
+            .chain(Field::LIGHT.iter())
+            .copied()
+            .find(|field| field.name() == name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Curve {
+    pub field: Field,
+    pub keyframes: Keyframes<f32>,
+}
+
+/// Curves of the fields of one animated object, at most one per field.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CurveSet {
+    pub curves: Vec<Curve>,
+}
+
+impl CurveSet {
+    pub fn new() -> Self {
+        CurveSet { curves: Vec::new() }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.curves.is_empty()
+    }
+    /// Time of the last keyframe.
+    pub fn duration(&self) -> f32 {
+        self.curves
+            .iter()
+            .map(|curve| curve.keyframes.duration())
+            .fold(0.0, f32::max)
+    }
+    pub fn curve(&self, field: Field) -> Option<&Curve> {
+        self.curves.iter().find(|curve| curve.field == field)
+    }
+    pub fn sample(&self, field: Field, time: f32) -> Option<f32> {
+        self.curve(field)?.keyframes.sample(time)
+    }
+    /// Keys the fields at `time`, new curves ease between their keyframes.
+    pub fn key(&mut self, values: &[(Field, f32)], time: f32) {
+        for &(field, value) in values {
+            match self.curves.iter_mut().find(|curve| curve.field == field) {
+                Some(curve) => {
+                    curve.keyframes.insert(time, value);
+                }
+                None => self.curves.push(Curve {
+                    field,
+                    keyframes: Keyframes::new(vec![time], vec![value], Interpolation::Ease),
+                }),
+            }
+        }
+    }
+    /// Drops the curves whose last keyframe was removed.
+    pub fn remove_empty(&mut self) {
+        self.curves
+            .retain(|curve| !curve.keyframes.times().is_empty());
+    }
+    /// Writes the animated transform fields, the others keep their current value.
+    pub fn apply_transform(&self, time: f32, matrix: &mut Matrix4<f32>) {
+        if !Field::TRANSFORM
+            .iter()
+            .any(|&field| self.curve(field).is_some())
+        {
+            return;
+        }
+        let values: Vec<f32> = transform_values(matrix)
+            .iter()
+            .map(|&(field, value)| self.sample(field, time).unwrap_or(value))
+            .collect();
+        let rotation = Quaternion::from(Euler {
+            x: Deg(values[3]),
+            y: Deg(values[4]),
+            z: Deg(values[5]),
+        });
+        *matrix = Matrix4::from_translation(Vector3::new(values[0], values[1], values[2]))
+            * Matrix4::from(rotation)
+            * Matrix4::from_nonuniform_scale(values[6], values[7], values[8]);
+    }
+    pub fn apply_camera(&self, time: f32, camera: &mut Camera) {
+        let sample = |field, value| self.sample(field, time).unwrap_or(value);
+        let target = camera.target();
+        let target = Vector3::new(
+            sample(Field::TargetX, target.x),
+            sample(Field::TargetY, target.y),
+            sample(Field::TargetZ, target.z),
+        );
+        if target != camera.target() {
+            camera.focus_on(target);
+        }
+        let pitch = sample(Field::Pitch, camera.pitch().to_degrees());
+        let yaw = sample(Field::Yaw, camera.yaw().to_degrees());
+        camera.set_orientation(pitch.to_radians(), yaw.to_radians());
+        camera.set_distance(sample(Field::Distance, camera.distance()));
+        let focal_length = sample(Field::FocalLength, camera.focal_length());
+        camera.set_focal_length(focal_length, camera.sensor_size());
+    }
+    pub fn apply_light(&self, time: f32, light: &mut Light) {
+        let sample = |field, value| self.sample(field, time).unwrap_or(value);
+        let color = light.color();
+        light.set_color(Vector3::new(
+            sample(Field::LightRed, color.x),
+            sample(Field::LightGreen, color.y),
+            sample(Field::LightBlue, color.z),
+        ));
+        light.set_power(sample(Field::LightPower, light.power()));
+    }
+}
+
+/// Translation, Euler rotation and scale of a transform without shear. A mirrored transform
+/// gets a negative X scale, and the axes scaled to zero take their direction from the others.
+pub fn transform_values(matrix: &Matrix4<f32>) -> [(Field, f32); 9] {
+    let translation = matrix.w.truncate();
+    let axes = [
+        matrix.x.truncate(),
+        matrix.y.truncate(),
+        matrix.z.truncate(),
+    ];
+    let mut scale = Vector3::new(
+        axes[0].magnitude(),
+        axes[1].magnitude(),
+        axes[2].magnitude(),
+    );
+    if Matrix3::from_cols(axes[0], axes[1], axes[2]).determinant() < 0.0 {
+        scale.x = -scale.x;
+    }
+    let scales = [scale.x, scale.y, scale.z];
+    let direction = |axis: usize| {
+        if scales[axis].abs() > f32::EPSILON {
+            Some(axes[axis] / scales[axis])
+        } else {
+            None
+        }
+    };
+    let rotation = match [direction(0), direction(1), direction(2)] {
+        [Some(x), Some(y), Some(z)] => Matrix3::from_cols(x, y, z),
+        [None, Some(y), Some(z)] => Matrix3::from_cols(y.cross(z), y, z),
+        [Some(x), None, Some(z)] => Matrix3::from_cols(x, z.cross(x), z),
+        [Some(x), Some(y), None] => Matrix3::from_cols(x, y, x.cross(y)),
+        _ => Matrix3::identity(),
+    };
+    let euler = Euler::from(Quaternion::from(rotation));
+    let degrees = |angle: Rad<f32>| Deg::from(angle).0;
+    [
+        (Field::TranslationX, translation.x),
+        (Field::TranslationY, translation.y),
+        (Field::TranslationZ, translation.z),
+        (Field::RotationX, degrees(euler.x)),
+        (Field::RotationY, degrees(euler.y)),
+        (Field::RotationZ, degrees(euler.z)),
+        (Field::ScaleX, scale.x),
+        (Field::ScaleY, scale.y),
+        (Field::ScaleZ, scale.z),
+    ]
+}
+
+pub fn camera_values(camera: &Camera) -> [(Field, f32); 7] {
+    let target = camera.target();
+    [
+        (Field::TargetX, target.x),
+        (Field::TargetY, target.y),
+        (Field::TargetZ, target.z),
+        (Field::Yaw, camera.yaw().to_degrees()),
+        (Field::Pitch, camera.pitch().to_degrees()),
+        (Field::Distance, camera.distance()),
+        (Field::FocalLength, camera.focal_length()),
+    ]
+}
+
+pub fn light_values(light: &Light) -> [(Field, f32); 4] {
+    let color = light.color();
+    [
+        (Field::LightRed, color.x),
+        (Field::LightGreen, color.y),
+        (Field::LightBlue, color.z),
+        (Field::LightPower, light.power()),
+    ]
+}
+
+/// What the curves of an animation file apply to, meshes and cameras are found by name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AnimationTarget {
+    Light,
+    Mesh(String),
+    Camera(String),
+}
+
+#[derive(Debug)]
+pub enum AnimationError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl From<io::Error> for AnimationError {
+    fn from(other: io::Error) -> Self {
+        AnimationError::Io(other)
+    }
+}
+
+impl std::fmt::Display for AnimationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            AnimationError::Io(err) => err.fmt(f),
+            AnimationError::Parse { line, message } => write!(f, "Line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for AnimationError {}
+
+/// Keyframed curves of a scene, saved next to the models they animate.
+///
+/// ```text
+/// length 10
+/// mesh suzanne
+/// curve rotation_y linear
+/// key 0 0
+/// key 10 360
+/// curve translation_y bezier
+/// key 0 0 0 2
+/// key 5 1 -1 -1
+/// ```
+///
+/// Keys end with their incoming and outgoing tangents, in value per second, unless both are
+/// flat.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AnimationFile {
+    /// Length of the timeline, in seconds.
+    pub length: f32,
+    pub targets: Vec<(AnimationTarget, CurveSet)>,
+}
+
+impl AnimationFile {
+    pub fn load(path: &Path) -> Result<Self, AnimationError> {
+        AnimationFile::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parses the content of an animation file, see `load`.
+    pub fn parse(text: &str) -> Result<Self, AnimationError> {
+        let mut file = AnimationFile::default();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parse_error = |message: String| AnimationError::Parse {
+                line: index + 1,
+                message,
+            };
+            let parse_f32 = |value: Option<&str>| {
+                let value = value.unwrap_or_default();
+                value
+                    .parse::<f32>()
+                    .map_err(|_| parse_error(format!("Invalid number {:?}.", value)))
+            };
+            let mut parts = line.splitn(2, ' ');
+            let keyword = parts.next().unwrap_or_default();
+            let rest = parts.next().unwrap_or_default().trim();
+            let target = match keyword {
+                "length" => {
+                    file.length = parse_f32(Some(rest))?;
+                    continue;
+                }
+                "light" => Some(AnimationTarget::Light),
+                "mesh" => Some(AnimationTarget::Mesh(rest.to_owned())),
+                "camera" => Some(AnimationTarget::Camera(rest.to_owned())),
+                _ => None,
+            };
+            if let Some(target) = target {
+                file.targets.push((target, CurveSet::new()));
+                continue;
+            }
+
+            let curves = match file.targets.last_mut() {
+                Some((_, curves)) => curves,
+                None => return Err(parse_error(String::from("Expected a target first."))),
+            };
+            let mut values = rest.split_whitespace();
+            match keyword {
+                "curve" => {
+                    let name = values.next().unwrap_or_default();
+                    let field = Field::from_name(name)
+                        .ok_or_else(|| parse_error(format!("Unknown field {:?}.", name)))?;
+                    let name = values.next().unwrap_or_default();
+                    let interpolation = Interpolation::from_name(name)
+                        .ok_or_else(|| parse_error(format!("Unknown interpolation {:?}.", name)))?;
+                    if curves.curve(field).is_some() {
+                        return Err(parse_error(format!("Duplicate curve {:?}.", field.name())));
+                    }
+                    curves.curves.push(Curve {
+                        field,
+                        keyframes: Keyframes::new(Vec::new(), Vec::new(), interpolation),
+                    });
+                }
+                "key" => {
+                    let time = parse_f32(values.next())?;
+                    let value = parse_f32(values.next())?;
+                    let tangents = match (values.next(), values.next()) {
+                        (None, _) => None,
+                        (incoming, outgoing) => Some(Tangents {
+                            incoming: parse_f32(incoming)?,
+                            outgoing: parse_f32(outgoing)?,
+                        }),
+                    };
+                    if let Some(extra) = values.next() {
+                        return Err(parse_error(format!("Unexpected {:?}.", extra)));
+                    }
+                    let curve = curves
+                        .curves
+                        .last_mut()
+                        .ok_or_else(|| parse_error(String::from("Expected a curve first.")))?;
+                    let index = curve.keyframes.insert(time, value);
+                    if let Some(tangents) = tangents {
+                        curve.keyframes.set_tangents(index, tangents);
+                    }
+                }
+                _ => return Err(parse_error(format!("Unknown keyword {:?}.", keyword))),
+            }
+        }
+        Ok(file)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.serialize())
+    }
+
+    /// Content of the animation file written by `save`.
+    pub fn serialize(&self) -> String {
+        let mut content = String::from("# Palantir animation\n");
+        content.push_str(&format!("length {}\n", self.length));
+        for (target, curves) in &self.targets {
+            match target {
+                AnimationTarget::Light => content.push_str("light\n"),
+                AnimationTarget::Mesh(name) => content.push_str(&format!("mesh {}\n", name)),
+                AnimationTarget::Camera(name) => content.push_str(&format!("camera {}\n", name)),
+            }
+            for curve in &curves.curves {
+                content.push_str(&format!(
+                    "curve {} {}\n",
+                    curve.field.name(),
+                    curve.keyframes.interpolation().name()
+                ));
+                let keyframes = &curve.keyframes;
+                let keys = keyframes
+                    .times()
+                    .iter()
+                    .zip(keyframes.values())
+                    .zip(keyframes.tangents());
+                for ((time, value), tangents) in keys {
+                    if *tangents == Tangents::flat() {
+                        content.push_str(&format!("key {} {}\n", time, value));
+                    } else {
+                        content.push_str(&format!(
+                            "key {} {} {} {}\n",
+                            time, value, tangents.incoming, tangents.outgoing
+                        ));
+                    }
+                }
+            }
+        }
+        content
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(values: &[(Field, f32)], field: Field) -> f32 {
+        values
+            .iter()
+            .find(|(other, _)| *other == field)
+            .map(|(_, value)| *value)
+            .unwrap()
+    }
+
+    #[test]
+    fn animation_file_round_trip() {
+        let mut curves = CurveSet::new();
+        curves.key(&[(Field::RotationY, 0.0), (Field::ScaleX, 1.0)], 0.0);
+        curves.key(&[(Field::RotationY, 360.0)], 10.0);
+        curves.curves[1]
+            .keyframes
+            .set_interpolation(Interpolation::Step);
+        curves.key(&[(Field::TranslationY, 1.0)], 5.0);
+        let bezier = &mut curves.curves[2].keyframes;
+        bezier.set_interpolation(Interpolation::Bezier);
+        bezier.insert(0.0, 0.0);
+        bezier.set_tangents(
+            0,
+            Tangents {
+                incoming: 0.0,
+                outgoing: 2.0,
+            },
+        );
+        let file = AnimationFile {
+            length: 10.0,
+            targets: vec![
+                (AnimationTarget::Mesh(String::from("suzanne")), curves),
+                (AnimationTarget::Light, CurveSet::new()),
+            ],
+        };
+        assert_eq!(AnimationFile::parse(&file.serialize()).unwrap(), file);
+    }
+
+    #[test]
+    fn animation_file_parse() {
+        let file = AnimationFile::parse(
+            "# comment\nlength 4\ncamera main view\ncurve yaw linear\nkey 0 10\nkey 4 50\n",
+        )
+        .unwrap();
+        assert_eq!(file.length, 4.0);
+        let (target, curves) = &file.targets[0];
+        assert_eq!(*target, AnimationTarget::Camera(String::from("main view")));
+        assert_eq!(curves.sample(Field::Yaw, 1.0), Some(20.0));
+    }
+
+    #[test]
+    fn animation_file_tangents() {
+        let file = AnimationFile::parse(
+            "light\ncurve light_power bezier\nkey 0 0 0 1\nkey 3 3 1 -1\nkey 6 0\n",
+        )
+        .unwrap();
+        let keyframes = &file.targets[0].1.curves[0].keyframes;
+        assert_eq!(keyframes.interpolation(), Interpolation::Bezier);
+        assert_eq!(
+            keyframes.tangents()[1],
+            Tangents {
+                incoming: 1.0,
+                outgoing: -1.0,
+            }
+        );
+        assert_eq!(keyframes.tangents()[2], Tangents::flat());
+        // Tangents along the line from the first keyframe to the second.
+        assert!((keyframes.sample(1.0).unwrap() - 1.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn animation_file_errors() {
+        let missing_target = AnimationFile::parse("length 4\nkey 0 1\n");
+        let unknown_interpolation = AnimationFile::parse("light\ncurve light_power cubic\n");
+        let duplicate_curve =
+            AnimationFile::parse("light\ncurve light_power linear\ncurve light_power step\n");
+        let missing_tangent = AnimationFile::parse("light\ncurve light_power bezier\nkey 0 1 2\n");
+        match missing_target {
+            Err(AnimationError::Parse { line, .. }) => assert_eq!(line, 2),
+            other => panic!("Unexpected result {:?}.", other),
+        }
+        match unknown_interpolation {
+            Err(AnimationError::Parse { line, .. }) => assert_eq!(line, 2),
+            other => panic!("Unexpected result {:?}.", other),
+        }
+        match duplicate_curve {
+            Err(AnimationError::Parse { line, .. }) => assert_eq!(line, 3),
+            other => panic!("Unexpected result {:?}.", other),
+        }
+        match missing_tangent {
+            Err(AnimationError::Parse { line, .. }) => assert_eq!(line, 3),
+            other => panic!("Unexpected result {:?}.", other),
+        }
+    }
+
+    #[test]
+    fn transform_values_zero_scale() {
+        let matrix =
+            Matrix4::from_angle_y(Deg(90.0)) * Matrix4::from_nonuniform_scale(2.0, 0.0, 1.0);
+        let values = transform_values(&matrix);
+        assert!(values.iter().all(|(_, value)| value.is_finite()));
+        assert!((value(&values, Field::RotationY) - 90.0).abs() < 0.01);
+        assert_eq!(value(&values, Field::ScaleY), 0.0);
+
+        let values = transform_values(&Matrix4::from_scale(0.0));
+        assert!(values.iter().all(|(_, value)| value.is_finite()));
+    }
+
+    #[test]
+    fn transform_values_mirrored() {
+        let matrix =
+            Matrix4::from_angle_z(Deg(30.0)) * Matrix4::from_nonuniform_scale(1.0, -2.0, 1.0);
+        let mut rebuilt = Matrix4::identity();
+        let mut curves = CurveSet::new();
+        curves.key(&transform_values(&matrix), 0.0);
+        curves.apply_transform(0.0, &mut rebuilt);
+        for (column, expected) in [rebuilt.x, rebuilt.y, rebuilt.z, rebuilt.w]
+            .iter()
+            .zip(&[matrix.x, matrix.y, matrix.z, matrix.w])
+        {
+            assert!((column - expected).magnitude() < 0.001);
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use crate::import::ImportSettings;
+use crate::loader::{AssetLoader, LoadTarget};
+use crate::picking::{pick_entity, Ray};
+use crate::resources::Resources;
+use crate::Scene;
+use cgmath::Matrix4;
+use legion::prelude::*;
+use std::path::Path;
+
+const MESH_EXTENSIONS: [&str; 3] = ["obj", "gltf", "glb"];
+const IMAGE_EXTENSIONS: [&str; 6] = ["png", "jpg", "jpeg", "bmp", "tga", "tif"];
+
+/// Starts loading a file dropped on the window.
+///
+/// Meshes are placed where the cursor points on the ground, images become the diffuse texture
+/// of the selected mesh, or of the mesh under the cursor when nothing is selected.
+pub fn on_file_dropped(
+    path: &Path,
+    cursor: (f32, f32),
+    window_size: (f32, f32),
+    scene: &Scene,
+    world: &World,
+    loader: &mut AssetLoader,
+    resources: &Resources,
+) {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let ray = match Ray::from_screen(
+        scene.camera(),
+        cursor.0,
+        cursor.1,
+        window_size.0,
+        window_size.1,
+    ) {
+        Some(ray) => ray,
+        None => {
+            eprintln!("Cannot place {}, the view is degenerate.", path.display());
+            return;
+        }
+    };
+
+    if MESH_EXTENSIONS.contains(&extension.as_str()) {
+        let position = ray
+            .intersect_ground()
+            .unwrap_or_else(|| ray.at(scene.camera().distance()));
+        let settings: ImportSettings = *scene.import_settings();
+        let target = LoadTarget::Spawn(Matrix4::from_translation(position));
+        loader.load_model(path, resources, settings, target);
+    } else if IMAGE_EXTENSIONS.contains(&extension.as_str()) {
+        match scene.selection().or_else(|| pick_entity(world, &ray)) {
+            Some(entity) => loader.load_image(path, LoadTarget::DiffuseTexture(entity)),
+            None => eprintln!(
+                "Select a mesh before dropping {} to use it as texture.",
+                path.display()
+            ),
+        }
+    } else {
+        eprintln!("Unsupported file format: {}", path.display());
+    }
+}
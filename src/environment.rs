@@ -0,0 +1,34 @@
+use crate::resources::Resources;
+use palantir_lib::{
+    Environment, EnvironmentBaker, EnvironmentShaders, HdrImage, ShaderError, ShaderProgram, Skybox,
+};
+use std::error::Error;
+use std::path::Path;
+
+pub fn create_environment_baker(resources: &Resources) -> Result<EnvironmentBaker, ShaderError> {
+    let load = |name: &str| {
+        ShaderProgram::from_path(resources.resource_name_to_path(&format!("shaders/ibl/{}", name)))
+    };
+    let shaders = EnvironmentShaders {
+        equirectangular_to_cubemap: load("equirectangular_to_cubemap.glsl")?,
+        irradiance: load("irradiance.glsl")?,
+        prefilter: load("prefilter.glsl")?,
+        brdf: load("brdf.glsl")?,
+    };
+    Ok(EnvironmentBaker::new(shaders))
+}
+
+pub fn create_skybox(resources: &Resources) -> Result<Skybox, ShaderError> {
+    let shader =
+        ShaderProgram::from_path(resources.resource_name_to_path("shaders/ibl/skybox.glsl"))?;
+    Ok(Skybox::new(shader))
+}
+
+/// Reads an equirectangular `.hdr` image and bakes its lighting maps.
+pub fn load_environment(
+    baker: &mut EnvironmentBaker,
+    path: &Path,
+) -> Result<Environment, Box<dyn Error>> {
+    let image = HdrImage::open(path)?;
+    Ok(baker.bake(&image))
+}
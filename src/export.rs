@@ -0,0 +1,667 @@
+use crate::material_file::MaterialFile;
+use cgmath::prelude::*;
+use cgmath::{Matrix3, Matrix4, Vector3};
+use gltf::json;
+use gltf::json::accessor::{self, ComponentType, GenericComponentType};
+use gltf::json::buffer::View;
+use gltf::json::material::{
+    AlphaCutoff, AlphaMode, PbrBaseColorFactor, PbrMetallicRoughness, StrengthFactor,
+};
+use gltf::json::mesh::{Mode, Primitive, Semantic};
+use gltf::json::texture::{self, Sampler, WrappingMode};
+use gltf::json::validation::Checked::Valid;
+use gltf::json::{Accessor, Node};
+use palantir_lib::{BlendMode, Material, Vertex};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum ExportError {
+    Io(io::Error),
+    Glb(gltf::Error),
+}
+
+impl std::fmt::Display for ExportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ExportError::Io(err) => err.fmt(f),
+            ExportError::Glb(err) => err.fmt(f),
+        }
+    }
+}
+
+impl From<io::Error> for ExportError {
+    fn from(other: io::Error) -> Self {
+        ExportError::Io(other)
+    }
+}
+impl From<gltf::Error> for ExportError {
+    fn from(other: gltf::Error) -> Self {
+        ExportError::Glb(other)
+    }
+}
+impl std::error::Error for ExportError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// With a material library next to it.
+    Obj,
+    /// With its buffer in a `.bin` file next to it.
+    Gltf,
+    /// Binary glTF, a single file.
+    Glb,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 3] = [ExportFormat::Obj, ExportFormat::Gltf, ExportFormat::Glb];
+
+    /// Extension of the exported file.
+    pub fn name(self) -> &'static str {
+        match self {
+            ExportFormat::Obj => "obj",
+            ExportFormat::Gltf => "gltf",
+            ExportFormat::Glb => "glb",
+        }
+    }
+    pub fn label(self) -> &'static str {
+        match self {
+            ExportFormat::Obj => "OBJ",
+            ExportFormat::Gltf => "glTF",
+            ExportFormat::Glb => "glTF Binary",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ExportSettings {
+    pub format: ExportFormat,
+    /// Writes the vertices in world space. Otherwise glTF nodes keep the transforms, and OBJ,
+    /// which has none, loses them.
+    pub bake_transforms: bool,
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        ExportSettings {
+            format: ExportFormat::Gltf,
+            bake_transforms: false,
+        }
+    }
+}
+
+pub struct ExportedSubmesh {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    pub material_index: Option<usize>,
+}
+
+/// Full detail geometry of a mesh of the scene, in its rest pose.
+pub struct ExportedMesh {
+    pub name: String,
+    /// World transform of the mesh.
+    pub matrix: Matrix4<f32>,
+    pub submeshes: Vec<ExportedSubmesh>,
+    pub materials: Vec<MaterialFile>,
+}
+
+impl ExportedMesh {
+    /// Vertices and indices of a submesh, in world space when `bake` is set.
+    fn geometry(&self, submesh: &ExportedSubmesh, bake: bool) -> (Vec<Vertex>, Vec<u32>) {
+        if !bake || self.matrix.is_identity() {
+            return (submesh.vertices.clone(), submesh.indices.clone());
+        }
+        let linear = Matrix3::from_cols(
+            self.matrix.x.truncate(),
+            self.matrix.y.truncate(),
+            self.matrix.z.truncate(),
+        );
+        let normal_matrix = linear
+            .invert()
+            .map(|inverse| inverse.transpose())
+            .unwrap_or(linear);
+        let vertices = submesh
+            .vertices
+            .iter()
+            .map(|vertex| Vertex {
+                position: (self.matrix * vertex.position.extend(1.0)).truncate(),
+                normal: (normal_matrix * vertex.normal).normalize(),
+                uv: vertex.uv,
+            })
+            .collect();
+        let mut indices = submesh.indices.clone();
+        // Mirroring turns the triangles inside out.
+        if linear.determinant() < 0.0 {
+            for triangle in indices.chunks_mut(3) {
+                triangle.swap(1, 2);
+            }
+        }
+        (vertices, indices)
+    }
+}
+
+pub fn export(
+    path: &Path,
+    meshes: &[ExportedMesh],
+    settings: &ExportSettings,
+) -> Result<(), ExportError> {
+    match settings.format {
+        ExportFormat::Obj => write_obj(path, meshes, settings.bake_transforms)?,
+        ExportFormat::Gltf => write_gltf(path, meshes, settings.bake_transforms, false)?,
+        ExportFormat::Glb => write_gltf(path, meshes, settings.bake_transforms, true)?,
+    }
+    Ok(())
+}
+
+/// Name usable in the `usemtl` statements, which end at the first space.
+fn obj_name(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join("_")
+}
+
+fn write_obj(path: &Path, meshes: &[ExportedMesh], bake: bool) -> io::Result<()> {
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    let mtl_path = path.with_extension("mtl");
+    let mtl_name = mtl_path
+        .file_name()
+        .map(|file_name| file_name.to_string_lossy().into_owned());
+    let (obj, mtl) = obj_content(meshes, bake, directory, mtl_name.as_deref());
+    fs::write(path, obj)?;
+    fs::write(mtl_path, mtl)
+}
+
+/// Content of the OBJ and MTL files, texture paths are relative to `directory`.
+fn obj_content(
+    meshes: &[ExportedMesh],
+    bake: bool,
+    directory: &Path,
+    mtl_name: Option<&str>,
+) -> (String, String) {
+    let mut obj = String::from("# Palantir export\n");
+    let mut mtl = String::from("# Palantir export\n");
+    if let Some(mtl_name) = mtl_name {
+        obj.push_str(&format!("mtllib {}\n", mtl_name));
+    }
+    // OBJ indices start at 1 and are shared by the whole file.
+    let mut offset = 1;
+    let mut used_names = HashSet::new();
+    let mut uses_default = false;
+    for (mesh_index, mesh) in meshes.iter().enumerate() {
+        // Meshes loaded twice share their name, the second one gets its index appended.
+        let mut name = obj_name(&mesh.name);
+        if !used_names.insert(name.clone()) {
+            name = format!("{}_{}", name, mesh_index);
+            while !used_names.insert(name.clone()) {
+                name.push('_');
+            }
+        }
+        // Unique too, the material index follows the last underscore.
+        let material_names: Vec<String> = (0..mesh.materials.len())
+            .map(|index| format!("{}_{}", name, index))
+            .collect();
+        for (material, material_name) in mesh.materials.iter().zip(&material_names) {
+            mtl.push_str(&material.mtl_entry(material_name, directory));
+        }
+        obj.push_str(&format!("o {}\n", name));
+        for submesh in &mesh.submeshes {
+            let (vertices, indices) = mesh.geometry(submesh, bake);
+            for vertex in &vertices {
+                let Vertex {
+                    position,
+                    normal,
+                    uv,
+                } = vertex;
+                obj.push_str(&format!("v {} {} {}\n", position.x, position.y, position.z));
+                obj.push_str(&format!("vt {} {}\n", uv.x, uv.y));
+                obj.push_str(&format!("vn {} {} {}\n", normal.x, normal.y, normal.z));
+            }
+            match submesh
+                .material_index
+                .and_then(|index| material_names.get(index))
+            {
+                Some(material_name) => obj.push_str(&format!("usemtl {}\n", material_name)),
+                None => {
+                    obj.push_str("usemtl default\n");
+                    uses_default = true;
+                }
+            }
+            for triangle in indices.chunks(3) {
+                obj.push('f');
+                for index in triangle {
+                    let index = offset + *index as usize;
+                    obj.push_str(&format!(" {}/{}/{}", index, index, index));
+                }
+                obj.push('\n');
+            }
+            offset += vertices.len();
+        }
+    }
+    if uses_default {
+        let white = Material::new(Vector3::new(1.0, 1.0, 1.0), None);
+        mtl.push_str(&MaterialFile::new(&white, None).mtl_entry("default", directory));
+    }
+    (obj, mtl)
+}
+
+/// Binary buffer and JSON document of a glTF file being written.
+#[derive(Default)]
+struct GltfBuilder {
+    buffer: Vec<u8>,
+    root: json::Root,
+}
+
+impl GltfBuilder {
+    /// Appends data to the buffer and returns the index of its view.
+    fn push_view(&mut self, data: &[u8], target: json::buffer::Target) -> json::Index<View> {
+        let offset = self.buffer.len();
+        self.buffer.extend_from_slice(data);
+        self.root.buffer_views.push(View {
+            buffer: json::Index::new(0),
+            byte_length: data.len() as u32,
+            byte_offset: Some(offset as u32),
+            byte_stride: None,
+            name: None,
+            target: Some(Valid(target)),
+            extensions: None,
+            extras: Default::default(),
+        });
+        json::Index::new(self.root.buffer_views.len() as u32 - 1)
+    }
+    fn push_accessor(
+        &mut self,
+        view: json::Index<View>,
+        component_type: ComponentType,
+        count: usize,
+        type_: accessor::Type,
+        bounds: Option<(Vec<f32>, Vec<f32>)>,
+    ) -> json::Index<Accessor> {
+        let (min, max) = match bounds {
+            Some((min, max)) => (Some(json::Value::from(min)), Some(json::Value::from(max))),
+            None => (None, None),
+        };
+        self.root.accessors.push(Accessor {
+            buffer_view: Some(view),
+            byte_offset: 0,
+            count: count as u32,
+            component_type: Valid(GenericComponentType(component_type)),
+            extensions: None,
+            extras: Default::default(),
+            type_: Valid(type_),
+            min,
+            max,
+            name: None,
+            normalized: false,
+            sparse: None,
+        });
+        json::Index::new(self.root.accessors.len() as u32 - 1)
+    }
+    fn push_floats(&mut self, values: &[f32], components: usize) -> json::Index<Accessor> {
+        let data: Vec<u8> = values
+            .iter()
+            .flat_map(|value| value.to_le_bytes().to_vec())
+            .collect();
+        let view = self.push_view(&data, json::buffer::Target::ArrayBuffer);
+        let type_ = match components {
+            2 => accessor::Type::Vec2,
+            _ => accessor::Type::Vec3,
+        };
+        // Required for the positions, written for every attribute. Invalid values are left
+        // out, JSON has no NaN or infinity.
+        let mut min = vec![f32::INFINITY; components];
+        let mut max = vec![f32::NEG_INFINITY; components];
+        for element in values.chunks(components) {
+            for (i, value) in element.iter().enumerate().filter(|(_, v)| v.is_finite()) {
+                min[i] = min[i].min(*value);
+                max[i] = max[i].max(*value);
+            }
+        }
+        let bounds = if values.is_empty() {
+            None
+        } else {
+            Some((
+                min.iter().map(|value| finite_or(*value, 0.0)).collect(),
+                max.iter().map(|value| finite_or(*value, 0.0)).collect(),
+            ))
+        };
+        let count = values.len() / components;
+        self.push_accessor(view, ComponentType::F32, count, type_, bounds)
+    }
+    fn push_indices(&mut self, indices: &[u32]) -> json::Index<Accessor> {
+        let data: Vec<u8> = indices
+            .iter()
+            .flat_map(|index| index.to_le_bytes().to_vec())
+            .collect();
+        let view = self.push_view(&data, json::buffer::Target::ElementArrayBuffer);
+        let count = indices.len();
+        self.push_accessor(
+            view,
+            ComponentType::U32,
+            count,
+            accessor::Type::Scalar,
+            None,
+        )
+    }
+}
+
+fn finite_or(value: f32, default: f32) -> f32 {
+    if value.is_finite() {
+        value
+    } else {
+        default
+    }
+}
+
+fn write_gltf(
+    path: &Path,
+    meshes: &[ExportedMesh],
+    bake: bool,
+    binary: bool,
+) -> Result<(), ExportError> {
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut builder = gltf_content(meshes, bake, directory);
+    if !builder.buffer.is_empty() {
+        let uri = if binary {
+            None
+        } else {
+            let bin_path = path.with_extension("bin");
+            fs::write(&bin_path, &builder.buffer)?;
+            bin_path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        };
+        builder.root.buffers.push(json::Buffer {
+            byte_length: builder.buffer.len() as u32,
+            name: None,
+            uri,
+            extensions: None,
+            extras: Default::default(),
+        });
+    }
+    let json = json::serialize::to_vec(&builder.root)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+    if binary {
+        let glb = gltf::binary::Glb {
+            header: gltf::binary::Header {
+                magic: *b"glTF",
+                version: 2,
+                length: 0,
+            },
+            json: json.into(),
+            bin: if builder.buffer.is_empty() {
+                None
+            } else {
+                Some(builder.buffer.into())
+            },
+        };
+        glb.to_writer(fs::File::create(path)?)?;
+    } else {
+        fs::write(path, json)?;
+    }
+    Ok(())
+}
+
+/// Nodes, meshes and materials of the exported scene, without the buffer of the geometry
+/// which depends on the file format. The textures are referenced relative to `directory`.
+fn gltf_content(meshes: &[ExportedMesh], bake: bool, directory: &Path) -> GltfBuilder {
+    let mut builder = GltfBuilder::default();
+    let mut image_indices = HashMap::new();
+
+    for mesh in meshes {
+        let material_offset = builder.root.materials.len();
+        for material in &mesh.materials {
+            let material =
+                gltf_material(material, directory, &mut builder.root, &mut image_indices);
+            builder.root.materials.push(material);
+        }
+        let mut primitives = Vec::new();
+        for submesh in &mesh.submeshes {
+            let (vertices, indices) = mesh.geometry(submesh, bake);
+            if indices.is_empty() {
+                continue;
+            }
+            let positions: Vec<f32> = vertices
+                .iter()
+                .flat_map(|vertex| vec![vertex.position.x, vertex.position.y, vertex.position.z])
+                .collect();
+            let normals: Vec<f32> = vertices
+                .iter()
+                .flat_map(|vertex| vec![vertex.normal.x, vertex.normal.y, vertex.normal.z])
+                .collect();
+            // glTF UVs start at the top of the image.
+            let uvs: Vec<f32> = vertices
+                .iter()
+                .flat_map(|vertex| vec![vertex.uv.x, 1.0 - vertex.uv.y])
+                .collect();
+            let mut attributes = HashMap::new();
+            attributes.insert(
+                Valid(Semantic::Positions),
+                builder.push_floats(&positions, 3),
+            );
+            attributes.insert(Valid(Semantic::Normals), builder.push_floats(&normals, 3));
+            attributes.insert(Valid(Semantic::TexCoords(0)), builder.push_floats(&uvs, 2));
+            let material = submesh
+                .material_index
+                .filter(|index| *index < mesh.materials.len())
+                .map(|index| json::Index::new((material_offset + index) as u32));
+            primitives.push(Primitive {
+                attributes,
+                extensions: None,
+                extras: Default::default(),
+                indices: Some(builder.push_indices(&indices)),
+                material,
+                mode: Valid(Mode::Triangles),
+                targets: None,
+            });
+        }
+        if primitives.is_empty() {
+            continue;
+        }
+        builder.root.meshes.push(json::Mesh {
+            extensions: None,
+            extras: Default::default(),
+            name: Some(mesh.name.clone()),
+            primitives,
+            weights: None,
+        });
+        let matrix: &[f32; 16] = mesh.matrix.as_ref();
+        let matrix = if !bake
+            && !mesh.matrix.is_identity()
+            && matrix.iter().all(|value| value.is_finite())
+        {
+            Some(*matrix)
+        } else {
+            None
+        };
+        builder.root.nodes.push(Node {
+            camera: None,
+            children: None,
+            extensions: None,
+            extras: Default::default(),
+            matrix,
+            mesh: Some(json::Index::new(builder.root.meshes.len() as u32 - 1)),
+            name: Some(mesh.name.clone()),
+            rotation: None,
+            scale: None,
+            translation: None,
+            skin: None,
+            weights: None,
+        });
+    }
+
+    builder.root.asset.generator = Some(String::from("Palantir"));
+    builder.root.scenes.push(json::Scene {
+        extensions: None,
+        extras: Default::default(),
+        name: None,
+        nodes: (0..builder.root.nodes.len())
+            .map(|index| json::Index::new(index as u32))
+            .collect(),
+    });
+    builder.root.scene = Some(json::Index::new(0));
+    builder
+}
+
+/// A material, its texture is referenced by a path relative to `directory`, added to the
+/// images and textures of `root` the first time.
+fn gltf_material(
+    material: &MaterialFile,
+    directory: &Path,
+    root: &mut json::Root,
+    image_indices: &mut HashMap<String, u32>,
+) -> json::Material {
+    // The texture replaces the diffuse color when rendered, glTF multiplies them.
+    let color = match material.diffuse_texture {
+        Some(_) => Vector3::new(1.0, 1.0, 1.0),
+        None => material.diffuse,
+    };
+    let base_color_texture = material.texture_path_from(directory).map(|uri| {
+        let index = *image_indices.entry(uri.clone()).or_insert_with(|| {
+            if root.samplers.is_empty() {
+                root.samplers.push(Sampler {
+                    mag_filter: None,
+                    min_filter: None,
+                    name: None,
+                    wrap_s: Valid(WrappingMode::Repeat),
+                    wrap_t: Valid(WrappingMode::Repeat),
+                    extensions: None,
+                    extras: Default::default(),
+                });
+            }
+            root.images.push(json::Image {
+                buffer_view: None,
+                mime_type: None,
+                name: None,
+                uri: Some(uri),
+                extensions: None,
+                extras: Default::default(),
+            });
+            root.textures.push(json::Texture {
+                name: None,
+                sampler: Some(json::Index::new(0)),
+                source: json::Index::new(root.images.len() as u32 - 1),
+                extensions: None,
+                extras: Default::default(),
+            });
+            root.textures.len() as u32 - 1
+        });
+        texture::Info {
+            index: json::Index::new(index),
+            tex_coord: 0,
+            extensions: None,
+            extras: Default::default(),
+        }
+    });
+    if let Some(texture) = &material.alpha_texture {
+        eprintln!(
+            "glTF has no separate alpha texture, {} is not exported. Use a diffuse texture \
+             with an alpha channel instead.",
+            texture.display()
+        );
+    }
+    let (alpha_mode, alpha_cutoff) = match material.blend_mode {
+        BlendMode::Opaque => (AlphaMode::Opaque, 0.5),
+        BlendMode::Masked => (AlphaMode::Mask, finite_or(material.alpha_cutoff, 0.5)),
+        BlendMode::Blended => (AlphaMode::Blend, 0.5),
+    };
+    json::Material {
+        alpha_cutoff: AlphaCutoff(alpha_cutoff),
+        alpha_mode: Valid(alpha_mode),
+        pbr_metallic_roughness: PbrMetallicRoughness {
+            base_color_factor: PbrBaseColorFactor([
+                finite_or(color.x, 1.0),
+                finite_or(color.y, 1.0),
+                finite_or(color.z, 1.0),
+                finite_or(material.opacity, 1.0),
+            ]),
+            base_color_texture,
+            metallic_factor: StrengthFactor(finite_or(material.metallic, 0.0)),
+            roughness_factor: StrengthFactor(finite_or(material.roughness, 0.5)),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{Vector2, Vector3};
+    use std::path::PathBuf;
+
+    fn triangle(material_index: Option<usize>) -> ExportedSubmesh {
+        let vertex = |x: f32| Vertex {
+            position: Vector3::new(x, 0.0, 1.0),
+            normal: Vector3::unit_y(),
+            uv: Vector2::new(0.0, 0.0),
+        };
+        ExportedSubmesh {
+            vertices: vec![vertex(0.0), vertex(1.0), vertex(2.0)],
+            indices: vec![0, 1, 2],
+            material_index,
+        }
+    }
+
+    #[test]
+    fn obj_unique_names() {
+        let material =
+            || MaterialFile::new(&Material::new(Vector3::new(1.0, 0.0, 0.0), None), None);
+        let mesh = |name: &str| ExportedMesh {
+            name: String::from(name),
+            matrix: Matrix4::identity(),
+            submeshes: vec![triangle(Some(0)), triangle(None)],
+            materials: vec![material()],
+        };
+        let meshes = [mesh("rock"), mesh("rock"), mesh("rock 1"), mesh("rock_1")];
+        let (obj, mtl) = obj_content(&meshes, false, Path::new("/scene"), Some("scene.mtl"));
+
+        let lines = |text: &str, keyword: &str| -> Vec<String> {
+            text.lines()
+                .filter_map(|line| line.strip_prefix(keyword))
+                .map(str::to_owned)
+                .collect()
+        };
+        let objects = lines(&obj, "o ");
+        assert_eq!(objects, ["rock", "rock_1", "rock_1_2", "rock_1_3"]);
+        let materials = lines(&mtl, "newmtl ");
+        assert_eq!(
+            materials,
+            ["rock_0", "rock_1_0", "rock_1_2_0", "rock_1_3_0", "default"]
+        );
+        // Every material used is defined.
+        for used in lines(&obj, "usemtl ") {
+            assert!(materials.contains(&used), "{} is not defined.", used);
+        }
+        assert!(obj.starts_with("# Palantir export\nmtllib scene.mtl\n"));
+        // Indices continue over the meshes.
+        assert!(obj
+            .lines()
+            .any(|line| line == "f 22/22/22 23/23/23 24/24/24"));
+    }
+
+    #[test]
+    fn gltf_without_invalid_numbers() {
+        let mut material =
+            MaterialFile::new(&Material::new(Vector3::new(1.0, 1.0, 1.0), None), None);
+        material.roughness = f32::NAN;
+        material.diffuse_texture = Some(PathBuf::from("/textures/wood.png"));
+        let mut submesh = triangle(Some(0));
+        submesh.vertices[1].position.x = f32::NAN;
+        let mesh = ExportedMesh {
+            name: String::from("\"quoted\""),
+            matrix: Matrix4::from_scale(f32::INFINITY),
+            submeshes: vec![submesh],
+            materials: vec![material],
+        };
+        let root = gltf_content(&[mesh], false, Path::new("/scene")).root;
+        let accessor = &root.accessors[0];
+        assert_eq!(accessor.min, Some(json::Value::from(vec![0.0, 0.0, 1.0])));
+        assert_eq!(accessor.max, Some(json::Value::from(vec![2.0, 0.0, 1.0])));
+        assert_eq!(root.nodes[0].name.as_deref(), Some("\"quoted\""));
+        assert_eq!(root.nodes[0].matrix, None);
+        let roughness = root.materials[0].pbr_metallic_roughness.roughness_factor;
+        assert_eq!(roughness.0, 0.5);
+        let uri = root.images[0].uri.as_deref().unwrap();
+        assert!(uri.starts_with("../"), "{:?} is not relative.", uri);
+    }
+}
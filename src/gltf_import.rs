@@ -0,0 +1,438 @@
+use crate::animation::{
+    AnimationClip, Interpolate, Interpolation, JointTrack, Keyframes, Tangents,
+};
+use crate::components::{Joint, JointPose};
+use crate::import::{
+    generate_normals, ImportReport, ImportStage, ImportedMaterial, ImportedModel, ImportedSubmesh,
+    NormalGeneration, SubmeshReport,
+};
+use cgmath::prelude::*;
+use cgmath::{Matrix3, Matrix4, Quaternion, Vector2, Vector3};
+use gltf::animation::util::ReadOutputs;
+use gltf::image::{Format, Source};
+use gltf::material::AlphaMode;
+use gltf::mesh::Mode;
+use image::{DynamicImage, ImageBuffer};
+use palantir_lib::{BlendMode, JointWeights, MorphTarget, Vertex};
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+
+/// Reads the meshes, materials, skeleton and animations of a glTF file.
+///
+/// Static meshes are moved to the space of the model. Skinned meshes use the first skin of
+/// the file, the meshes bound to other skins are imported in their rest pose.
+pub fn read_gltf(
+    path: &Path,
+    progress: &dyn Fn(ImportStage, f32),
+) -> Result<ImportedModel, Box<dyn Error + Send + Sync>> {
+    progress(ImportStage::Parsing, 0.0);
+    let (document, buffers, images) = gltf::import(path)?;
+    let read = |buffer: gltf::Buffer| Some(&*buffers[buffer.index()]);
+
+    let node_count = document.nodes().count();
+    let mut parents = vec![None; node_count];
+    let mut local_matrices = vec![Matrix4::identity(); node_count];
+    for node in document.nodes() {
+        local_matrices[node.index()] = Matrix4::from(node.transform().matrix());
+        for child in node.children() {
+            parents[child.index()] = Some(node.index());
+        }
+    }
+
+    // Nodes with a mesh in the displayed scene, with their model space matrix.
+    let roots: Vec<gltf::Node> = match document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+    {
+        Some(scene) => scene.nodes().collect(),
+        None => document
+            .nodes()
+            .filter(|node| parents[node.index()].is_none())
+            .collect(),
+    };
+    let mut mesh_nodes = Vec::new();
+    let mut stack: Vec<(gltf::Node, Matrix4<f32>)> = roots
+        .into_iter()
+        .map(|node| (node, Matrix4::identity()))
+        .collect();
+    while let Some((node, parent_matrix)) = stack.pop() {
+        let matrix = parent_matrix * local_matrices[node.index()];
+        if node.mesh().is_some() {
+            mesh_nodes.push((node.clone(), matrix));
+        }
+        stack.extend(node.children().map(|child| (child, matrix)));
+    }
+
+    let skin = mesh_nodes.iter().find_map(|(node, _)| node.skin());
+    let skin_joints: Vec<gltf::Node> = skin.iter().flat_map(|skin| skin.joints()).collect();
+    let joint_of_node: HashMap<usize, usize> = skin_joints
+        .iter()
+        .enumerate()
+        .map(|(joint, node)| (node.index(), joint))
+        .collect();
+    // Parents before children, vertices and animations refer to the sorted joints.
+    let depth = |mut node: usize| {
+        let mut depth = 0;
+        while let Some(parent) = parents[node] {
+            depth += 1;
+            node = parent;
+        }
+        depth
+    };
+    let mut order: Vec<usize> = (0..skin_joints.len()).collect();
+    order.sort_by_key(|&joint| depth(skin_joints[joint].index()));
+    let mut sorted_index = vec![0; skin_joints.len()];
+    for (sorted, &joint) in order.iter().enumerate() {
+        sorted_index[joint] = sorted;
+    }
+
+    let inverse_bind_matrices: Vec<Matrix4<f32>> = skin
+        .as_ref()
+        .and_then(|skin| skin.reader(read).read_inverse_bind_matrices())
+        .map(|matrices| matrices.map(Matrix4::from).collect())
+        .unwrap_or_default();
+    let joints = order
+        .iter()
+        .map(|&joint| {
+            let node = &skin_joints[joint];
+            let mut offset = Matrix4::identity();
+            let mut parent = None;
+            let mut ancestor = parents[node.index()];
+            while let Some(index) = ancestor {
+                if let Some(&parent_joint) = joint_of_node.get(&index) {
+                    parent = Some(sorted_index[parent_joint]);
+                    break;
+                }
+                offset = local_matrices[index] * offset;
+                ancestor = parents[index];
+            }
+            let (translation, rotation, scale) = node.transform().decomposed();
+            Joint {
+                name: node
+                    .name()
+                    .map_or_else(|| format!("Joint {}", joint), str::to_owned),
+                parent,
+                offset,
+                inverse_bind_matrix: inverse_bind_matrices
+                    .get(joint)
+                    .copied()
+                    .unwrap_or_else(Matrix4::identity),
+                rest: JointPose {
+                    translation: Vector3::from(translation),
+                    rotation: Quaternion::new(rotation[3], rotation[0], rotation[1], rotation[2]),
+                    scale: Vector3::from(scale),
+                },
+            }
+        })
+        .collect();
+
+    let mut report = ImportReport {
+        path: path.to_path_buf(),
+        submeshes: Vec::new(),
+        missing_textures: Vec::new(),
+    };
+    let mut submeshes = Vec::new();
+    let mut morph_target_names = Vec::new();
+    let mut morph_weights = Vec::new();
+    for (i, (node, matrix)) in mesh_nodes.iter().enumerate() {
+        progress(ImportStage::Validating, i as f32 / mesh_nodes.len() as f32);
+        let mesh = node.mesh().unwrap();
+        let skinned = match (node.skin(), &skin) {
+            (Some(node_skin), Some(skin)) => node_skin.index() == skin.index(),
+            _ => false,
+        };
+        // Skinned vertices stay in the mesh space, their joints place them in the model.
+        let matrix = if skinned {
+            Matrix4::identity()
+        } else {
+            *matrix
+        };
+        let mesh_name = mesh
+            .name()
+            .map_or_else(|| format!("Mesh {}", mesh.index()), str::to_owned);
+        let linear = Matrix3::from_cols(
+            matrix.x.truncate(),
+            matrix.y.truncate(),
+            matrix.z.truncate(),
+        );
+        let normal_matrix = linear
+            .invert()
+            .map_or_else(Matrix3::identity, |inverse| inverse.transpose());
+
+        // The primitives of a mesh share its weights.
+        let morph_weight_offset = morph_weights.len();
+        let target_count = mesh
+            .primitives()
+            .map(|primitive| primitive.morph_targets().count())
+            .max()
+            .unwrap_or(0);
+        let default_weights = mesh.weights().unwrap_or_default();
+        for target in 0..target_count {
+            morph_target_names.push(format!("{} {}", mesh_name, target));
+            morph_weights.push(default_weights.get(target).copied().unwrap_or(0.0));
+        }
+
+        for primitive in mesh.primitives() {
+            if primitive.mode() != Mode::Triangles {
+                continue;
+            }
+            let reader = primitive.reader(read);
+            let positions: Vec<[f32; 3]> = match reader.read_positions() {
+                Some(positions) => positions.collect(),
+                None => continue,
+            };
+            let normals: Option<Vec<[f32; 3]>> =
+                reader.read_normals().map(|normals| normals.collect());
+            let uvs: Option<Vec<[f32; 2]>> = reader
+                .read_tex_coords(0)
+                .map(|uvs| uvs.into_f32().collect());
+            let mut submesh_report = SubmeshReport {
+                name: mesh_name.clone(),
+                missing_uvs: uvs.is_none(),
+                ..SubmeshReport::default()
+            };
+
+            let mut vertices: Vec<Vertex> = positions
+                .iter()
+                .enumerate()
+                .map(|(i, &position)| {
+                    let position = (matrix * Vector3::from(position).extend(1.0)).truncate();
+                    let normal = match &normals {
+                        Some(normals) => (normal_matrix * Vector3::from(normals[i])).normalize(),
+                        // Kept by faces without an area.
+                        None => Vector3::unit_y(),
+                    };
+                    // glTF UVs start at the top of the image, which is flipped for OpenGL.
+                    let uv = match &uvs {
+                        Some(uvs) => Vector2::new(uvs[i][0], 1.0 - uvs[i][1]),
+                        None => Vector2::zero(),
+                    };
+                    Vertex {
+                        position,
+                        normal,
+                        uv,
+                    }
+                })
+                .collect();
+
+            let all_indices: Vec<u32> = match reader.read_indices() {
+                Some(indices) => indices.into_u32().collect(),
+                None => (0..vertices.len() as u32).collect(),
+            };
+            let mut indices = Vec::with_capacity(all_indices.len());
+            for triangle in all_indices.chunks_exact(3) {
+                if triangle
+                    .iter()
+                    .all(|&index| (index as usize) < vertices.len())
+                {
+                    indices.extend_from_slice(triangle);
+                } else {
+                    submesh_report.invalid_triangles += 1;
+                }
+            }
+
+            if normals.is_none() {
+                let (generated_vertices, generated_indices) = generate_normals(
+                    &submesh_report.name,
+                    vertices,
+                    indices,
+                    NormalGeneration::Smooth,
+                );
+                vertices = generated_vertices;
+                indices = generated_indices;
+                submesh_report.generated_normals = Some(NormalGeneration::Smooth);
+            }
+
+            let skin = match (skinned, reader.read_joints(0), reader.read_weights(0)) {
+                (true, Some(joints), Some(weights)) => joints
+                    .into_u16()
+                    .zip(weights.into_f32())
+                    .map(|(joints, weights)| {
+                        let total: f32 = weights.iter().sum();
+                        let scale = if total > 0.0 { 1.0 / total } else { 0.0 };
+                        JointWeights {
+                            joints: joints.map(|joint| {
+                                sorted_index.get(joint as usize).copied().unwrap_or(0) as u32
+                            }),
+                            weights: weights.map(|weight| weight * scale),
+                        }
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            };
+            // Incomplete skins are drawn in the rest pose.
+            let skin = if skin.len() == vertices.len() {
+                skin
+            } else {
+                Vec::new()
+            };
+
+            let morph_targets = reader
+                .read_morph_targets()
+                .map(|(positions, normals, _)| MorphTarget {
+                    position_deltas: positions
+                        .map(|deltas| deltas.map(|delta| linear * Vector3::from(delta)).collect())
+                        .unwrap_or_default(),
+                    normal_deltas: normals
+                        .map(|deltas| {
+                            deltas
+                                .map(|delta| normal_matrix * Vector3::from(delta))
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                })
+                .collect();
+
+            submesh_report.vertex_count = vertices.len();
+            submesh_report.triangle_count = indices.len() / 3;
+            report.submeshes.push(submesh_report);
+            submeshes.push(ImportedSubmesh {
+                vertices,
+                indices,
+                skin,
+                morph_targets,
+                morph_weight_offset,
+                material_index: primitive.material().index(),
+                report: SubmeshReport::default(),
+            });
+        }
+    }
+
+    let mut materials = Vec::new();
+    let material_count = document.materials().count();
+    for (i, material) in document.materials().enumerate() {
+        progress(
+            ImportStage::DecodingTextures,
+            i as f32 / material_count as f32,
+        );
+        let pbr = material.pbr_metallic_roughness();
+        let [red, green, blue, alpha] = pbr.base_color_factor();
+        let diffuse_source = pbr.base_color_texture().map(|info| info.texture().source());
+        let diffuse_image = diffuse_source
+            .as_ref()
+            .and_then(|source| decode_image(&images[source.index()]))
+            .map(|image| image.flipv());
+        // Embedded images have no file to refer to.
+        let diffuse_path = match diffuse_source.map(|source| source.source()) {
+            Some(Source::Uri { uri, .. }) if !uri.starts_with("data:") => {
+                path.parent().map(|directory| directory.join(uri))
+            }
+            _ => None,
+        };
+        materials.push(ImportedMaterial {
+            diffuse: Vector3::new(red, green, blue),
+            roughness: pbr.roughness_factor(),
+            metallic: pbr.metallic_factor(),
+            diffuse_image,
+            diffuse_path,
+            alpha_path: None,
+            opacity: alpha,
+            alpha_cutoff: material.alpha_cutoff(),
+            blend_mode: match material.alpha_mode() {
+                AlphaMode::Opaque => BlendMode::Opaque,
+                AlphaMode::Mask => BlendMode::Masked,
+                AlphaMode::Blend => BlendMode::Blended,
+            },
+        });
+    }
+
+    let clips = if skin_joints.is_empty() {
+        Vec::new()
+    } else {
+        document
+            .animations()
+            .map(|animation| {
+                let mut tracks: HashMap<usize, JointTrack> = HashMap::new();
+                for channel in animation.channels() {
+                    let joint = match joint_of_node.get(&channel.target().node().index()) {
+                        Some(&joint) => sorted_index[joint],
+                        None => continue,
+                    };
+                    let reader = channel.reader(read);
+                    let times: Vec<f32> = match reader.read_inputs() {
+                        Some(times) => times.collect(),
+                        None => continue,
+                    };
+                    let interpolation = match channel.sampler().interpolation() {
+                        gltf::animation::Interpolation::Step => Interpolation::Step,
+                        gltf::animation::Interpolation::Linear => Interpolation::Linear,
+                        gltf::animation::Interpolation::CubicSpline => Interpolation::Bezier,
+                    };
+                    let track = tracks
+                        .entry(joint)
+                        .or_insert_with(|| JointTrack::new(joint));
+                    match reader.read_outputs() {
+                        Some(ReadOutputs::Translations(values)) => {
+                            let values = values.map(Vector3::from).collect();
+                            track.translation = keyframes(times, values, interpolation);
+                        }
+                        Some(ReadOutputs::Rotations(values)) => {
+                            let values = values
+                                .into_f32()
+                                .map(|[x, y, z, w]| Quaternion::new(w, x, y, z))
+                                .collect();
+                            track.rotation = keyframes(times, values, interpolation);
+                        }
+                        Some(ReadOutputs::Scales(values)) => {
+                            let values = values.map(Vector3::from).collect();
+                            track.scale = keyframes(times, values, interpolation);
+                        }
+                        _ => (),
+                    }
+                }
+                let name = animation
+                    .name()
+                    .map_or_else(|| format!("Animation {}", animation.index()), str::to_owned);
+                AnimationClip::new(name, tracks.into_values().collect())
+            })
+            .collect()
+    };
+
+    Ok(ImportedModel {
+        submeshes,
+        lods: Vec::new(),
+        materials,
+        joints,
+        morph_target_names,
+        morph_weights,
+        clips,
+        report,
+    })
+}
+
+/// Keyframes of a sampler, the values of cubic spline ones are stored between their in and
+/// out tangents.
+fn keyframes<T: Interpolate>(
+    times: Vec<f32>,
+    values: Vec<T>,
+    interpolation: Interpolation,
+) -> Keyframes<T> {
+    if interpolation != Interpolation::Bezier {
+        return Keyframes::new(times, values, interpolation);
+    }
+    let (values, tangents) = values
+        .chunks_exact(3)
+        .map(|keyframe| {
+            let tangents = Tangents {
+                incoming: keyframe[0],
+                outgoing: keyframe[2],
+            };
+            (keyframe[1], tangents)
+        })
+        .unzip();
+    Keyframes::with_tangents(times, values, tangents, interpolation)
+}
+
+fn decode_image(data: &gltf::image::Data) -> Option<DynamicImage> {
+    let (width, height, pixels) = (data.width, data.height, data.pixels.clone());
+    match data.format {
+        Format::R8 => ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageLuma8),
+        Format::R8G8 => ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageLumaA8),
+        Format::R8G8B8 => ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageRgb8),
+        Format::R8G8B8A8 => {
+            ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageRgba8)
+        }
+        _ => None,
+    }
+}
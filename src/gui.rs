@@ -1,13 +1,1372 @@
-use crate::components::{MeshComponent, TransformComponent};
+use crate::animation::{Interpolation, Tangents};
+use crate::components::{
+    AnimatorComponent, Camera, CameraComponent, CameraMode, KeyframesComponent, MeshComponent,
+    MorphWeightsComponent, Projection, TransformComponent,
+};
+use crate::curves::{AnimationFile, CurveSet};
+use crate::environment::load_environment;
+use crate::export::{export, ExportFormat, ExportSettings};
+use crate::import::NormalGeneration;
+use crate::input::{Action, InputBindings, Preset, BINDINGS_PATH};
+use crate::loader::{AssetLoader, LoadTarget};
+use crate::material_editor::{MaterialEditor, PREVIEW_SIZE};
+use crate::overlays::{OverlaySettings, Overlays};
+use crate::scene::{MeshOperation, RenderStats};
+use crate::screenshot::{ScreenshotFormat, ScreenshotSettings};
+use crate::timeline::Timeline;
+use crate::viewports::{Layout, Viewport, Viewports};
 use crate::{Application, Scene};
 use cgmath::prelude::*;
-use cgmath::Matrix4;
-use imgui::{im_str, Ui, Window};
+use cgmath::{Matrix4, Vector3};
+use imgui::{
+    im_str, CollapsingHeader, ColorEdit, ComboBox, Condition, ImString, Image, MenuItem,
+    MouseButton, ProgressBar, Selectable, Slider, StyleVar, TextureId, TreeNode, Ui, Window,
+};
 use legion::prelude::*;
 use nfd::Response;
-use std::path::PathBuf;
+use palantir_lib::{
+    BlendMode, EnvironmentBaker, Material, PostProcessSettings, Renderer, ShadingMode, TLight,
+    Texture, Tonemapping, TransparencyMode,
+};
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-pub fn debug_ui(ui: &Ui, fps: i32, scene: &mut Scene, world: &mut World, app: &Application) {
+/// Which of the optional windows are opened.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct WindowVisibility {
+    pub lighting: bool,
+    pub post_process: bool,
+    pub import_reports: bool,
+    pub preferences: bool,
+    pub cameras: bool,
+    pub timeline: bool,
+    pub inspector: bool,
+    pub materials: bool,
+    pub export: bool,
+}
+
+/// Draws the main menu bar, returns whether a screenshot was requested.
+pub fn main_menu_bar(
+    ui: &Ui,
+    screenshot_settings: &mut ScreenshotSettings,
+    windows: &mut WindowVisibility,
+    layout: &mut Layout,
+    viewport: &mut Viewport,
+    overlays: &mut Overlays,
+    renderer: &mut Renderer,
+) -> bool {
+    let mut screenshot_requested = false;
+    ui.main_menu_bar(|| {
+        ui.menu(im_str!("File"), true, || {
+            screenshot_requested = MenuItem::new(im_str!("Screenshot"))
+                .shortcut(im_str!("F12"))
+                .build(ui);
+            ui.menu(im_str!("Screenshot Settings"), true, || {
+                screenshot_settings_ui(ui, screenshot_settings)
+            });
+            if MenuItem::new(im_str!("Export...")).build(ui) {
+                windows.export = true;
+            }
+            if MenuItem::new(im_str!("Preferences")).build(ui) {
+                windows.preferences = true;
+            }
+        });
+        ui.menu(im_str!("View"), true, || {
+            if MenuItem::new(im_str!("Lighting"))
+                .selected(windows.lighting)
+                .build(ui)
+            {
+                windows.lighting = !windows.lighting;
+            }
+            if MenuItem::new(im_str!("Post Processing"))
+                .selected(windows.post_process)
+                .build(ui)
+            {
+                windows.post_process = !windows.post_process;
+            }
+            if MenuItem::new(im_str!("Cameras"))
+                .selected(windows.cameras)
+                .build(ui)
+            {
+                windows.cameras = !windows.cameras;
+            }
+            if MenuItem::new(im_str!("Inspector"))
+                .selected(windows.inspector)
+                .build(ui)
+            {
+                windows.inspector = !windows.inspector;
+            }
+            if MenuItem::new(im_str!("Materials"))
+                .selected(windows.materials)
+                .build(ui)
+            {
+                windows.materials = !windows.materials;
+            }
+            if MenuItem::new(im_str!("Timeline"))
+                .selected(windows.timeline)
+                .build(ui)
+            {
+                windows.timeline = !windows.timeline;
+            }
+            if MenuItem::new(im_str!("Import Reports"))
+                .selected(windows.import_reports)
+                .build(ui)
+            {
+                windows.import_reports = !windows.import_reports;
+            }
+            ui.separator();
+            ui.menu(im_str!("Layout"), true, || {
+                for option in Layout::ALL.iter() {
+                    if MenuItem::new(&im_str!("{}", option.name()))
+                        .selected(*layout == *option)
+                        .build(ui)
+                    {
+                        *layout = *option;
+                    }
+                }
+            });
+            ui.menu(im_str!("Shading"), true, || {
+                shading_ui(ui, &mut viewport.shading_mode, renderer)
+            });
+            ui.menu(im_str!("Overlays"), true, || {
+                overlays_ui(ui, &mut viewport.overlays, overlays)
+            });
+        });
+    });
+    screenshot_requested
+}
+
+fn shading_ui(ui: &Ui, shading_mode: &mut ShadingMode, renderer: &mut Renderer) {
+    for mode in ShadingMode::ALL.iter() {
+        if MenuItem::new(&im_str!("{}", mode.name()))
+            .selected(*shading_mode == *mode)
+            .build(ui)
+        {
+            *shading_mode = *mode;
+        }
+    }
+    ui.separator();
+    ui.menu(im_str!("Transparency"), true, || {
+        for mode in TransparencyMode::ALL.iter() {
+            if MenuItem::new(&im_str!("{}", mode.name()))
+                .selected(renderer.transparency_mode() == *mode)
+                .build(ui)
+            {
+                renderer.set_transparency_mode(*mode);
+            }
+        }
+        if renderer.active_transparency_mode() != renderer.transparency_mode() {
+            ui.separator();
+            ui.text(format!(
+                "{} is used, the view has no depth to share.",
+                renderer.active_transparency_mode().name()
+            ));
+        }
+    });
+    if let Some(shading) = renderer.shading_mut() {
+        ui.separator();
+        Slider::new(im_str!("Normal Length"), 0.001..=1.0).build(ui, &mut shading.normal_length);
+        Slider::new(im_str!("Checker Scale"), 0.25..=16.0).build(ui, &mut shading.uv_checker_scale);
+    }
+}
+
+fn overlays_ui(ui: &Ui, settings: &mut OverlaySettings, overlays: &mut Overlays) {
+    ui.checkbox(im_str!("Grid"), &mut settings.grid);
+    ui.checkbox(im_str!("World Axes"), &mut settings.axes);
+    ui.checkbox(
+        im_str!("Orientation Gizmo"),
+        &mut settings.orientation_gizmo,
+    );
+    ui.checkbox(im_str!("Cameras"), &mut settings.cameras);
+    ui.checkbox(im_str!("Bounding Boxes"), &mut settings.bounding_boxes);
+    ui.separator();
+    let grid = overlays.grid_mut();
+    Slider::new(im_str!("Cell Size"), 0.01..=10.0).build(ui, &mut grid.cell_size);
+    Slider::new(im_str!("Fade Distance"), 1.0..=500.0).build(ui, &mut grid.fade_distance);
+    Slider::new(im_str!("Axis Length"), 0.1..=10.0).build(ui, &mut overlays.axis_length);
+}
+
+struct GizmoAxis {
+    label: &'static str,
+    direction: Vector3<f32>,
+    color: [f32; 3],
+    pitch: f32,
+    yaw: f32,
+}
+
+/// Draws the axes of the camera orientation below `corner`, the top right corner of its
+/// viewport. Clicking an axis snaps the camera to look along it.
+pub fn orientation_gizmo(ui: &Ui, camera: &mut Camera, corner: [f32; 2]) {
+    use std::f32::consts::{FRAC_PI_2, PI};
+    const SIZE: f32 = 110.0;
+    const AXIS_LENGTH: f32 = 38.0;
+    const HANDLE_RADIUS: f32 = 9.0;
+
+    let axes = [
+        GizmoAxis {
+            label: "X",
+            direction: Vector3::unit_x(),
+            color: [0.9, 0.2, 0.2],
+            pitch: 0.0,
+            yaw: -FRAC_PI_2,
+        },
+        GizmoAxis {
+            label: "-X",
+            direction: -Vector3::unit_x(),
+            color: [0.9, 0.2, 0.2],
+            pitch: 0.0,
+            yaw: FRAC_PI_2,
+        },
+        GizmoAxis {
+            label: "Y",
+            direction: Vector3::unit_y(),
+            color: [0.2, 0.8, 0.2],
+            pitch: FRAC_PI_2,
+            yaw: 0.0,
+        },
+        GizmoAxis {
+            label: "-Y",
+            direction: -Vector3::unit_y(),
+            color: [0.2, 0.8, 0.2],
+            pitch: -FRAC_PI_2,
+            yaw: 0.0,
+        },
+        GizmoAxis {
+            label: "Z",
+            direction: Vector3::unit_z(),
+            color: [0.2, 0.3, 0.9],
+            pitch: 0.0,
+            yaw: 0.0,
+        },
+        GizmoAxis {
+            label: "-Z",
+            direction: -Vector3::unit_z(),
+            color: [0.2, 0.3, 0.9],
+            pitch: 0.0,
+            yaw: PI,
+        },
+    ];
+
+    Window::new(im_str!("Orientation Gizmo"))
+        .position(
+            [corner[0] - SIZE - 10.0, corner[1] + 10.0],
+            Condition::Always,
+        )
+        .size([SIZE, SIZE], Condition::Always)
+        .no_decoration()
+        .movable(false)
+        .save_settings(false)
+        .bg_alpha(0.0)
+        .build(ui, || {
+            let window_position = ui.window_pos();
+            let center = [
+                window_position[0] + SIZE * 0.5,
+                window_position[1] + SIZE * 0.5,
+            ];
+            let orientation = camera.orientation();
+
+            // Projected handle position and depth, drawn back to front.
+            let mut handles: Vec<_> = axes
+                .iter()
+                .map(|axis| {
+                    let direction = orientation.transform_vector(axis.direction);
+                    let position = [
+                        center[0] + direction.x * AXIS_LENGTH,
+                        center[1] - direction.y * AXIS_LENGTH,
+                    ];
+                    (axis, position, direction.z)
+                })
+                .collect();
+            handles.sort_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(Ordering::Equal));
+
+            let draw_list = ui.get_window_draw_list();
+            for (axis, position, _) in &handles {
+                let color = [axis.color[0], axis.color[1], axis.color[2], 1.0];
+                let positive = !axis.label.starts_with('-');
+                if positive {
+                    draw_list
+                        .add_line(center, *position, color)
+                        .thickness(2.0)
+                        .build();
+                }
+                draw_list
+                    .add_circle(*position, HANDLE_RADIUS, color)
+                    .filled(positive)
+                    .thickness(1.5)
+                    .build();
+                if positive {
+                    draw_list.add_text(
+                        [position[0] - 3.5, position[1] - 7.0],
+                        [0.0, 0.0, 0.0, 1.0],
+                        axis.label,
+                    );
+                }
+            }
+
+            if ui.is_window_hovered() && ui.is_mouse_clicked(MouseButton::Left) {
+                let mouse = ui.io().mouse_pos;
+                let clicked = handles.iter().rev().find(|(_, position, _)| {
+                    let dx = mouse[0] - position[0];
+                    let dy = mouse[1] - position[1];
+                    dx * dx + dy * dy <= HANDLE_RADIUS * HANDLE_RADIUS
+                });
+                if let Some((axis, _, _)) = clicked {
+                    camera.set_orientation(axis.pitch, axis.yaw);
+                }
+            }
+        });
+}
+
+fn screenshot_settings_ui(ui: &Ui, settings: &mut ScreenshotSettings) {
+    ui.checkbox(im_str!("Include Overlay"), &mut settings.include_overlay);
+    if settings.include_overlay {
+        // The settings below do not apply to copies of the window.
+        ui.text_disabled("Resolution Scale: 1");
+        ui.text_disabled("Transparent Background: off");
+        ui.text_disabled("Format: PNG");
+        ui.text("Overlay captures are copies of the window, saved as opaque PNG.");
+        return;
+    }
+    Slider::new(im_str!("Resolution Scale"), 1..=8).build(ui, &mut settings.scale);
+    ui.checkbox(
+        im_str!("Transparent Background"),
+        &mut settings.transparent_background,
+    );
+    ui.radio_button(im_str!("PNG"), &mut settings.format, ScreenshotFormat::Png);
+    ui.same_line(0.0);
+    ui.radio_button(im_str!("HDR"), &mut settings.format, ScreenshotFormat::Hdr);
+    if settings.format == ScreenshotFormat::Hdr {
+        ui.text(ScreenshotFormat::HDR_NOTE);
+    }
+}
+
+/// Shows the image of every visible viewport behind the other windows, with its name and
+/// shading mode. They take no input so the mouse reaches the scene.
+pub fn viewport_panes(ui: &Ui, viewports: &Viewports) {
+    let style = ui.push_style_vars(&[
+        StyleVar::WindowPadding([0.0, 0.0]),
+        StyleVar::WindowBorderSize(0.0),
+        StyleVar::WindowRounding(0.0),
+    ]);
+    let split = viewports.visible().len() > 1;
+    for (index, pane) in viewports.visible().iter().enumerate() {
+        let framebuffer = match pane.framebuffer() {
+            Some(framebuffer) => framebuffer,
+            None => continue,
+        };
+        let [left, top, width, height] = pane.rect();
+        Window::new(&im_str!("Viewport {}", index))
+            .position([left, top], Condition::Always)
+            .size([width, height], Condition::Always)
+            .no_decoration()
+            .no_inputs()
+            .movable(false)
+            .save_settings(false)
+            .bring_to_front_on_focus(false)
+            .focus_on_appearing(false)
+            .build(ui, || {
+                let texture = TextureId::from(framebuffer.color_texture() as usize);
+                // The framebuffer's first row is the bottom one.
+                Image::new(texture, [width, height])
+                    .uv0([0.0, 1.0])
+                    .uv1([1.0, 0.0])
+                    .build(ui);
+                let draw_list = ui.get_window_draw_list();
+                draw_list.add_text(
+                    [left + 8.0, top + 6.0],
+                    [0.9, 0.9, 0.9, 1.0],
+                    format!("{} - {}", pane.view.name(), pane.shading_mode.name()),
+                );
+                if split {
+                    let color = if index == viewports.active() {
+                        [1.0, 0.6, 0.1, 1.0]
+                    } else {
+                        [0.3, 0.3, 0.3, 1.0]
+                    };
+                    draw_list
+                        .add_rect([left, top], [left + width, top + height], color)
+                        .thickness(1.0)
+                        .build();
+                }
+            });
+    }
+    style.pop(ui);
+}
+
+pub fn lighting_ui(
+    ui: &Ui,
+    opened: &mut bool,
+    scene: &mut Scene,
+    renderer: &mut Renderer,
+    baker: &mut EnvironmentBaker,
+    app: &Application,
+) {
+    Window::new(im_str!("Lighting"))
+        .opened(opened)
+        .always_auto_resize(true)
+        .build(ui, || {
+            let light = scene.light_mut();
+            ui.text("Directional Light");
+            let mut color: [f32; 3] = light.color().into();
+            if ColorEdit::new(im_str!("Color"), &mut color).build(ui) {
+                light.set_color(color.into());
+            }
+            let mut power = light.power();
+            if Slider::new(im_str!("Power"), 0.0..=10.0).build(ui, &mut power) {
+                light.set_power(power);
+            }
+            let mut ambient_strength = light.ambient_strength();
+            if Slider::new(im_str!("Ambient"), 0.0..=1.0).build(ui, &mut ambient_strength) {
+                light.set_ambient_strength(ambient_strength);
+            }
+
+            ui.separator();
+            ui.text("Environment");
+            if ui.button(im_str!("Load HDR"), [100.0, 25.0]) {
+                on_load_environment_button_released(app, renderer, baker);
+            }
+            let mut clear_environment = false;
+            match renderer.environment_mut() {
+                Some(environment) => {
+                    ui.same_line(0.0);
+                    clear_environment = ui.button(im_str!("Clear"), [100.0, 25.0]);
+                    Slider::new(im_str!("Intensity"), 0.0..=5.0)
+                        .build(ui, &mut environment.intensity);
+                    ui.checkbox(im_str!("Show Background"), &mut environment.show_background);
+                    Slider::new(im_str!("Background Blur"), 0.0..=1.0)
+                        .build(ui, &mut environment.background_blur);
+                }
+                None => ui.text("No environment, the ambient term is used."),
+            }
+            if clear_environment {
+                renderer.set_environment(None);
+            }
+        });
+}
+
+fn on_load_environment_button_released(
+    app: &Application,
+    renderer: &mut Renderer,
+    baker: &mut EnvironmentBaker,
+) {
+    let file_choice = nfd::dialog()
+        .filter("hdr")
+        .default_path(
+            app.resources
+                .root_path()
+                .to_str()
+                .expect("Could not convert path buffer to string."),
+        )
+        .open();
+
+    if let Ok(Response::Okay(path)) = file_choice {
+        match load_environment(baker, &PathBuf::from(path)) {
+            Ok(environment) => renderer.set_environment(Some(environment)),
+            Err(err) => eprintln!("Could not load environment: {}", err),
+        }
+    }
+}
+
+/// Lists the camera entities, bookmarks the current view and looks through a camera.
+pub fn cameras_ui(ui: &Ui, opened: &mut bool, scene: &mut Scene, world: &mut World) {
+    Window::new(im_str!("Cameras"))
+        .opened(opened)
+        .size([320.0, 400.0], Condition::FirstUseEver)
+        .build(ui, || {
+            if ui.button(im_str!("Bookmark View"), [120.0, 25.0]) {
+                scene.bookmark_camera(world);
+            }
+            if scene.looking_through().is_some() {
+                ui.same_line(0.0);
+                if ui.button(im_str!("Free Viewport"), [120.0, 25.0]) {
+                    scene.stop_looking_through();
+                }
+            }
+            ui.separator();
+
+            let looking_through = scene.looking_through();
+            let mut look_through = None;
+            let mut deleted = None;
+            let query = <Write<CameraComponent>>::query();
+            for (entity, mut component) in query.iter_entities_mut(world) {
+                let active = Some(entity) == looking_through;
+                let header = if active {
+                    im_str!("{} (viewing)##{:?}", component.name, entity)
+                } else {
+                    im_str!("{}##{:?}", component.name, entity)
+                };
+                if !CollapsingHeader::new(&header).build(ui) {
+                    continue;
+                }
+                let id = ui.push_id(&im_str!("{:?}", entity));
+
+                if ui.button(im_str!("Look Through"), [100.0, 0.0]) {
+                    look_through = Some(entity);
+                }
+                ui.same_line(0.0);
+                if ui.button(im_str!("Delete"), [100.0, 0.0]) {
+                    deleted = Some(entity);
+                }
+
+                let mut name = ImString::with_capacity(64);
+                name.push_str(&component.name);
+                if ui.input_text(im_str!("Name"), &mut name).build() {
+                    component.name = name.to_str().to_owned();
+                }
+
+                // The viewport camera is the one edited while looking through, it is written
+                // back to the entity every frame.
+                let camera = if active {
+                    scene.camera_mut()
+                } else {
+                    &mut component.camera
+                };
+                camera_settings_ui(ui, camera);
+                id.pop(ui);
+            }
+
+            if let Some(entity) = look_through {
+                scene.look_through(world, entity);
+            }
+            if let Some(entity) = deleted {
+                if scene.looking_through() == Some(entity) {
+                    scene.stop_looking_through();
+                }
+                world.delete(entity);
+            }
+        });
+}
+
+/// Shows the selected mesh, processes its geometry and edits the weights of its blend shapes.
+pub fn inspector_ui(ui: &Ui, opened: &mut bool, scene: &mut Scene, world: &mut World) {
+    Window::new(im_str!("Inspector"))
+        .opened(opened)
+        .size([320.0, 300.0], Condition::FirstUseEver)
+        .build(ui, || {
+            let entity = match scene.selection() {
+                Some(entity) => entity,
+                None => {
+                    ui.text("Nothing selected.");
+                    return;
+                }
+            };
+            if let Some(mesh) = world.get_component::<MeshComponent>(entity) {
+                ui.label_text(&im_str!("{}", mesh.name), im_str!("Mesh"));
+                ui.label_text(
+                    &im_str!("{}", mesh.mesh.submeshes.len()),
+                    im_str!("Submeshes"),
+                );
+            }
+            if world.get_component::<MeshComponent>(entity).is_some()
+                && CollapsingHeader::new(im_str!("Processing")).build(ui)
+            {
+                processing_ui(ui, scene, world, entity);
+            }
+
+            let mut morph_weights = match world.get_component_mut::<MorphWeightsComponent>(entity) {
+                Some(morph_weights) => morph_weights,
+                None => return,
+            };
+            ui.separator();
+            ui.text("Morph Targets");
+            if ui.button(im_str!("Reset Weights"), [120.0, 0.0]) {
+                for weight in &mut morph_weights.weights {
+                    *weight = 0.0;
+                }
+            }
+            let MorphWeightsComponent { names, weights } = &mut *morph_weights;
+            for (index, (name, weight)) in names.iter().zip(weights.iter_mut()).enumerate() {
+                Slider::new(&im_str!("{}##{}", name, index), 0.0..=1.0).build(ui, weight);
+            }
+        });
+}
+
+/// Edits the geometry and pivot of a mesh entity, merges other meshes in it or splits it.
+fn processing_ui(ui: &Ui, scene: &mut Scene, world: &mut World, entity: Entity) {
+    let (deformable, morphed) = match world.get_component::<MeshComponent>(entity) {
+        Some(mesh) => (mesh.mesh.is_deformable(), mesh.mesh.has_morph_targets()),
+        None => return,
+    };
+    let settings = scene.processing_settings_mut();
+    Slider::new(im_str!("Smoothing Angle"), 0.0..=180.0).build(ui, &mut settings.normal_angle);
+    Slider::new(im_str!("Weld Distance"), 0.0..=0.1)
+        .power(4.0)
+        .build(ui, &mut settings.weld_distance);
+    let operations = MeshOperation::ALL.iter().filter(|operation| {
+        !(operation.moves_vertices() && deformable || operation.changes_vertex_count() && morphed)
+    });
+    for (index, operation) in operations.enumerate() {
+        if index % 2 == 1 {
+            ui.same_line(0.0);
+        }
+        if ui.button(&im_str!("{}", operation.name()), [150.0, 0.0]) {
+            if let Err(err) = scene.process_mesh(world, entity, *operation) {
+                eprintln!("Could not apply {}: {}", operation.name(), err);
+            }
+        }
+    }
+    if deformable {
+        ui.text("Skinned and morphed meshes keep their vertices in place.");
+        if morphed {
+            ui.text("Morphed meshes also keep their vertex count.");
+        }
+        return;
+    }
+
+    ui.separator();
+    let others: Vec<(Entity, String)> = <Read<MeshComponent>>::query()
+        .iter_entities(world)
+        .filter(|(other, mesh)| *other != entity && !mesh.mesh.is_deformable())
+        .map(|(other, mesh)| (other, mesh.name.clone()))
+        .collect();
+    ui.menu(im_str!("Merge With"), !others.is_empty(), || {
+        for (other, name) in &others {
+            if MenuItem::new(&im_str!("{}##{:?}", name, other)).build(ui) {
+                scene.merge_meshes(world, entity, *other);
+            }
+        }
+    });
+    if ui.button(im_str!("Split by Material"), [150.0, 0.0]) {
+        scene.split_by_material(world, entity);
+    }
+}
+
+/// Lists the materials of the meshes, edits the selected one over a preview and assigns the
+/// materials of its mesh to the submeshes.
+pub fn material_editor_ui(
+    ui: &Ui,
+    opened: &mut bool,
+    editor: &mut MaterialEditor,
+    scene: &mut Scene,
+    world: &mut World,
+    app: &Application,
+) {
+    Window::new(im_str!("Materials"))
+        .opened(opened)
+        .size([360.0, 600.0], Condition::FirstUseEver)
+        .build(ui, || {
+            let meshes: Vec<(Entity, String, usize)> = <Read<MeshComponent>>::query()
+                .iter_entities(world)
+                .map(|(entity, mesh)| (entity, mesh.name.clone(), mesh.mesh.materials.len()))
+                .collect();
+            if meshes.is_empty() {
+                ui.text("No meshes.");
+                return;
+            }
+            for (entity, name, material_count) in &meshes {
+                let id = ui.push_id(&im_str!("{:?}", entity));
+                TreeNode::new(&im_str!("{}", name)).build(ui, || {
+                    for index in 0..*material_count {
+                        let selected = editor.selection == Some((*entity, index));
+                        if Selectable::new(&im_str!("Material {}", index))
+                            .selected(selected)
+                            .build(ui)
+                        {
+                            editor.selection = Some((*entity, index));
+                        }
+                    }
+                    if ui.button(im_str!("Add Material"), [110.0, 0.0]) {
+                        if let Some(mut mesh) = world.get_component_mut::<MeshComponent>(*entity) {
+                            let white = Material::new(Vector3::new(1.0, 1.0, 1.0), None);
+                            mesh.mesh.materials.push(Arc::new(white));
+                            editor.selection = Some((*entity, *material_count));
+                        }
+                    }
+                });
+                id.pop(ui);
+            }
+            ui.separator();
+
+            let (entity, index) = match editor.selection {
+                Some(selection) => selection,
+                None => {
+                    ui.text("Select a material to edit it.");
+                    return;
+                }
+            };
+            let mut mesh = match world.get_component_mut::<MeshComponent>(entity) {
+                Some(mesh) if index < mesh.mesh.materials.len() => mesh,
+                _ => {
+                    editor.selection = None;
+                    return;
+                }
+            };
+            let texture = TextureId::from(editor.preview_texture() as usize);
+            Image::new(texture, [PREVIEW_SIZE as f32, PREVIEW_SIZE as f32])
+                .uv0([0.0, 1.0])
+                .uv1([1.0, 0.0])
+                .build(ui);
+
+            let mut material = *mesh.mesh.materials[index];
+            let mut edited = material_ui(ui, &mut material, scene, app);
+            if ui.button(im_str!("Load Material"), [110.0, 0.0]) {
+                if let Some(loaded) = on_load_material_button_released(app, scene) {
+                    material = loaded;
+                    edited = true;
+                }
+            }
+            ui.same_line(0.0);
+            if ui.button(im_str!("Save Material"), [110.0, 0.0]) {
+                on_save_material_button_released(app, scene, &material, "material");
+            }
+            ui.same_line(0.0);
+            if ui.button(im_str!("Save MTL"), [110.0, 0.0]) {
+                on_save_material_button_released(app, scene, &material, "mtl");
+            }
+            if edited {
+                *Arc::make_mut(&mut mesh.mesh.materials[index]) = material;
+            }
+
+            ui.separator();
+            ui.text("Submeshes");
+            let mut names = vec![ImString::new("Default")];
+            names.extend((0..mesh.mesh.materials.len()).map(|index| im_str!("Material {}", index)));
+            let names: Vec<&ImString> = names.iter().collect();
+            for submesh_index in 0..mesh.mesh.submeshes.len() {
+                let previous = mesh.mesh.submeshes[submesh_index].material_index;
+                let mut current = previous.map_or(0, |index| index + 1);
+                if ComboBox::new(&im_str!("Submesh {}", submesh_index)).build_simple_string(
+                    ui,
+                    &mut current,
+                    &names,
+                ) {
+                    let material_index = current.checked_sub(1);
+                    mesh.mesh.submeshes[submesh_index].material_index = material_index;
+                    // The levels of detail keep the order of the submeshes they simplify.
+                    for lod in &mut mesh.mesh.lods {
+                        if let Some(submesh) = lod.submeshes.get_mut(submesh_index) {
+                            if submesh.material_index == previous {
+                                submesh.material_index = material_index;
+                            }
+                        }
+                    }
+                }
+            }
+        });
+}
+
+/// Edits the parameters and texture of a material, returns whether they changed.
+fn material_ui(ui: &Ui, material: &mut Material, scene: &mut Scene, app: &Application) -> bool {
+    let mut edited = false;
+    let mut diffuse: [f32; 3] = material.diffuse().into();
+    if ColorEdit::new(im_str!("Diffuse"), &mut diffuse).build(ui) {
+        material.set_diffuse(diffuse.into());
+        edited = true;
+    }
+    let mut metallic = material.metallic();
+    if Slider::new(im_str!("Metallic"), 0.0..=1.0).build(ui, &mut metallic) {
+        material.set_metallic(metallic);
+        edited = true;
+    }
+    let mut roughness = material.roughness();
+    if Slider::new(im_str!("Roughness"), 0.0..=1.0).build(ui, &mut roughness) {
+        material.set_roughness(roughness);
+        edited = true;
+    }
+    let mut opacity = material.opacity();
+    if Slider::new(im_str!("Opacity"), 0.0..=1.0).build(ui, &mut opacity) {
+        material.set_opacity(opacity);
+        edited = true;
+    }
+    let mut alpha_cutoff = material.alpha_cutoff();
+    if Slider::new(im_str!("Alpha Cutoff"), 0.0..=1.0).build(ui, &mut alpha_cutoff) {
+        material.set_alpha_cutoff(alpha_cutoff);
+        edited = true;
+    }
+    let mut blend_mode = material.blend_mode();
+    for (index, mode) in BlendMode::ALL.iter().enumerate() {
+        if index > 0 {
+            ui.same_line(0.0);
+        }
+        if ui.radio_button(&im_str!("{}", mode.name()), &mut blend_mode, *mode) {
+            material.set_blend_mode(blend_mode);
+            edited = true;
+        }
+    }
+
+    let texture_label = match material.diffuse_texture() {
+        Some(texture) => match scene.texture_path(texture) {
+            Some(path) => path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            None => String::from("Embedded"),
+        },
+        None => String::from("None"),
+    };
+    ui.label_text(&im_str!("{}", texture_label), im_str!("Texture"));
+    if ui.button(im_str!("Load Texture"), [110.0, 0.0]) {
+        if let Some(texture) = on_load_texture_button_released(app, scene) {
+            material.set_diffuse_texture(Some(texture));
+            edited = true;
+        }
+    }
+    ui.same_line(0.0);
+    if ui.button(im_str!("Clear Texture"), [110.0, 0.0]) {
+        material.set_diffuse_texture(None);
+        edited = true;
+    }
+    edited
+}
+
+fn open_file_dialog(app: &Application, filter: &str) -> Option<PathBuf> {
+    let file_choice = nfd::dialog()
+        .filter(filter)
+        .default_path(
+            app.resources
+                .root_path()
+                .to_str()
+                .expect("Could not convert path buffer to string."),
+        )
+        .open();
+    match file_choice {
+        Ok(Response::Okay(path)) => Some(PathBuf::from(path)),
+        _ => None,
+    }
+}
+
+fn on_load_texture_button_released(app: &Application, scene: &mut Scene) -> Option<Texture> {
+    let path = open_file_dialog(app, "png,jpg,jpeg,bmp,tga,tif")?;
+    match scene.load_texture(&path) {
+        Ok(texture) => Some(texture),
+        Err(err) => {
+            eprintln!("Could not load texture: {}", err);
+            None
+        }
+    }
+}
+
+fn on_load_material_button_released(app: &Application, scene: &mut Scene) -> Option<Material> {
+    let path = open_file_dialog(app, "material")?;
+    match scene.load_material(&path) {
+        Ok(material) => Some(material),
+        Err(err) => {
+            eprintln!("Could not load material: {}", err);
+            None
+        }
+    }
+}
+
+/// Saves the material as a native file or, for the `mtl` extension, as a material library.
+fn on_save_material_button_released(
+    app: &Application,
+    scene: &Scene,
+    material: &Material,
+    extension: &str,
+) {
+    let file_choice = nfd::dialog_save()
+        .filter(extension)
+        .default_path(
+            app.resources
+                .root_path()
+                .to_str()
+                .expect("Could not convert path buffer to string."),
+        )
+        .open();
+
+    if let Ok(Response::Okay(path)) = file_choice {
+        let mut path = PathBuf::from(path);
+        if path.extension().is_none() {
+            path.set_extension(extension);
+        }
+        let file = scene.material_file(material);
+        let result = if extension == "mtl" {
+            file.save_mtl(&path, &app.resources.root_path())
+        } else {
+            file.save(&path)
+        };
+        if let Err(err) = result {
+            eprintln!("Could not save material: {}", err);
+        }
+    }
+}
+
+/// Writes the meshes of the scene with their materials to a file.
+pub fn export_ui(
+    ui: &Ui,
+    opened: &mut bool,
+    settings: &mut ExportSettings,
+    scene: &Scene,
+    world: &World,
+    app: &Application,
+) {
+    Window::new(im_str!("Export"))
+        .opened(opened)
+        .always_auto_resize(true)
+        .build(ui, || {
+            for (index, format) in ExportFormat::ALL.iter().enumerate() {
+                if index > 0 {
+                    ui.same_line(0.0);
+                }
+                ui.radio_button(
+                    &im_str!("{}", format.label()),
+                    &mut settings.format,
+                    *format,
+                );
+            }
+            ui.checkbox(im_str!("Bake Transforms"), &mut settings.bake_transforms);
+            if settings.format == ExportFormat::Obj && !settings.bake_transforms {
+                ui.text("OBJ files have no transforms, the meshes keep their own space.");
+            }
+            if ui.button(im_str!("Export"), [110.0, 0.0]) {
+                on_export_button_released(app, scene, world, settings);
+            }
+        });
+}
+
+fn on_export_button_released(
+    app: &Application,
+    scene: &Scene,
+    world: &World,
+    settings: &ExportSettings,
+) {
+    let extension = settings.format.name();
+    let file_choice = nfd::dialog_save()
+        .filter(extension)
+        .default_path(
+            app.resources
+                .root_path()
+                .to_str()
+                .expect("Could not convert path buffer to string."),
+        )
+        .open();
+
+    if let Ok(Response::Okay(path)) = file_choice {
+        let mut path = PathBuf::from(path);
+        if path.extension().is_none() {
+            path.set_extension(extension);
+        }
+        if let Err(err) = export(&path, &scene.export_meshes(world), settings) {
+            eprintln!("Could not export the scene: {}", err);
+        }
+    }
+}
+
+/// Plays and scrubs the animations, keys the selection, camera and light and edits their
+/// curves.
+pub fn timeline_ui(
+    ui: &Ui,
+    opened: &mut bool,
+    timeline: &mut Timeline,
+    scene: &mut Scene,
+    world: &mut World,
+    app: &Application,
+) {
+    Window::new(im_str!("Timeline"))
+        .opened(opened)
+        .size([420.0, 480.0], Condition::FirstUseEver)
+        .build(ui, || {
+            if ui.button(im_str!("|<"), [30.0, 0.0]) {
+                timeline.rewind();
+            }
+            ui.same_line(0.0);
+            let label = if timeline.playing {
+                im_str!("Pause")
+            } else {
+                im_str!("Play")
+            };
+            if ui.button(label, [60.0, 0.0]) {
+                timeline.playing = !timeline.playing;
+            }
+            ui.same_line(0.0);
+            ui.checkbox(im_str!("Loop"), &mut timeline.looping);
+            Slider::new(im_str!("Speed"), 0.0..=4.0).build(ui, &mut timeline.speed);
+            if ui
+                .input_float(im_str!("Length"), &mut timeline.length)
+                .build()
+            {
+                timeline.length = timeline.length.max(0.0);
+            }
+
+            if timeline.end() > 0.0 {
+                let mut time = timeline.time();
+                if Slider::new(im_str!("Time"), 0.0..=timeline.end())
+                    .display_format(im_str!("%.2f s"))
+                    .build(ui, &mut time)
+                {
+                    timeline.set_time(time);
+                }
+            } else {
+                ui.text("Nothing to animate.");
+            }
+            ui.separator();
+
+            let time = timeline.time();
+            let selection = scene.selection();
+            if let Some(entity) = selection {
+                if ui.button(im_str!("Key Transform"), [110.0, 0.0]) {
+                    scene.key_transform(world, entity, time);
+                }
+                ui.same_line(0.0);
+            }
+            if scene.looking_through().is_some() {
+                if ui.button(im_str!("Key Camera"), [110.0, 0.0]) {
+                    scene.key_camera(world, time);
+                }
+                ui.same_line(0.0);
+            }
+            if ui.button(im_str!("Key Light"), [110.0, 0.0]) {
+                scene.key_light(time);
+            }
+            if ui.button(im_str!("Save Animation"), [110.0, 0.0]) {
+                on_save_animation_button_released(app, timeline, scene, world);
+            }
+            ui.same_line(0.0);
+            if ui.button(im_str!("Load Animation"), [110.0, 0.0]) {
+                on_load_animation_button_released(app, timeline, scene, world);
+            }
+
+            let mut changed = false;
+            let keyed = [
+                ("Selection", selection),
+                ("Camera", scene.looking_through()),
+            ];
+            for &(name, entity) in keyed.iter() {
+                let entity = match entity {
+                    Some(entity) => entity,
+                    None => continue,
+                };
+                if let Some(mut keyframes) = world.get_component_mut::<KeyframesComponent>(entity) {
+                    if CollapsingHeader::new(&im_str!("{} Keyframes", name)).build(ui) {
+                        let id = ui.push_id(name);
+                        changed |= curves_ui(ui, &mut keyframes.curves);
+                        id.pop(ui);
+                    }
+                }
+            }
+            let light_curves = scene.light_curves_mut();
+            if !light_curves.is_empty()
+                && CollapsingHeader::new(im_str!("Light Keyframes")).build(ui)
+            {
+                let id = ui.push_id("Light");
+                changed |= curves_ui(ui, light_curves);
+                id.pop(ui);
+            }
+            if changed {
+                timeline.refresh();
+            }
+            ui.separator();
+
+            let mut animator = match selection
+                .and_then(|entity| world.get_component_mut::<AnimatorComponent>(entity))
+            {
+                Some(animator) => animator,
+                None => {
+                    ui.text("Select an animated mesh to pick its clip.");
+                    return;
+                }
+            };
+            let current = animator.current();
+            let mut played = None;
+            if ui.radio_button_bool(im_str!("Rest Pose"), current.is_none()) {
+                played = Some(None);
+            }
+            for (index, clip) in animator.clips.iter().enumerate() {
+                let label = im_str!("{} ({:.2} s)##{}", clip.name, clip.duration(), index);
+                if ui.radio_button_bool(&label, current == Some(index)) {
+                    played = Some(Some(index));
+                }
+            }
+            if let Some(clip) = played {
+                animator.play(clip, time);
+            }
+            Slider::new(im_str!("Fade Duration"), 0.0..=2.0).build(ui, &mut animator.fade_duration);
+        });
+}
+
+/// Edits the interpolation and keyframes of each curve, returns whether anything changed.
+fn curves_ui(ui: &Ui, curves: &mut CurveSet) -> bool {
+    let mut changed = false;
+    for curve in &mut curves.curves {
+        let keyframes = &mut curve.keyframes;
+        TreeNode::new(&im_str!("{}", curve.field.label())).build(ui, || {
+            for interpolation in Interpolation::ALL.iter() {
+                let mut current = keyframes.interpolation();
+                let label = im_str!("{}", interpolation.label());
+                if ui.radio_button(&label, &mut current, *interpolation) {
+                    keyframes.set_interpolation(current);
+                    changed = true;
+                }
+                ui.same_line(0.0);
+            }
+            ui.new_line();
+
+            let bezier = keyframes.interpolation() == Interpolation::Bezier;
+            if bezier && ui.button(im_str!("Smooth Tangents"), [0.0, 0.0]) {
+                keyframes.smooth_tangents();
+                changed = true;
+            }
+
+            let mut removed = None;
+            let mut edited = None;
+            let mut edited_tangents = None;
+            for index in 0..keyframes.times().len() {
+                let mut key = [keyframes.times()[index], keyframes.values()[index]];
+                ui.set_next_item_width(200.0);
+                if ui
+                    .input_float2(&im_str!("##key{}", index), &mut key)
+                    .enter_returns_true(true)
+                    .build()
+                {
+                    edited = Some((index, key));
+                }
+                if bezier {
+                    let tangents = keyframes.tangents()[index];
+                    let mut slopes = [tangents.incoming, tangents.outgoing];
+                    ui.same_line(0.0);
+                    ui.set_next_item_width(200.0);
+                    if ui
+                        .input_float2(&im_str!("In / Out##tangents{}", index), &mut slopes)
+                        .enter_returns_true(true)
+                        .build()
+                    {
+                        edited_tangents = Some((index, slopes));
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("Slopes entering and leaving the keyframe, per second.");
+                    }
+                }
+                ui.same_line(0.0);
+                if ui.button(&im_str!("Remove##{}", index), [0.0, 0.0]) {
+                    removed = Some(index);
+                }
+            }
+            if let Some((index, [incoming, outgoing])) = edited_tangents {
+                keyframes.set_tangents(index, Tangents { incoming, outgoing });
+                changed = true;
+            }
+            if let Some((index, [time, value])) = edited {
+                let tangents = keyframes.tangents()[index];
+                keyframes.remove(index);
+                let index = keyframes.insert(time.max(0.0), value);
+                keyframes.set_tangents(index, tangents);
+                changed = true;
+            }
+            if let Some(index) = removed {
+                keyframes.remove(index);
+                changed = true;
+            }
+        });
+    }
+    curves.remove_empty();
+    changed
+}
+
+fn on_save_animation_button_released(
+    app: &Application,
+    timeline: &Timeline,
+    scene: &Scene,
+    world: &World,
+) {
+    let file_choice = nfd::dialog_save()
+        .filter("anim")
+        .default_path(
+            app.resources
+                .root_path()
+                .to_str()
+                .expect("Could not convert path buffer to string."),
+        )
+        .open();
+
+    if let Ok(Response::Okay(path)) = file_choice {
+        let mut path = PathBuf::from(path);
+        if path.extension().is_none() {
+            path.set_extension("anim");
+        }
+        if let Err(err) = scene.animation_file(world, timeline.length).save(&path) {
+            eprintln!("Could not save animation: {}", err);
+        }
+    }
+}
+
+fn on_load_animation_button_released(
+    app: &Application,
+    timeline: &mut Timeline,
+    scene: &mut Scene,
+    world: &mut World,
+) {
+    let file_choice = nfd::dialog()
+        .filter("anim")
+        .default_path(
+            app.resources
+                .root_path()
+                .to_str()
+                .expect("Could not convert path buffer to string."),
+        )
+        .open();
+
+    if let Ok(Response::Okay(path)) = file_choice {
+        match AnimationFile::load(Path::new(&path)) {
+            Ok(file) => {
+                timeline.length = file.length;
+                for target in scene.set_animation_file(world, file) {
+                    eprintln!("Animated {:?} is not in the scene.", target);
+                }
+                timeline.refresh();
+            }
+            Err(err) => eprintln!("Could not load animation: {}", err),
+        }
+    }
+}
+
+fn camera_settings_ui(ui: &Ui, camera: &mut Camera) {
+    for projection in Projection::ALL.iter() {
+        let mut current = camera.projection();
+        if ui.radio_button(&im_str!("{}", projection.name()), &mut current, *projection) {
+            camera.set_projection(current);
+        }
+        ui.same_line(0.0);
+    }
+    ui.new_line();
+
+    let mut focal_length = camera.focal_length();
+    let mut sensor_size = camera.sensor_size();
+    let focal_length_changed =
+        Slider::new(im_str!("Focal Length"), 8.0..=300.0).build(ui, &mut focal_length);
+    let sensor_size_changed =
+        Slider::new(im_str!("Sensor Size"), 4.0..=70.0).build(ui, &mut sensor_size);
+    if focal_length_changed || sensor_size_changed {
+        camera.set_focal_length(focal_length, sensor_size);
+    }
+
+    let mut near_clip = camera.near_clip();
+    let mut far_clip = camera.far_clip();
+    let near_changed = ui.input_float(im_str!("Near Clip"), &mut near_clip).build();
+    let far_changed = ui.input_float(im_str!("Far Clip"), &mut far_clip).build();
+    if near_changed || far_changed {
+        camera.set_clip(near_clip, far_clip);
+    }
+}
+
+pub fn post_process_ui(ui: &Ui, opened: &mut bool, settings: &mut PostProcessSettings) {
+    Window::new(im_str!("Post Processing"))
+        .opened(opened)
+        .always_auto_resize(true)
+        .build(ui, || {
+            let bloom = &mut settings.bloom;
+            ui.text("Bloom");
+            ui.checkbox(im_str!("Enable Bloom"), &mut bloom.enabled);
+            Slider::new(im_str!("Threshold"), 0.0..=10.0).build(ui, &mut bloom.threshold);
+            Slider::new(im_str!("Intensity"), 0.0..=2.0).build(ui, &mut bloom.intensity);
+            Slider::new(im_str!("Blur Iterations"), 1..=10).build(ui, &mut bloom.iterations);
+
+            let tonemapping = &mut settings.tonemapping;
+            ui.separator();
+            ui.text("Tonemapping");
+            ui.checkbox(im_str!("Enable Tonemapping"), &mut tonemapping.enabled);
+            Slider::new(im_str!("Exposure"), 0.0..=10.0).build(ui, &mut tonemapping.exposure);
+            for operator in Tonemapping::ALL.iter() {
+                ui.radio_button(
+                    &im_str!("{}", operator.name()),
+                    &mut tonemapping.operator,
+                    *operator,
+                );
+            }
+
+            let vignette = &mut settings.vignette;
+            ui.separator();
+            ui.text("Vignette");
+            ui.checkbox(im_str!("Enable Vignette"), &mut vignette.enabled);
+            Slider::new(im_str!("Vignette Intensity"), 0.0..=1.0)
+                .build(ui, &mut vignette.intensity);
+            Slider::new(im_str!("Smoothness"), 0.01..=0.8).build(ui, &mut vignette.smoothness);
+
+            ui.separator();
+            ui.text("Output");
+            ui.checkbox(im_str!("sRGB Output"), &mut settings.srgb_output);
+            ui.checkbox(im_str!("FXAA"), &mut settings.fxaa);
+        });
+}
+
+/// Edits the input bindings. Clicking a binding sets `rebinding`, the next key or mouse button
+/// pressed in the main loop replaces it.
+pub fn preferences_ui(
+    ui: &Ui,
+    opened: &mut bool,
+    bindings: &mut InputBindings,
+    rebinding: &mut Option<Action>,
+) {
+    Window::new(im_str!("Preferences"))
+        .opened(opened)
+        .always_auto_resize(true)
+        .build(ui, || {
+            ui.text("Navigation Preset");
+            for preset in Preset::ALL.iter() {
+                ui.same_line(0.0);
+                let selected = bindings.matching_preset() == Some(*preset);
+                let label = if selected {
+                    im_str!("[{}]", preset.name())
+                } else {
+                    im_str!("{}", preset.name())
+                };
+                if ui.button(&label, [0.0, 0.0]) {
+                    bindings.apply_preset(*preset);
+                    *rebinding = None;
+                }
+            }
+
+            ui.separator();
+            ui.text("Bindings");
+            for action in Action::ALL.iter() {
+                ui.text(action.label());
+                ui.same_line(140.0);
+                let label = if *rebinding == Some(*action) {
+                    if action.is_drag() {
+                        im_str!("Press a mouse button...##{}", action.name())
+                    } else {
+                        im_str!("Press a key...##{}", action.name())
+                    }
+                } else {
+                    match bindings.binding(*action) {
+                        Some(binding) => im_str!("{}##{}", binding, action.name()),
+                        None => im_str!("None##{}", action.name()),
+                    }
+                };
+                if ui.button(&label, [200.0, 0.0]) {
+                    *rebinding = Some(*action);
+                }
+                ui.same_line(0.0);
+                if ui.button(&im_str!("Clear##{}", action.name()), [0.0, 0.0]) {
+                    bindings.set_binding(*action, None);
+                }
+            }
+            ui.text_disabled("Escape cancels rebinding, a left click always selects.");
+
+            ui.separator();
+            ui.text("Sensitivity");
+            Slider::new(im_str!("Orbit"), 0.001..=0.05).build(ui, &mut bindings.orbit_sensitivity);
+            Slider::new(im_str!("Pan"), 0.001..=0.05).build(ui, &mut bindings.pan_sensitivity);
+            Slider::new(im_str!("Zoom"), 0.001..=0.05).build(ui, &mut bindings.zoom_sensitivity);
+            Slider::new(im_str!("Wheel"), 0.1..=5.0).build(ui, &mut bindings.wheel_sensitivity);
+            ui.checkbox(im_str!("Invert Wheel"), &mut bindings.invert_wheel);
+
+            ui.separator();
+            let path = Path::new(BINDINGS_PATH);
+            if ui.button(im_str!("Save"), [100.0, 25.0]) {
+                if let Err(err) = bindings.save(path) {
+                    eprintln!("Could not save input bindings: {}", err);
+                }
+            }
+            ui.same_line(0.0);
+            if ui.button(im_str!("Reload"), [100.0, 25.0]) {
+                match InputBindings::load(path) {
+                    Ok(loaded) => *bindings = loaded,
+                    Err(err) => eprintln!("Could not load input bindings: {}", err),
+                }
+            }
+        });
+}
+
+pub fn debug_ui(
+    ui: &Ui,
+    fps: i32,
+    render_stats: RenderStats,
+    scene: &mut Scene,
+    world: &World,
+    app: &Application,
+    loader: &mut AssetLoader,
+) {
     Window::new(im_str!("Debug"))
         .always_auto_resize(true)
         .resizable(true)
@@ -18,17 +1377,41 @@ pub fn debug_ui(ui: &Ui, fps: i32, scene: &mut Scene, world: &mut World, app: &A
                 &im_str!("{}", render_meshes_query.iter(world).count()),
                 im_str!("Mesh Count"),
             );
+            ui.label_text(&im_str!("{}", render_stats.drawn), im_str!("Drawn"));
+            ui.label_text(&im_str!("{}", render_stats.culled), im_str!("Culled"));
+            ui.label_text(&im_str!("{}", render_stats.triangles), im_str!("Triangles"));
+            ui.checkbox(im_str!("Frustum Culling"), &mut scene.frustum_culling);
+            ui.checkbox(im_str!("Levels of Detail"), &mut scene.lod_selection);
+            let camera = scene.camera();
+            ui.label_text(&im_str!("{}", camera.mode().name()), im_str!("Camera"));
+            let target = camera.target();
+            ui.label_text(
+                &im_str!("{:.2}, {:.2}, {:.2}", target.x, target.y, target.z),
+                im_str!("Target"),
+            );
+            ui.label_text(
+                &im_str!(
+                    "{:.1}, {:.1}",
+                    camera.yaw().to_degrees(),
+                    camera.pitch().to_degrees()
+                ),
+                im_str!("Yaw, Pitch"),
+            );
+            ui.label_text(&im_str!("{:.2}", camera.distance()), im_str!("Distance"));
+            if camera.mode() == CameraMode::Fly {
+                ui.label_text(&im_str!("{:.2}", camera.fly_speed()), im_str!("Fly Speed"));
+            }
 
             let import_button_released = ui.button(im_str!("Import"), [100.0, 25.0]);
             if import_button_released {
-                on_import_button_released(app, scene, world)
+                on_import_button_released(app, scene, loader)
             }
         });
 }
 
-fn on_import_button_released(app: &Application, scene: &mut Scene, world: &mut World) {
+fn on_import_button_released(app: &Application, scene: &Scene, loader: &mut AssetLoader) {
     let file_choice = nfd::dialog_multiple()
-        .filter("obj")
+        .filter("obj,gltf,glb")
         .default_path(
             app.resources
                 .root_path()
@@ -37,45 +1420,98 @@ fn on_import_button_released(app: &Application, scene: &mut Scene, world: &mut W
         )
         .open();
 
-    if let Ok(file) = file_choice {
-        match file {
-            Response::Okay(path) => {
-                world.insert(
-                    (),
-                    (0..1).map(|_| {
-                        (
-                            TransformComponent {
-                                matrix: Matrix4::identity(),
-                            },
-                            MeshComponent {
-                                mesh: scene
-                                    .load_obj(PathBuf::from(path.clone()), &app.resources)
-                                    .expect("Error loading mesh."),
-                            },
-                        )
-                    }),
-                );
+    let paths = match file_choice {
+        Ok(Response::Okay(path)) => vec![path],
+        Ok(Response::OkayMultiple(paths)) => paths,
+        _ => Vec::new(),
+    };
+    for path in paths {
+        loader.load_model(
+            &PathBuf::from(path),
+            &app.resources,
+            *scene.import_settings(),
+            LoadTarget::Spawn(Matrix4::identity()),
+        );
+    }
+}
+
+/// Lists the files being loaded in the background.
+pub fn loading_ui(ui: &Ui, loader: &AssetLoader) {
+    Window::new(im_str!("Loading"))
+        .always_auto_resize(true)
+        .build(ui, || {
+            for progress in loader.jobs() {
+                let file_name = progress
+                    .path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                ui.text(file_name);
+                let stage = im_str!("{}", progress.stage.name());
+                ProgressBar::new(progress.overall())
+                    .size([250.0, 0.0])
+                    .overlay_text(&stage)
+                    .build(ui);
             }
-            Response::OkayMultiple(paths) => {
-                for path in paths {
-                    world.insert(
-                        (),
-                        (0..1).map(|_| {
-                            (
-                                TransformComponent {
-                                    matrix: Matrix4::identity(),
-                                },
-                                MeshComponent {
-                                    mesh: scene
-                                        .load_obj(PathBuf::from(path.clone()), &app.resources)
-                                        .expect("Error loading mesh."),
-                                },
-                            )
-                        }),
-                    );
+        });
+}
+
+pub fn import_report_ui(ui: &Ui, opened: &mut bool, scene: &mut Scene) {
+    Window::new(im_str!("Import Reports"))
+        .opened(opened)
+        .size([420.0, 360.0], Condition::FirstUseEver)
+        .build(ui, || {
+            let settings = scene.import_settings_mut();
+            ui.text("Missing normals");
+            ui.same_line(0.0);
+            ui.radio_button(
+                im_str!("Smooth"),
+                &mut settings.normal_generation,
+                NormalGeneration::Smooth,
+            );
+            ui.same_line(0.0);
+            ui.radio_button(
+                im_str!("Flat"),
+                &mut settings.normal_generation,
+                NormalGeneration::Flat,
+            );
+            Slider::new(im_str!("Generated LODs"), 0..=4).build(ui, &mut settings.generated_lods);
+            Slider::new(im_str!("LOD Reduction"), 0.1..=0.9).build(ui, &mut settings.lod_reduction);
+            ui.checkbox(im_str!("Keep Mesh Data"), &mut settings.keep_mesh_data);
+            if ui.button(im_str!("Clear Reports"), [100.0, 25.0]) {
+                scene.clear_import_reports();
+            }
+            ui.separator();
+
+            for (i, report) in scene.import_reports().iter().enumerate() {
+                let file_name = report
+                    .path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                let header = im_str!("{} ({} warnings)##{}", file_name, report.warning_count(), i);
+                if !CollapsingHeader::new(&header)
+                    .default_open(report.warning_count() > 0)
+                    .build(ui)
+                {
+                    continue;
+                }
+                for (j, submesh) in report.submeshes.iter().enumerate() {
+                    let label = im_str!("{}##{}_{}", submesh.name, i, j);
+                    TreeNode::new(&label).build(ui, || {
+                        ui.text(format!(
+                            "{} vertices, {} triangles",
+                            submesh.vertex_count, submesh.triangle_count
+                        ));
+                        let warnings = submesh.warnings();
+                        if warnings.is_empty() {
+                            ui.text_disabled("No problem found.");
+                        }
+                        for warning in warnings {
+                            ui.text_colored([1.0, 0.8, 0.3, 1.0], warning);
+                        }
+                    });
                 }
             }
-            Response::Cancel => (),
-        }
-    }
+        });
 }
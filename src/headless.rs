@@ -0,0 +1,97 @@
+use crate::cli::Options;
+use crate::components::{MeshComponent, TransformComponent};
+use crate::environment::{create_environment_baker, create_skybox, load_environment};
+use crate::import::model_name;
+use crate::post_process::create_post_process_stack;
+use crate::{Application, Scene};
+use cgmath::prelude::*;
+use cgmath::{Matrix4, Point3, Vector3};
+use legion::prelude::*;
+use palantir_lib::{Framebuffer, Renderer, ShaderProgram, ShadingMode, TLight};
+use std::error::Error;
+use std::fs;
+
+/// Renders the models given on the command line to PNG frames, orbiting the
+/// camera a full turn around them over `options.frames` frames.
+pub fn render_turntable(app: &Application, options: &Options) -> Result<(), Box<dyn Error>> {
+    let universe = Universe::new();
+    let mut world = universe.create_world();
+
+    let mut scene = Scene::new();
+    for path in &options.models {
+        let mesh = scene.load_model(path.clone(), &app.resources)?;
+        world.insert(
+            (),
+            vec![(
+                TransformComponent {
+                    matrix: Matrix4::identity(),
+                },
+                MeshComponent {
+                    name: model_name(path),
+                    mesh,
+                },
+            )],
+        );
+    }
+
+    let camera = scene.camera_mut();
+    camera.set_aspect_ratio(options.width as f32 / options.height as f32);
+    camera.set_focal_length(options.focal_length, 36.0);
+    camera.set_distance(options.distance);
+    camera.rotate(0.0, options.elevation.to_radians());
+
+    // A zero or vertical direction gives a NaN matrix, which still inverts.
+    let direction = options.light_direction;
+    let horizontal = direction.cross(Vector3::unit_y()).magnitude2();
+    if !horizontal.is_finite() || horizontal <= f32::EPSILON * direction.magnitude2() {
+        return Err("Light direction must be neither zero nor vertical.".into());
+    }
+    let light = scene.light_mut();
+    light.set_matrix(
+        Matrix4::look_at_dir(
+            Point3::new(0.0, 0.0, 0.0),
+            direction.normalize(),
+            Vector3::unit_y(),
+        )
+        .inverse_transform()
+        .ok_or("Light direction must be neither zero nor vertical.")?,
+    );
+    light.set_color(options.light_color);
+    light.set_power(options.light_power);
+    light.set_ambient_strength(options.ambient_strength);
+
+    let pbr_shader_path = app.resources.resource_name_to_path("shaders/pbr.glsl");
+    let pbr_shader = ShaderProgram::from_path(pbr_shader_path)?;
+    let mut renderer = Renderer::new(pbr_shader);
+    if let Some(path) = &options.environment {
+        let mut baker = create_environment_baker(&app.resources)?;
+        renderer.set_environment(Some(load_environment(&mut baker, path)?));
+        renderer.set_skybox(create_skybox(&app.resources)?);
+    }
+
+    let framebuffer = Framebuffer::new(options.width, options.height)?;
+    let mut post_process_stack =
+        create_post_process_stack(&app.resources, options.width, options.height)?;
+    fs::create_dir_all(&options.output)?;
+
+    let step = std::f32::consts::PI * 2.0 / options.frames as f32;
+    for frame in 0..options.frames {
+        post_process_stack.begin();
+        renderer.clear(
+            options.background.x,
+            options.background.y,
+            options.background.z,
+        );
+        scene.render(&mut renderer, &world, ShadingMode::Solid);
+        post_process_stack.end(Some(&framebuffer));
+
+        let path = options.output.join(format!("frame_{:04}.png", frame));
+        framebuffer.read_pixels().save(&path)?;
+        println!("Rendered {}", path.display());
+
+        scene.camera_mut().rotate(step, 0.0);
+    }
+    Framebuffer::unbind();
+
+    Ok(())
+}
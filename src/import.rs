@@ -0,0 +1,574 @@
+use crate::animation::AnimationClip;
+use crate::components::Joint;
+use crate::gltf_import::read_gltf;
+use crate::material_file::blend_mode_from_name;
+use crate::resources::Resources;
+use cgmath::prelude::*;
+use cgmath::{Vector2, Vector3};
+use image::DynamicImage;
+use palantir_lib::{
+    position_key, simplify, weld_positions, BlendMode, JointWeights, MeshData, MorphTarget, Vertex,
+};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::f32::consts::PI;
+use std::path::{Path, PathBuf};
+
+/// Normals computed for meshes imported without them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NormalGeneration {
+    /// Averaged over the faces sharing a position.
+    Smooth,
+    /// One normal per face, vertices are split.
+    Flat,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImportSettings {
+    pub normal_generation: NormalGeneration,
+    /// Levels of detail simplified from the model when the file has no `_LOD<n>` objects.
+    pub generated_lods: u32,
+    /// Fraction of the triangles of a level kept in the next one.
+    pub lod_reduction: f32,
+    /// Keeps the full detail geometry in memory, for precise picking, export and editing.
+    pub keep_mesh_data: bool,
+}
+
+impl Default for ImportSettings {
+    fn default() -> Self {
+        ImportSettings {
+            normal_generation: NormalGeneration::Smooth,
+            generated_lods: 0,
+            lod_reduction: 0.5,
+            keep_mesh_data: true,
+        }
+    }
+}
+
+/// Statistics and problems found while importing one OBJ model.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SubmeshReport {
+    pub name: String,
+    pub vertex_count: usize,
+    pub triangle_count: usize,
+    pub generated_normals: Option<NormalGeneration>,
+    pub missing_uvs: bool,
+    pub degenerate_triangles: usize,
+    pub non_manifold_edges: usize,
+    pub duplicate_vertices: usize,
+    /// Triangles dropped because they referenced a vertex that does not exist.
+    pub invalid_triangles: usize,
+    pub invalid_material_id: Option<usize>,
+}
+
+impl SubmeshReport {
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        match self.generated_normals {
+            Some(NormalGeneration::Smooth) => {
+                warnings.push(String::from("Missing normals, smooth normals generated."))
+            }
+            Some(NormalGeneration::Flat) => {
+                warnings.push(String::from("Missing normals, flat normals generated."))
+            }
+            None => (),
+        }
+        if self.missing_uvs {
+            warnings.push(String::from("Missing texture coordinates."));
+        }
+        if self.degenerate_triangles > 0 {
+            warnings.push(format!(
+                "{} degenerate triangles.",
+                self.degenerate_triangles
+            ));
+        }
+        if self.non_manifold_edges > 0 {
+            warnings.push(format!("{} non-manifold edges.", self.non_manifold_edges));
+        }
+        if self.duplicate_vertices > 0 {
+            warnings.push(format!("{} duplicate vertices.", self.duplicate_vertices));
+        }
+        if self.invalid_triangles > 0 {
+            warnings.push(format!(
+                "{} triangles with out of range indices were removed.",
+                self.invalid_triangles
+            ));
+        }
+        if let Some(id) = self.invalid_material_id {
+            warnings.push(format!(
+                "Material id {} is out of range, the default material is used.",
+                id
+            ));
+        }
+        warnings
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportReport {
+    pub path: PathBuf,
+    pub submeshes: Vec<SubmeshReport>,
+    /// Diffuse textures referenced by the materials that could not be read.
+    pub missing_textures: Vec<PathBuf>,
+}
+
+impl ImportReport {
+    pub fn warning_count(&self) -> usize {
+        self.submeshes
+            .iter()
+            .map(|submesh| submesh.warnings().len())
+            .sum::<usize>()
+            + self.missing_textures.len()
+    }
+}
+
+/// Step of `read_model`, reported along with the fraction of the step done.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImportStage {
+    Parsing,
+    Validating,
+    GeneratingLods,
+    DecodingTextures,
+}
+
+impl ImportStage {
+    pub fn name(self) -> &'static str {
+        match self {
+            ImportStage::Parsing => "Parsing",
+            ImportStage::Validating => "Validating",
+            ImportStage::GeneratingLods => "Generating LODs",
+            ImportStage::DecodingTextures => "Decoding textures",
+        }
+    }
+}
+
+pub struct ImportedMaterial {
+    pub diffuse: Vector3<f32>,
+    pub roughness: f32,
+    pub metallic: f32,
+    /// Decoded diffuse texture, flipped for OpenGL.
+    pub diffuse_image: Option<DynamicImage>,
+    /// File of the diffuse texture, to save the material with it.
+    pub diffuse_path: Option<PathBuf>,
+    /// File of the dissolve map merged into the alpha of the diffuse texture.
+    pub alpha_path: Option<PathBuf>,
+    pub opacity: f32,
+    pub alpha_cutoff: f32,
+    pub blend_mode: BlendMode,
+}
+
+/// Content of a model file read on the CPU, the GPU resources are created by
+/// `Scene::add_model`.
+pub struct ImportedModel {
+    pub submeshes: Vec<ImportedSubmesh>,
+    /// Levels of detail 1 and up, read from `_LOD<n>` objects or generated.
+    pub lods: Vec<Vec<ImportedSubmesh>>,
+    pub materials: Vec<ImportedMaterial>,
+    /// Joints deforming the skinned submeshes, parents first.
+    pub joints: Vec<Joint>,
+    /// Blend shapes of the submeshes, with their initial weights.
+    pub morph_target_names: Vec<String>,
+    pub morph_weights: Vec<f32>,
+    pub clips: Vec<AnimationClip>,
+    pub report: ImportReport,
+}
+
+/// Name of the model of a file, its name without the extension.
+pub fn model_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Reads an OBJ or glTF file, depending on its extension.
+pub fn read_model(
+    path: &Path,
+    resources: &Resources,
+    settings: &ImportSettings,
+    progress: &dyn Fn(ImportStage, f32),
+) -> Result<ImportedModel, Box<dyn Error + Send + Sync>> {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "gltf" | "glb" => read_gltf(path, progress),
+        _ => read_obj(path, resources, settings, progress),
+    }
+}
+
+/// Parses, validates and decodes the textures of an OBJ file.
+///
+/// Does not touch OpenGL so it can run on a worker thread.
+pub fn read_obj(
+    path: &Path,
+    resources: &Resources,
+    settings: &ImportSettings,
+    progress: &dyn Fn(ImportStage, f32),
+) -> Result<ImportedModel, Box<dyn Error + Send + Sync>> {
+    progress(ImportStage::Parsing, 0.0);
+    let (models, materials) = tobj::load_obj(path, true)?;
+
+    let mut submeshes = Vec::new();
+    let mut lods: Vec<Vec<ImportedSubmesh>> = Vec::new();
+    let mut report = ImportReport {
+        path: path.to_path_buf(),
+        submeshes: Vec::new(),
+        missing_textures: Vec::new(),
+    };
+    for (i, model) in models.iter().enumerate() {
+        progress(ImportStage::Validating, i as f32 / models.len() as f32);
+        let mut imported = import_submesh(model, materials.len(), settings);
+        report.submeshes.push(std::mem::take(&mut imported.report));
+        match lod_level(&model.name) {
+            0 => submeshes.push(imported),
+            level => {
+                if lods.len() < level {
+                    lods.resize_with(level, Vec::new);
+                }
+                lods[level - 1].push(imported);
+            }
+        }
+    }
+    // Levels missing from the file are skipped.
+    lods.retain(|level| !level.is_empty());
+    if lods.is_empty() {
+        lods = generate_lods(&submeshes, settings, progress);
+    }
+
+    let mut imported_materials = Vec::new();
+    for (i, material) in materials.iter().enumerate() {
+        progress(
+            ImportStage::DecodingTextures,
+            i as f32 / materials.len() as f32,
+        );
+        let mut open_texture = |texture_path: &str| {
+            if texture_path.is_empty() {
+                return None;
+            }
+            // Next to the OBJ file, as exported ones are, or else in the resources.
+            let beside = path.parent().map(|directory| directory.join(texture_path));
+            let full_path = match beside {
+                Some(beside) if !texture_path.starts_with("res://") && beside.is_file() => beside,
+                _ => resources.resource_name_to_path(&texture_path.replace("res://", "")),
+            };
+            match image::open(&full_path) {
+                Ok(image) => Some((image.flipv(), full_path)),
+                Err(_) => {
+                    report.missing_textures.push(full_path);
+                    None
+                }
+            }
+        };
+        let (mut diffuse_image, diffuse_path) = match open_texture(&material.diffuse_texture) {
+            Some((image, path)) => (Some(image), Some(path)),
+            None => (None, None),
+        };
+        let (alpha_image, alpha_path) = match open_texture(&material.dissolve_texture) {
+            Some((image, path)) => (Some(image), Some(path)),
+            None => (None, None),
+        };
+        // Extensions of the format, written by the material editor and the exporter.
+        let extension_parameter = |name: &str| {
+            material
+                .unknown_param
+                .get(name)
+                .and_then(|value| value.trim().parse::<f32>().ok())
+        };
+        let blend_mode = material
+            .unknown_param
+            .get("blend_mode")
+            .and_then(|name| blend_mode_from_name(name.trim()));
+        // Otherwise a dissolve map usually cuts out leaves or fences, a dissolve factor is glass.
+        let blend_mode = blend_mode.unwrap_or(if material.dissolve < 1.0 {
+            BlendMode::Blended
+        } else if alpha_image.is_some() {
+            BlendMode::Masked
+        } else {
+            BlendMode::Opaque
+        });
+        if let Some(alpha_image) = alpha_image {
+            diffuse_image = Some(with_alpha(diffuse_image, &alpha_image));
+        }
+        imported_materials.push(ImportedMaterial {
+            diffuse: Vector3::from(material.diffuse),
+            // Usual conversion from a Blinn-Phong exponent to a GGX roughness.
+            roughness: extension_parameter("Pr")
+                .unwrap_or_else(|| (2.0 / (material.shininess + 2.0)).sqrt()),
+            metallic: extension_parameter("Pm").unwrap_or(0.0),
+            diffuse_image,
+            diffuse_path,
+            alpha_path,
+            opacity: material.dissolve,
+            alpha_cutoff: extension_parameter("alpha_cutoff").unwrap_or(0.5),
+            blend_mode,
+        });
+    }
+
+    Ok(ImportedModel {
+        submeshes,
+        lods,
+        materials: imported_materials,
+        joints: Vec::new(),
+        morph_target_names: Vec::new(),
+        morph_weights: Vec::new(),
+        clips: Vec::new(),
+        report,
+    })
+}
+
+/// Vertices and indices of a model, validated and ready to be uploaded.
+pub struct ImportedSubmesh {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    /// One per vertex for skinned submeshes, empty otherwise.
+    pub skin: Vec<JointWeights>,
+    pub morph_targets: Vec<MorphTarget>,
+    /// Index of the weight of the first morph target in the weights of the model.
+    pub morph_weight_offset: usize,
+    pub material_index: Option<usize>,
+    pub report: SubmeshReport,
+}
+
+pub fn import_submesh(
+    model: &tobj::Model,
+    material_count: usize,
+    settings: &ImportSettings,
+) -> ImportedSubmesh {
+    let obj_mesh = &model.mesh;
+    let vertex_count = obj_mesh.positions.len() / 3;
+    let has_normals = obj_mesh.normals.len() == vertex_count * 3;
+    let has_uvs = obj_mesh.texcoords.len() == vertex_count * 2;
+
+    let mut report = SubmeshReport {
+        name: model.name.clone(),
+        missing_uvs: !has_uvs,
+        ..SubmeshReport::default()
+    };
+
+    let mut vertices: Vec<Vertex> = (0..vertex_count)
+        .map(|i| {
+            let position = Vector3::new(
+                obj_mesh.positions[i * 3],
+                obj_mesh.positions[i * 3 + 1],
+                obj_mesh.positions[i * 3 + 2],
+            );
+            let normal = if has_normals {
+                Vector3::new(
+                    obj_mesh.normals[i * 3],
+                    obj_mesh.normals[i * 3 + 1],
+                    obj_mesh.normals[i * 3 + 2],
+                )
+            } else {
+                // Kept by faces without an area.
+                Vector3::unit_y()
+            };
+            let uv = if has_uvs {
+                Vector2::new(obj_mesh.texcoords[i * 2], obj_mesh.texcoords[i * 2 + 1])
+            } else {
+                Vector2::zero()
+            };
+            Vertex {
+                position,
+                normal,
+                uv,
+            }
+        })
+        .collect();
+
+    let mut indices = Vec::with_capacity(obj_mesh.indices.len());
+    for triangle in obj_mesh.indices.chunks_exact(3) {
+        if triangle
+            .iter()
+            .all(|&index| (index as usize) < vertex_count)
+        {
+            indices.extend_from_slice(triangle);
+        } else {
+            report.invalid_triangles += 1;
+        }
+    }
+
+    let welded = weld_positions(&vertices);
+    report.duplicate_vertices = count_duplicate_vertices(&vertices);
+    report.degenerate_triangles = count_degenerate_triangles(&vertices, &welded, &indices);
+    report.non_manifold_edges = count_non_manifold_edges(&welded, &indices);
+
+    if !has_normals {
+        let (generated_vertices, generated_indices) =
+            generate_normals(&model.name, vertices, indices, settings.normal_generation);
+        vertices = generated_vertices;
+        indices = generated_indices;
+        report.generated_normals = Some(settings.normal_generation);
+    }
+
+    let material_index = match obj_mesh.material_id {
+        Some(id) if id >= material_count => {
+            report.invalid_material_id = Some(id);
+            None
+        }
+        id => id,
+    };
+
+    report.vertex_count = vertices.len();
+    report.triangle_count = indices.len() / 3;
+    ImportedSubmesh {
+        vertices,
+        indices,
+        skin: Vec::new(),
+        morph_targets: Vec::new(),
+        morph_weight_offset: 0,
+        material_index,
+        report,
+    }
+}
+
+/// Level of detail of an OBJ object from its name, `Rock_LOD2` is level 2.
+fn lod_level(name: &str) -> usize {
+    name.rfind("_LOD")
+        .and_then(|start| name[start + 4..].parse().ok())
+        .unwrap_or(0)
+}
+
+/// Replaces the alpha of the diffuse texture by the luminance of `alpha_image`, a white
+/// texture is created when there is none.
+pub fn with_alpha(diffuse_image: Option<DynamicImage>, alpha_image: &DynamicImage) -> DynamicImage {
+    let alpha = alpha_image.to_luma8();
+    let mut rgba = match diffuse_image {
+        Some(diffuse_image) => diffuse_image.to_rgba8(),
+        None => image::RgbaImage::from_pixel(
+            alpha.width(),
+            alpha.height(),
+            image::Rgba([255, 255, 255, 255]),
+        ),
+    };
+    let alpha = if alpha.dimensions() == rgba.dimensions() {
+        alpha
+    } else {
+        image::imageops::resize(
+            &alpha,
+            rgba.width(),
+            rgba.height(),
+            image::imageops::FilterType::Triangle,
+        )
+    };
+    for (pixel, alpha) in rgba.pixels_mut().zip(alpha.pixels()) {
+        pixel[3] = alpha[0];
+    }
+    DynamicImage::ImageRgba8(rgba)
+}
+
+/// Simplifies each level from the previous one, `settings.generated_lods` times.
+fn generate_lods(
+    submeshes: &[ImportedSubmesh],
+    settings: &ImportSettings,
+    progress: &dyn Fn(ImportStage, f32),
+) -> Vec<Vec<ImportedSubmesh>> {
+    let mut lods: Vec<Vec<ImportedSubmesh>> = Vec::new();
+    for level in 1..=settings.generated_lods {
+        progress(
+            ImportStage::GeneratingLods,
+            (level - 1) as f32 / settings.generated_lods as f32,
+        );
+        let previous = lods.last().map_or(submeshes, |lod| lod.as_slice());
+        let lod = previous
+            .iter()
+            .map(|submesh| {
+                let triangle_count = submesh.indices.len() / 3;
+                let target = (triangle_count as f32 * settings.lod_reduction) as usize;
+                let (vertices, indices) =
+                    simplify(&submesh.vertices, &submesh.indices, target.max(1));
+                ImportedSubmesh {
+                    report: SubmeshReport {
+                        vertex_count: vertices.len(),
+                        triangle_count: indices.len() / 3,
+                        ..SubmeshReport::default()
+                    },
+                    vertices,
+                    indices,
+                    skin: Vec::new(),
+                    morph_targets: Vec::new(),
+                    morph_weight_offset: 0,
+                    material_index: submesh.material_index,
+                }
+            })
+            .collect();
+        lods.push(lod);
+    }
+    lods
+}
+
+fn count_duplicate_vertices(vertices: &[Vertex]) -> usize {
+    let mut seen = HashSet::new();
+    let mut duplicates = 0;
+    for vertex in vertices {
+        let key = (
+            position_key(vertex.position),
+            position_key(vertex.normal),
+            [vertex.uv.x.to_bits(), vertex.uv.y.to_bits()],
+        );
+        if !seen.insert(key) {
+            duplicates += 1;
+        }
+    }
+    duplicates
+}
+
+fn count_degenerate_triangles(vertices: &[Vertex], welded: &[u32], indices: &[u32]) -> usize {
+    indices
+        .chunks_exact(3)
+        .filter(|triangle| {
+            let [a, b, c] = [
+                welded[triangle[0] as usize],
+                welded[triangle[1] as usize],
+                welded[triangle[2] as usize],
+            ];
+            if a == b || b == c || a == c {
+                return true;
+            }
+            let p0 = vertices[a as usize].position;
+            let p1 = vertices[b as usize].position;
+            let p2 = vertices[c as usize].position;
+            (p1 - p0).cross(p2 - p0).magnitude2() <= f32::EPSILON * f32::EPSILON
+        })
+        .count()
+}
+
+/// Edges shared by more than two triangles.
+fn count_non_manifold_edges(welded: &[u32], indices: &[u32]) -> usize {
+    let mut edge_faces: HashMap<(u32, u32), u32> = HashMap::new();
+    for triangle in indices.chunks_exact(3) {
+        let corners = [
+            welded[triangle[0] as usize],
+            welded[triangle[1] as usize],
+            welded[triangle[2] as usize],
+        ];
+        // Degenerate triangles are reported separately.
+        if corners[0] == corners[1] || corners[1] == corners[2] || corners[0] == corners[2] {
+            continue;
+        }
+        for (start, end) in [(0, 1), (1, 2), (2, 0)].iter() {
+            let (a, b) = (corners[*start], corners[*end]);
+            *edge_faces.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+        }
+    }
+    edge_faces.values().filter(|&&count| count > 2).count()
+}
+
+/// Fills in the normals of vertices imported without them, faces split from their
+/// neighbours with `NormalGeneration::Flat` get their own vertices.
+pub fn generate_normals(
+    name: &str,
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+    generation: NormalGeneration,
+) -> (Vec<Vertex>, Vec<u32>) {
+    let angle_threshold = match generation {
+        NormalGeneration::Smooth => PI,
+        NormalGeneration::Flat => 0.0,
+    };
+    let mut data = MeshData::new(vertices, indices);
+    if let Err(err) = data.recompute_normals(angle_threshold) {
+        eprintln!("Could not generate the normals of {}: {}", name, err);
+    }
+    (data.vertices, data.indices)
+}
@@ -0,0 +1,699 @@
+use crate::components::Camera;
+use cgmath::prelude::*;
+use cgmath::{Vector2, Vector3};
+use sdl2::event::Event;
+use sdl2::keyboard::{KeyboardState, Keycode, Mod, Scancode};
+use sdl2::mouse::{MouseButton, MouseState};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Keys moving a camera in fly mode, see `InputBindings::fly`.
+const FLY_KEYS: [Scancode; 6] = [
+    Scancode::W,
+    Scancode::A,
+    Scancode::S,
+    Scancode::D,
+    Scancode::Q,
+    Scancode::E,
+];
+
+/// Whether the key moves a camera in fly mode, its binding is ignored while flying then.
+pub fn is_fly_key(scancode: Scancode) -> bool {
+    FLY_KEYS.contains(&scancode)
+}
+
+/// Where the bindings edited in the preferences window are saved, relative to the working
+/// directory.
+pub const BINDINGS_PATH: &str = "input_bindings.cfg";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Orbit,
+    Pan,
+    Zoom,
+    Focus,
+    FrameAll,
+    ToggleFly,
+    Screenshot,
+}
+
+impl Action {
+    pub const ALL: [Action; 7] = [
+        Action::Orbit,
+        Action::Pan,
+        Action::Zoom,
+        Action::Focus,
+        Action::FrameAll,
+        Action::ToggleFly,
+        Action::Screenshot,
+    ];
+
+    /// Name used in the bindings file.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Orbit => "orbit",
+            Action::Pan => "pan",
+            Action::Zoom => "zoom",
+            Action::Focus => "focus",
+            Action::FrameAll => "frame_all",
+            Action::ToggleFly => "toggle_fly",
+            Action::Screenshot => "screenshot",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::Orbit => "Orbit",
+            Action::Pan => "Pan",
+            Action::Zoom => "Zoom",
+            Action::Focus => "Frame Selection",
+            Action::FrameAll => "Frame All",
+            Action::ToggleFly => "Toggle Fly Mode",
+            Action::Screenshot => "Screenshot",
+        }
+    }
+
+    /// Drag actions are active while their binding is held and the mouse moves, the others
+    /// fire once when their binding is pressed.
+    pub fn is_drag(self) -> bool {
+        match self {
+            Action::Orbit | Action::Pan | Action::Zoom => true,
+            Action::Focus | Action::FrameAll | Action::ToggleFly | Action::Screenshot => false,
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Action> {
+        Action::ALL
+            .iter()
+            .copied()
+            .find(|action| action.name() == name)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+}
+
+impl Modifiers {
+    pub const NONE: Modifiers = Modifiers {
+        ctrl: false,
+        alt: false,
+        shift: false,
+    };
+
+    pub fn from_keymod(keymod: Mod) -> Self {
+        Modifiers {
+            ctrl: keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD),
+            alt: keymod.intersects(Mod::LALTMOD | Mod::RALTMOD),
+            shift: keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD),
+        }
+    }
+
+    /// Modifiers held according to the last processed events, mouse events do not carry them.
+    pub fn from_keyboard(keyboard: &KeyboardState) -> Self {
+        let pressed =
+            |left, right| keyboard.is_scancode_pressed(left) || keyboard.is_scancode_pressed(right);
+        Modifiers {
+            ctrl: pressed(Scancode::LCtrl, Scancode::RCtrl),
+            alt: pressed(Scancode::LAlt, Scancode::RAlt),
+            shift: pressed(Scancode::LShift, Scancode::RShift),
+        }
+    }
+
+    fn alt(self) -> Self {
+        Modifiers { alt: true, ..self }
+    }
+
+    fn ctrl(self) -> Self {
+        Modifiers { ctrl: true, ..self }
+    }
+
+    fn shift(self) -> Self {
+        Modifiers {
+            shift: true,
+            ..self
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Trigger {
+    Mouse(MouseButton),
+    Key(Keycode),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Binding {
+    pub trigger: Trigger,
+    pub modifiers: Modifiers,
+}
+
+impl Binding {
+    pub fn mouse(button: MouseButton, modifiers: Modifiers) -> Self {
+        Binding {
+            trigger: Trigger::Mouse(button),
+            modifiers,
+        }
+    }
+
+    pub fn key(keycode: Keycode, modifiers: Modifiers) -> Self {
+        Binding {
+            trigger: Trigger::Key(keycode),
+            modifiers,
+        }
+    }
+
+    /// Parses the `to_string` format, for example `Ctrl+Alt+Mouse Middle` or `Shift+F`.
+    fn parse(text: &str) -> Option<Binding> {
+        let mut modifiers = Modifiers::NONE;
+        let mut rest = text.trim();
+        loop {
+            if let Some(stripped) = rest.strip_prefix("Ctrl+") {
+                modifiers.ctrl = true;
+                rest = stripped;
+            } else if let Some(stripped) = rest.strip_prefix("Alt+") {
+                modifiers.alt = true;
+                rest = stripped;
+            } else if let Some(stripped) = rest.strip_prefix("Shift+") {
+                modifiers.shift = true;
+                rest = stripped;
+            } else {
+                break;
+            }
+        }
+
+        let trigger = match rest {
+            "Mouse Left" => Trigger::Mouse(MouseButton::Left),
+            "Mouse Middle" => Trigger::Mouse(MouseButton::Middle),
+            "Mouse Right" => Trigger::Mouse(MouseButton::Right),
+            "Mouse X1" => Trigger::Mouse(MouseButton::X1),
+            "Mouse X2" => Trigger::Mouse(MouseButton::X2),
+            name => Trigger::Key(Keycode::from_name(name)?),
+        };
+        Some(Binding { trigger, modifiers })
+    }
+
+    /// Binding for `action` from a key or mouse button press, drag actions only take mouse
+    /// buttons and the others only keys. Modifier keys alone are not bindings.
+    pub fn capture(action: Action, event: &Event, modifiers: Modifiers) -> Option<Binding> {
+        match *event {
+            Event::MouseButtonDown { mouse_btn, .. } if action.is_drag() => {
+                Some(Binding::mouse(mouse_btn, modifiers))
+            }
+            Event::KeyDown {
+                keycode: Some(keycode),
+                keymod,
+                ..
+            } if !action.is_drag() => match keycode {
+                Keycode::LCtrl
+                | Keycode::RCtrl
+                | Keycode::LAlt
+                | Keycode::RAlt
+                | Keycode::LShift
+                | Keycode::RShift
+                | Keycode::LGui
+                | Keycode::RGui => None,
+                _ => Some(Binding::key(keycode, Modifiers::from_keymod(keymod))),
+            },
+            _ => None,
+        }
+    }
+
+    fn is_held(&self, mouse_state: &MouseState, modifiers: Modifiers) -> bool {
+        match self.trigger {
+            Trigger::Mouse(button) => {
+                self.modifiers == modifiers && mouse_state.is_mouse_button_pressed(button)
+            }
+            Trigger::Key(_) => false,
+        }
+    }
+}
+
+impl std::fmt::Display for Binding {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.modifiers.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.alt {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.shift {
+            write!(f, "Shift+")?;
+        }
+        match self.trigger {
+            Trigger::Mouse(MouseButton::Left) => write!(f, "Mouse Left"),
+            Trigger::Mouse(MouseButton::Middle) => write!(f, "Mouse Middle"),
+            Trigger::Mouse(MouseButton::Right) => write!(f, "Mouse Right"),
+            Trigger::Mouse(MouseButton::X1) => write!(f, "Mouse X1"),
+            Trigger::Mouse(MouseButton::X2) => write!(f, "Mouse X2"),
+            Trigger::Mouse(MouseButton::Unknown) => write!(f, "Mouse Unknown"),
+            Trigger::Key(keycode) => write!(f, "{}", keycode.name()),
+        }
+    }
+}
+
+/// Sets of bindings emulating the navigation of other applications.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Preset {
+    Palantir,
+    Maya,
+    Blender,
+    Max,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 4] = [Preset::Palantir, Preset::Maya, Preset::Blender, Preset::Max];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::Palantir => "Palantir",
+            Preset::Maya => "Maya",
+            Preset::Blender => "Blender",
+            Preset::Max => "3ds Max",
+        }
+    }
+
+    pub fn binding(self, action: Action) -> Binding {
+        let none = Modifiers::NONE;
+        match (self, action) {
+            (_, Action::Screenshot) => Binding::key(Keycode::F12, none),
+            (Preset::Blender, Action::ToggleFly) => Binding::key(Keycode::Backquote, none.shift()),
+            (_, Action::ToggleFly) => Binding::key(Keycode::Tab, none),
+
+            (Preset::Palantir, Action::Orbit) => Binding::mouse(MouseButton::Left, none),
+            (Preset::Palantir, Action::Pan) => Binding::mouse(MouseButton::Middle, none),
+            (Preset::Palantir, Action::Zoom) => Binding::mouse(MouseButton::Right, none),
+            (Preset::Palantir, Action::Focus) => Binding::key(Keycode::F, none),
+            (Preset::Palantir, Action::FrameAll) => Binding::key(Keycode::Home, none),
+
+            (Preset::Maya, Action::Orbit) => Binding::mouse(MouseButton::Left, none.alt()),
+            (Preset::Maya, Action::Pan) => Binding::mouse(MouseButton::Middle, none.alt()),
+            (Preset::Maya, Action::Zoom) => Binding::mouse(MouseButton::Right, none.alt()),
+            (Preset::Maya, Action::Focus) => Binding::key(Keycode::F, none),
+            (Preset::Maya, Action::FrameAll) => Binding::key(Keycode::A, none),
+
+            (Preset::Blender, Action::Orbit) => Binding::mouse(MouseButton::Middle, none),
+            (Preset::Blender, Action::Pan) => Binding::mouse(MouseButton::Middle, none.shift()),
+            (Preset::Blender, Action::Zoom) => Binding::mouse(MouseButton::Middle, none.ctrl()),
+            (Preset::Blender, Action::Focus) => Binding::key(Keycode::KpPeriod, none),
+            (Preset::Blender, Action::FrameAll) => Binding::key(Keycode::Home, none),
+
+            (Preset::Max, Action::Orbit) => Binding::mouse(MouseButton::Middle, none.alt()),
+            (Preset::Max, Action::Pan) => Binding::mouse(MouseButton::Middle, none),
+            (Preset::Max, Action::Zoom) => Binding::mouse(MouseButton::Middle, none.ctrl().alt()),
+            (Preset::Max, Action::Focus) => Binding::key(Keycode::Z, none),
+            (Preset::Max, Action::FrameAll) => Binding::key(Keycode::Z, none.ctrl().shift()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum BindingsError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl From<io::Error> for BindingsError {
+    fn from(other: io::Error) -> Self {
+        BindingsError::Io(other)
+    }
+}
+
+impl std::fmt::Display for BindingsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BindingsError::Io(err) => err.fmt(f),
+            BindingsError::Parse { line, message } => write!(f, "Line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for BindingsError {}
+
+/// Maps input to named actions, along with the sensitivity of the navigation.
+///
+/// A left click that does not move the mouse always selects, whatever is bound to the left
+/// button.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InputBindings {
+    bindings: Vec<(Action, Option<Binding>)>,
+    pub orbit_sensitivity: f32,
+    pub pan_sensitivity: f32,
+    pub zoom_sensitivity: f32,
+    pub wheel_sensitivity: f32,
+    pub invert_wheel: bool,
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        InputBindings::from_preset(Preset::Palantir)
+    }
+}
+
+impl InputBindings {
+    pub fn from_preset(preset: Preset) -> Self {
+        let mut bindings = InputBindings {
+            bindings: Vec::new(),
+            orbit_sensitivity: 0.01,
+            pan_sensitivity: 0.005,
+            zoom_sensitivity: 0.01,
+            wheel_sensitivity: 1.0,
+            invert_wheel: false,
+        };
+        bindings.apply_preset(preset);
+        bindings
+    }
+
+    /// Replaces the bindings of every action, the sensitivities are kept.
+    pub fn apply_preset(&mut self, preset: Preset) {
+        self.bindings = Action::ALL
+            .iter()
+            .map(|&action| (action, Some(preset.binding(action))))
+            .collect();
+    }
+
+    /// The preset the bindings are identical to, if any.
+    pub fn matching_preset(&self) -> Option<Preset> {
+        Preset::ALL.iter().copied().find(|&preset| {
+            Action::ALL
+                .iter()
+                .all(|&action| self.binding(action) == Some(preset.binding(action)))
+        })
+    }
+
+    pub fn binding(&self, action: Action) -> Option<Binding> {
+        self.bindings
+            .iter()
+            .find(|(bound_action, _)| *bound_action == action)
+            .and_then(|(_, binding)| *binding)
+    }
+
+    pub fn set_binding(&mut self, action: Action, binding: Option<Binding>) {
+        match self
+            .bindings
+            .iter_mut()
+            .find(|(bound_action, _)| *bound_action == action)
+        {
+            Some((_, bound)) => *bound = binding,
+            None => self.bindings.push((action, binding)),
+        }
+    }
+
+    /// The drag action whose mouse button is held with exactly these modifiers.
+    pub fn drag_action(&self, mouse_state: &MouseState, modifiers: Modifiers) -> Option<Action> {
+        Action::ALL.iter().copied().find(|&action| {
+            action.is_drag()
+                && self
+                    .binding(action)
+                    .is_some_and(|binding| binding.is_held(mouse_state, modifiers))
+        })
+    }
+
+    /// The action bound to this key press.
+    pub fn key_action(&self, keycode: Keycode, modifiers: Modifiers) -> Option<Action> {
+        let pressed = Binding::key(keycode, modifiers);
+        Action::ALL
+            .iter()
+            .copied()
+            .find(|&action| self.binding(action) == Some(pressed))
+    }
+
+    /// Moves the camera for a drag action and a mouse motion in pixels.
+    pub fn navigate(&self, camera: &mut Camera, action: Action, xrel: i32, yrel: i32) {
+        match action {
+            Action::Orbit => {
+                let y_angle = xrel as f32 * self.orbit_sensitivity;
+                let x_angle = yrel as f32 * self.orbit_sensitivity;
+                camera.rotate(y_angle, x_angle);
+            }
+            Action::Zoom => {
+                let mouse_vector = Vector2::new(xrel as f32, yrel as f32);
+                let mut direction =
+                    mouse_vector.dot(Vector2::unit_x()) + mouse_vector.dot(Vector2::unit_y());
+                if direction > 0.0 {
+                    direction = 1.0;
+                } else if direction < 0.0 {
+                    direction = -1.0;
+                }
+                let zoom_amount = direction * mouse_vector.magnitude() * self.zoom_sensitivity;
+                if !zoom_amount.is_nan() {
+                    camera.zoom(zoom_amount);
+                }
+            }
+            Action::Pan => {
+                let x = xrel as f32 * self.pan_sensitivity;
+                let y = yrel as f32 * self.pan_sensitivity;
+                camera.pan(x, y);
+            }
+            Action::Focus | Action::FrameAll | Action::ToggleFly | Action::Screenshot => (),
+        }
+    }
+
+    /// Moves a camera in fly mode with the keys held, W, A, S and D along the ground and Q and
+    /// E down and up. They are read by position so other keyboard layouts get the same shape,
+    /// Shift sprints.
+    pub fn fly(&self, camera: &mut Camera, keyboard: &KeyboardState, delta_s: f32) {
+        const SPRINT_FACTOR: f32 = 4.0;
+
+        let axis = |negative, positive| {
+            let mut value = 0.0;
+            if keyboard.is_scancode_pressed(negative) {
+                value -= 1.0;
+            }
+            if keyboard.is_scancode_pressed(positive) {
+                value += 1.0;
+            }
+            value
+        };
+        let direction = Vector3::new(
+            axis(Scancode::A, Scancode::D),
+            axis(Scancode::Q, Scancode::E),
+            axis(Scancode::W, Scancode::S),
+        );
+        if direction == Vector3::zero() {
+            return;
+        }
+
+        let mut speed = camera.fly_speed();
+        if Modifiers::from_keyboard(keyboard).shift {
+            speed *= SPRINT_FACTOR;
+        }
+        camera.fly(direction.normalize() * speed * delta_s);
+    }
+
+    /// Scales the fly speed for a mouse wheel scroll.
+    pub fn wheel_fly_speed(&self, camera: &mut Camera, y: i32) {
+        const SPEED_STEP: f32 = 1.2;
+        let speed = camera.fly_speed() * SPEED_STEP.powf(self.wheel_zoom(y));
+        camera.set_fly_speed(speed);
+    }
+
+    /// Zoom amount for a mouse wheel scroll.
+    pub fn wheel_zoom(&self, y: i32) -> f32 {
+        let amount = y as f32 * self.wheel_sensitivity;
+        if self.invert_wheel {
+            -amount
+        } else {
+            amount
+        }
+    }
+
+    /// Reads a bindings file, the actions and settings it does not mention keep their default.
+    pub fn load(path: &Path) -> Result<Self, BindingsError> {
+        InputBindings::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parses the content of a bindings file, see `load`.
+    pub fn parse(text: &str) -> Result<Self, BindingsError> {
+        let mut bindings = InputBindings::default();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parse_error = |message: String| BindingsError::Parse {
+                line: index + 1,
+                message,
+            };
+            let mut parts = line.splitn(2, '=');
+            let key = parts.next().unwrap_or_default().trim();
+            let value = parts
+                .next()
+                .ok_or_else(|| parse_error(format!("Expected `name = value`, got {:?}.", line)))?
+                .trim();
+            let parse_f32 = |value: &str| {
+                value
+                    .parse::<f32>()
+                    .map_err(|_| parse_error(format!("Invalid number {:?}.", value)))
+            };
+
+            match key {
+                "orbit_sensitivity" => bindings.orbit_sensitivity = parse_f32(value)?,
+                "pan_sensitivity" => bindings.pan_sensitivity = parse_f32(value)?,
+                "zoom_sensitivity" => bindings.zoom_sensitivity = parse_f32(value)?,
+                "wheel_sensitivity" => bindings.wheel_sensitivity = parse_f32(value)?,
+                "invert_wheel" => {
+                    bindings.invert_wheel = value
+                        .parse()
+                        .map_err(|_| parse_error(format!("Invalid boolean {:?}.", value)))?
+                }
+                name => {
+                    let action = Action::from_name(name)
+                        .ok_or_else(|| parse_error(format!("Unknown action {:?}.", name)))?;
+                    let binding = if value == "None" {
+                        None
+                    } else {
+                        let binding = Binding::parse(value)
+                            .ok_or_else(|| parse_error(format!("Invalid binding {:?}.", value)))?;
+                        Some(binding)
+                    };
+                    bindings.set_binding(action, binding);
+                }
+            }
+        }
+        Ok(bindings)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.serialize())
+    }
+
+    /// Content of the bindings file written by `save`.
+    pub fn serialize(&self) -> String {
+        let mut content = String::from("# Palantir input bindings\n");
+        for &action in Action::ALL.iter() {
+            let binding = match self.binding(action) {
+                Some(binding) => binding.to_string(),
+                None => String::from("None"),
+            };
+            content.push_str(&format!("{} = {}\n", action.name(), binding));
+        }
+        content.push_str(&format!(
+            "orbit_sensitivity = {}\npan_sensitivity = {}\nzoom_sensitivity = {}\n\
+             wheel_sensitivity = {}\ninvert_wheel = {}\n",
+            self.orbit_sensitivity,
+            self.pan_sensitivity,
+            self.zoom_sensitivity,
+            self.wheel_sensitivity,
+            self.invert_wheel,
+        ));
+        content
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_bindings() {
+        let none = Modifiers::NONE;
+        assert_eq!(
+            Binding::parse("Mouse Left"),
+            Some(Binding::mouse(MouseButton::Left, none))
+        );
+        assert_eq!(
+            Binding::parse(" Ctrl+Alt+Mouse Middle "),
+            Some(Binding::mouse(MouseButton::Middle, none.ctrl().alt()))
+        );
+        assert_eq!(
+            Binding::parse("Shift+F"),
+            Some(Binding::key(Keycode::F, none.shift()))
+        );
+        assert_eq!(
+            Binding::parse("Keypad +"),
+            Some(Binding::key(Keycode::KpPlus, none))
+        );
+        assert_eq!(
+            Binding::parse("Ctrl+Shift+Keypad +"),
+            Some(Binding::key(Keycode::KpPlus, none.ctrl().shift()))
+        );
+        assert_eq!(
+            Binding::parse("Alt++"),
+            Some(Binding::key(Keycode::Plus, none.alt()))
+        );
+        assert_eq!(Binding::parse("Ctrl+"), None);
+        assert_eq!(Binding::parse("Mouse Unknown"), None);
+        assert_eq!(Binding::parse("Not a key"), None);
+    }
+
+    #[test]
+    fn display_round_trip() {
+        let none = Modifiers::NONE;
+        let bindings = [
+            (Binding::mouse(MouseButton::X1, none), "Mouse X1"),
+            (
+                Binding::mouse(MouseButton::Right, none.ctrl().alt().shift()),
+                "Ctrl+Alt+Shift+Mouse Right",
+            ),
+            (Binding::key(Keycode::Home, none), "Home"),
+            (Binding::key(Keycode::KpPlus, none.ctrl()), "Ctrl+Keypad +"),
+            (Binding::key(Keycode::Plus, none.shift()), "Shift++"),
+        ];
+        for &(binding, text) in bindings.iter() {
+            assert_eq!(binding.to_string(), text);
+            assert_eq!(Binding::parse(text), Some(binding));
+        }
+    }
+
+    #[test]
+    fn preset_round_trip() {
+        for &preset in Preset::ALL.iter() {
+            let bindings = InputBindings::from_preset(preset);
+            assert_eq!(bindings.matching_preset(), Some(preset));
+            let parsed = InputBindings::parse(&bindings.serialize()).unwrap();
+            assert_eq!(parsed, bindings);
+            assert_eq!(parsed.matching_preset(), Some(preset));
+        }
+    }
+
+    #[test]
+    fn unbound_actions() {
+        let mut bindings = InputBindings::from_preset(Preset::Maya);
+        bindings.set_binding(Action::Focus, None);
+        bindings.invert_wheel = true;
+        bindings.pan_sensitivity = 0.25;
+        let text = bindings.serialize();
+        assert!(text.contains("focus = None\n"));
+
+        let parsed = InputBindings::parse(&text).unwrap();
+        assert_eq!(parsed, bindings);
+        assert_eq!(parsed.binding(Action::Focus), None);
+        assert_eq!(parsed.matching_preset(), None);
+        assert_eq!(parsed.key_action(Keycode::F, Modifiers::NONE), None);
+    }
+
+    #[test]
+    fn parse_defaults_and_errors() {
+        let parsed = InputBindings::parse("# Comment\n\nframe_all = Ctrl+Keypad +\n").unwrap();
+        let mut expected = InputBindings::default();
+        expected.set_binding(
+            Action::FrameAll,
+            Some(Binding::key(Keycode::KpPlus, Modifiers::NONE.ctrl())),
+        );
+        assert_eq!(parsed, expected);
+
+        let line = |text: &str| match InputBindings::parse(text) {
+            Err(BindingsError::Parse { line, .. }) => Some(line),
+            _ => None,
+        };
+        assert_eq!(line("orbit = Mouse Left\nfocus"), Some(2));
+        assert_eq!(line("unknown = F"), Some(1));
+        assert_eq!(line("focus = Ctrl+"), Some(1));
+        assert_eq!(line("\npan_sensitivity = fast"), Some(2));
+        assert_eq!(line("invert_wheel = yes"), Some(1));
+    }
+
+    #[test]
+    fn load_missing_file() {
+        let path = Path::new("/nonexistent/palantir/bindings.txt");
+        assert!(matches!(
+            InputBindings::load(path),
+            Err(BindingsError::Io(_))
+        ));
+    }
+}
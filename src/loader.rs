@@ -0,0 +1,209 @@
+use crate::import::{read_model, ImportSettings, ImportStage, ImportedModel};
+use crate::resources::Resources;
+use cgmath::Matrix4;
+use image::DynamicImage;
+use legion::prelude::Entity;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LoadStage {
+    Import(ImportStage),
+    /// Read, waiting for its GPU resources to be created on the main thread.
+    Uploading,
+}
+
+impl LoadStage {
+    pub fn name(self) -> &'static str {
+        match self {
+            LoadStage::Import(stage) => stage.name(),
+            LoadStage::Uploading => "Uploading",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoadProgress {
+    pub path: PathBuf,
+    pub stage: LoadStage,
+    /// Fraction of the current stage done.
+    pub fraction: f32,
+}
+
+impl LoadProgress {
+    /// Rough fraction of the whole load done, for progress bars.
+    pub fn overall(&self) -> f32 {
+        let (start, span) = match self.stage {
+            LoadStage::Import(ImportStage::Parsing) => (0.0, 0.1),
+            LoadStage::Import(ImportStage::Validating) => (0.1, 0.3),
+            LoadStage::Import(ImportStage::GeneratingLods) => (0.4, 0.2),
+            LoadStage::Import(ImportStage::DecodingTextures) => (0.6, 0.3),
+            LoadStage::Uploading => (0.9, 0.1),
+        };
+        start + span * self.fraction
+    }
+}
+
+pub enum LoadedAsset {
+    /// Boxed, models are much larger than the other messages of the loader.
+    Model(Box<ImportedModel>),
+    /// Decoded image, flipped for OpenGL, and its file.
+    Image(DynamicImage, PathBuf),
+}
+
+/// What to do with an asset once it is loaded.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LoadTarget {
+    /// Adds the mesh to the world with this transform.
+    Spawn(Matrix4<f32>),
+    /// Uses the image as diffuse texture of the entity's mesh.
+    DiffuseTexture(Entity),
+}
+
+enum LoaderMessage {
+    Progress {
+        id: usize,
+        stage: ImportStage,
+        fraction: f32,
+    },
+    Finished {
+        id: usize,
+        result: Result<LoadedAsset, String>,
+    },
+}
+
+struct LoadJob {
+    id: usize,
+    target: LoadTarget,
+    progress: LoadProgress,
+}
+
+/// Reads files on worker threads and hands them back to the main thread, one per
+/// `next_upload` call, so creating their GPU resources does not stall a frame.
+pub struct AssetLoader {
+    next_id: usize,
+    sender: Sender<LoaderMessage>,
+    receiver: Receiver<LoaderMessage>,
+    jobs: Vec<LoadJob>,
+    upload_queue: VecDeque<(usize, LoadedAsset)>,
+}
+
+impl Default for AssetLoader {
+    fn default() -> Self {
+        AssetLoader::new()
+    }
+}
+
+impl AssetLoader {
+    pub fn new() -> Self {
+        let (sender, receiver) = channel();
+        AssetLoader {
+            next_id: 0,
+            sender,
+            receiver,
+            jobs: Vec::new(),
+            upload_queue: VecDeque::new(),
+        }
+    }
+    pub fn load_model(
+        &mut self,
+        path: &Path,
+        resources: &Resources,
+        settings: ImportSettings,
+        target: LoadTarget,
+    ) {
+        let resources = resources.clone();
+        self.spawn(path, target, move |path, progress| {
+            read_model(path, &resources, &settings, progress)
+                .map(|model| LoadedAsset::Model(Box::new(model)))
+                .map_err(|err| err.to_string())
+        });
+    }
+    pub fn load_image(&mut self, path: &Path, target: LoadTarget) {
+        self.spawn(path, target, |path, progress| {
+            progress(ImportStage::DecodingTextures, 0.0);
+            image::open(path)
+                .map(|image| LoadedAsset::Image(image.flipv(), path.to_path_buf()))
+                .map_err(|err| err.to_string())
+        });
+    }
+    fn spawn<F>(&mut self, path: &Path, target: LoadTarget, load: F)
+    where
+        F: FnOnce(&Path, &dyn Fn(ImportStage, f32)) -> Result<LoadedAsset, String> + Send + 'static,
+    {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.jobs.push(LoadJob {
+            id,
+            target,
+            progress: LoadProgress {
+                path: path.to_path_buf(),
+                stage: LoadStage::Import(ImportStage::Parsing),
+                fraction: 0.0,
+            },
+        });
+
+        let path = path.to_path_buf();
+        let sender = self.sender.clone();
+        thread::spawn(move || {
+            let progress_sender = sender.clone();
+            let progress = move |stage: ImportStage, fraction: f32| {
+                // The loader may have been dropped, there is nobody to report to then.
+                let _ = progress_sender.send(LoaderMessage::Progress {
+                    id,
+                    stage,
+                    fraction,
+                });
+            };
+            let result = load(&path, &progress);
+            let _ = sender.send(LoaderMessage::Finished { id, result });
+        });
+    }
+    pub fn is_busy(&self) -> bool {
+        !self.jobs.is_empty()
+    }
+    pub fn jobs(&self) -> impl Iterator<Item = &LoadProgress> {
+        self.jobs.iter().map(|job| &job.progress)
+    }
+    /// Handles the messages of the workers, returns the files that failed to load.
+    pub fn poll(&mut self) -> Vec<(PathBuf, String)> {
+        let mut failures = Vec::new();
+        while let Ok(message) = self.receiver.try_recv() {
+            match message {
+                LoaderMessage::Progress {
+                    id,
+                    stage,
+                    fraction,
+                } => {
+                    if let Some(job) = self.jobs.iter_mut().find(|job| job.id == id) {
+                        job.progress.stage = LoadStage::Import(stage);
+                        job.progress.fraction = fraction;
+                    }
+                }
+                LoaderMessage::Finished { id, result } => match result {
+                    Ok(asset) => {
+                        if let Some(job) = self.jobs.iter_mut().find(|job| job.id == id) {
+                            job.progress.stage = LoadStage::Uploading;
+                            job.progress.fraction = 0.0;
+                        }
+                        self.upload_queue.push_back((id, asset));
+                    }
+                    Err(err) => {
+                        if let Some(index) = self.jobs.iter().position(|job| job.id == id) {
+                            failures.push((self.jobs.remove(index).progress.path, err));
+                        }
+                    }
+                },
+            }
+        }
+        failures
+    }
+    /// Next file ready to be uploaded, must be called from the thread owning the GL context.
+    pub fn next_upload(&mut self) -> Option<(LoadedAsset, LoadTarget)> {
+        let (id, asset) = self.upload_queue.pop_front()?;
+        let index = self.jobs.iter().position(|job| job.id == id)?;
+        Some((asset, self.jobs.remove(index).target))
+    }
+}
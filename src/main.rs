@@ -1,24 +1,76 @@
+mod animation;
 mod app;
+mod cli;
 mod components;
+mod curves;
+mod drag_drop;
+mod environment;
+mod export;
+mod gltf_import;
 mod gui;
+mod headless;
+mod import;
+mod input;
+mod loader;
+mod material_editor;
+mod material_file;
+mod overlays;
+mod picking;
+mod post_process;
 mod resources;
 mod scene;
+mod screenshot;
+mod shading;
+mod timeline;
+mod viewports;
 
-use crate::components::{MeshComponent, TransformComponent};
+use crate::components::{
+    AnimatorComponent, MeshComponent, MorphWeightsComponent, SkeletonComponent, TransformComponent,
+};
 use app::Application;
 use cgmath::prelude::*;
-use cgmath::{Matrix4, Vector2, Vector3};
-use components::{Camera, Light};
+use cgmath::{Matrix4, Vector3};
+use cli::Options;
+use components::{Camera, CameraMode, Light};
+use export::ExportSettings;
 use imgui::Context;
+use input::{Action, Binding, InputBindings, Modifiers};
 use legion::prelude::*;
-use palantir_lib::{Renderer, ShaderProgram, TCamera};
-use scene::Scene;
+use loader::{AssetLoader, LoadTarget, LoadedAsset};
+use material_editor::MaterialEditor;
+use palantir_lib::{Framebuffer, Renderer, ShaderProgram, TCamera, Texture};
+use scene::{RenderStats, Scene};
+use screenshot::ScreenshotSettings;
 use sdl2::event::{Event, WindowEvent};
-use sdl2::mouse::MouseState;
+use sdl2::keyboard::{KeyboardState, Keycode};
+use sdl2::mouse::{MouseButton, MouseState};
+use std::path::Path;
 use std::time::Instant;
+use timeline::Timeline;
+use viewports::Viewports;
 
 fn main() {
-    let mut app = Application::new(1280, 720).unwrap();
+    let options = match Options::from_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, cli::USAGE);
+            std::process::exit(1);
+        }
+    };
+    if options.help {
+        println!("{}", cli::USAGE);
+        return;
+    }
+    if options.headless {
+        let app = Application::new_headless(options.width, options.height).unwrap();
+        if let Err(err) = headless::render_turntable(&app, &options) {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let mut app = Application::new(options.width, options.height).unwrap();
     let (width, height) = app.window.size();
     let aspect = width as f32 / height as f32;
 
@@ -28,9 +80,38 @@ fn main() {
     let mut scene = Scene::new();
     scene.camera_mut().set_aspect_ratio(aspect);
 
-    let lambert_shader_path = app.resources.resource_name_to_path("shaders/lambert.glsl");
-    let lambert_shader = ShaderProgram::from_path(lambert_shader_path).unwrap();
-    let mut renderer = Renderer::new(lambert_shader);
+    let mut loader = AssetLoader::new();
+    for path in &options.models {
+        let target = LoadTarget::Spawn(Matrix4::identity());
+        loader.load_model(path, &app.resources, *scene.import_settings(), target);
+    }
+
+    let pbr_shader_path = app.resources.resource_name_to_path("shaders/pbr.glsl");
+    let pbr_shader = ShaderProgram::from_path(pbr_shader_path.clone()).unwrap();
+    let skinned_shader =
+        ShaderProgram::from_path_with_defines(pbr_shader_path, &["SKINNED"]).unwrap();
+    let mut renderer = Renderer::new(pbr_shader);
+    renderer.set_skinned_shader(skinned_shader);
+    renderer.set_weighted_blended_shader(
+        ShaderProgram::from_path(
+            app.resources
+                .resource_name_to_path("shaders/weighted_blended_composite.glsl"),
+        )
+        .unwrap(),
+    );
+    renderer.set_skybox(environment::create_skybox(&app.resources).unwrap());
+    renderer.set_shading(shading::create_shading(&app.resources).unwrap());
+    let mut environment_baker = environment::create_environment_baker(&app.resources).unwrap();
+    if let Some(path) = &options.environment {
+        let loaded_environment = environment::load_environment(&mut environment_baker, path)
+            .expect("Error loading environment.");
+        renderer.set_environment(Some(loaded_environment));
+    }
+
+    let (drawable_width, drawable_height) = app.window.drawable_size();
+    let mut post_process_stack =
+        post_process::create_post_process_stack(&app.resources, drawable_width, drawable_height)
+            .unwrap();
 
     let mut imgui = Context::create();
     imgui.set_ini_filename(None);
@@ -39,12 +120,65 @@ fn main() {
     let imgui_renderer =
         imgui_opengl_renderer::Renderer::new(&mut imgui, |s| app.video.gl_get_proc_address(s) as _);
 
+    let mut overlays = overlays::Overlays::new(&app.resources).unwrap();
+    let mut material_editor = MaterialEditor::new(&app.resources).unwrap();
+
+    let mut screenshot_settings = ScreenshotSettings::default();
+    let mut export_settings = ExportSettings::default();
+    let mut screenshot_requested = false;
+    let mut windows = gui::WindowVisibility::default();
+    let mut viewports = Viewports::new(scene.camera());
+    let mut timeline = Timeline::new();
+    // Known once imgui has laid out a frame, the viewports start below it.
+    let mut menu_bar_height = 0.0;
+
+    let bindings_path = Path::new(input::BINDINGS_PATH);
+    let mut input_bindings = if bindings_path.exists() {
+        InputBindings::load(bindings_path).unwrap_or_else(|err| {
+            eprintln!("Could not load input bindings: {}", err);
+            InputBindings::default()
+        })
+    } else {
+        InputBindings::default()
+    };
+    // Action whose binding is replaced by the next key or mouse button pressed.
+    let mut rebinding = None;
+
     let mut last_frame = Instant::now();
+    // Where the left button went down, a release close to it is a click rather than an orbit.
+    let mut click_start = None;
+    // Last cursor position seen inside the window, a drag from another application moves the
+    // cursor without motion events.
+    let mut cursor = None;
 
     'main: loop {
         // EVENT HANDLING
         let mouse_state = MouseState::new(&app.events);
+        let modifiers = Modifiers::from_keyboard(&KeyboardState::new(&app.events));
         for event in app.events.poll_iter() {
+            if let Some(action) = rebinding {
+                if let Event::KeyDown {
+                    keycode: Some(Keycode::Escape),
+                    ..
+                } = event
+                {
+                    rebinding = None;
+                    continue;
+                }
+                if let Some(binding) = Binding::capture(action, &event, modifiers) {
+                    input_bindings.set_binding(action, Some(binding));
+                    rebinding = None;
+                    continue;
+                }
+            }
+            match event {
+                Event::MouseMotion { x, y, .. } => cursor = Some((x as f32, y as f32)),
+                Event::Window {
+                    win_event: WindowEvent::Leave,
+                    ..
+                } => cursor = None,
+                _ => {}
+            }
             imgui_sdl2.handle_event(&mut imgui, &event);
             if imgui_sdl2.ignore_event(&event) {
                 continue;
@@ -56,83 +190,365 @@ fn main() {
                     ..
                 } => unsafe {
                     gl::Viewport(0, 0, x, y);
-                    scene.camera_mut().set_aspect_ratio(x as f32 / y as f32);
                 },
                 Event::MouseMotion { xrel, yrel, .. } => {
-                    const PAN_SENSITIVITY: f32 = 0.005;
-                    const ORBIT_SENSITIVITY: f32 = 0.01;
-                    const ZOOM_SENSITIVITY: f32 = 0.01;
-
-                    if mouse_state.left() {
-                        let y_angle = xrel as f32 * ORBIT_SENSITIVITY;
-                        let x_angle = yrel as f32 * ORBIT_SENSITIVITY;
-                        scene.camera_mut().rotate(Vector3::unit_y(), y_angle);
-                        scene.camera_mut().rotate(Vector3::unit_x(), x_angle);
-                    } else if mouse_state.right() {
-                        let mouse_vector = Vector2::new(xrel as f32, yrel as f32);
-                        let mut direction = mouse_vector.dot(Vector2::unit_x())
-                            + mouse_vector.dot(Vector2::unit_y());
-                        if direction > 0.0 {
-                            direction = 1.0;
-                        } else if direction < 0.0 {
-                            direction = -1.0;
+                    if let Some(action) = input_bindings.drag_action(&mouse_state, modifiers) {
+                        input_bindings.navigate(scene.camera_mut(), action, xrel, yrel);
+                    }
+                }
+                Event::MouseButtonDown {
+                    mouse_btn, x, y, ..
+                } => {
+                    if let Some(index) = viewports.pane_at(x as f32, y as f32) {
+                        if index != viewports.active() {
+                            scene.stop_looking_through();
+                            viewports.activate(index, scene.camera_mut());
                         }
-                        let zoom_amount = direction * mouse_vector.magnitude() * ZOOM_SENSITIVITY;
-                        if !zoom_amount.is_nan() {
-                            scene.camera_mut().zoom(zoom_amount);
+                    }
+                    if mouse_btn == MouseButton::Left {
+                        click_start = Some((x, y));
+                    }
+                }
+                Event::MouseButtonUp {
+                    mouse_btn: MouseButton::Left,
+                    x,
+                    y,
+                    ..
+                } => {
+                    const CLICK_TOLERANCE: i32 = 3;
+                    if let Some((start_x, start_y)) = click_start.take() {
+                        if (x - start_x).abs() <= CLICK_TOLERANCE
+                            && (y - start_y).abs() <= CLICK_TOLERANCE
+                        {
+                            let [left, top, width, height] = viewports.active_pane().rect();
+                            if let Some(ray) = picking::Ray::from_screen(
+                                scene.camera(),
+                                x as f32 - left,
+                                y as f32 - top,
+                                width,
+                                height,
+                            ) {
+                                let picked = picking::pick_entity(&world, &ray);
+                                scene.set_selection(picked);
+                            }
                         }
-                    } else if mouse_state.middle() {
-                        let x = xrel as f32 * PAN_SENSITIVITY;
-                        let y = yrel as f32 * PAN_SENSITIVITY;
-                        scene.camera_mut().pan(x, y);
                     }
                 }
-                Event::MouseWheel { y, .. } => scene.camera_mut().zoom(y as f32),
-                Event::KeyDown { keycode, .. } => match keycode {
-                    //TODO: refacto to focus on selection
-                    Some(sdl2::keyboard::Keycode::F) => scene.camera_mut().focus(),
+                Event::DropFile { filename, .. } => {
+                    let hovered = cursor.and_then(|(x, y)| Some((viewports.pane_at(x, y)?, x, y)));
+                    if let Some((index, _, _)) = hovered {
+                        if index != viewports.active() {
+                            scene.stop_looking_through();
+                            viewports.activate(index, scene.camera_mut());
+                        }
+                    }
+                    let [left, top, width, height] = viewports.active_pane().rect();
+                    let position = match hovered {
+                        Some((_, x, y)) => (x - left, y - top),
+                        None => (width / 2.0, height / 2.0),
+                    };
+                    drag_drop::on_file_dropped(
+                        Path::new(&filename),
+                        position,
+                        (width, height),
+                        &scene,
+                        &world,
+                        &mut loader,
+                        &app.resources,
+                    );
+                }
+                Event::MouseWheel { y, .. } => {
+                    if scene.camera().mode() == CameraMode::Fly {
+                        input_bindings.wheel_fly_speed(scene.camera_mut(), y);
+                    } else {
+                        let amount = input_bindings.wheel_zoom(y);
+                        scene.camera_mut().zoom(amount);
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    scancode,
+                    keymod,
+                    ..
+                } => match input_bindings.key_action(keycode, Modifiers::from_keymod(keymod)) {
+                    _ if scene.camera().mode() == CameraMode::Fly
+                        && scancode.is_some_and(input::is_fly_key) => {}
+                    Some(Action::Focus) => match scene.selection_bounds(&world) {
+                        Some(bounds) => scene.camera_mut().frame_bounds(&bounds),
+                        None => scene.frame_all(&world),
+                    },
+                    Some(Action::FrameAll) => scene.frame_all(&world),
+                    Some(Action::ToggleFly) => {
+                        let mode = match scene.camera().mode() {
+                            CameraMode::Orbit => CameraMode::Fly,
+                            CameraMode::Fly => CameraMode::Orbit,
+                        };
+                        scene.camera_mut().set_mode(mode);
+                    }
+                    Some(Action::Screenshot) => screenshot_requested = true,
                     _ => (),
                 },
                 _ => {}
             }
         }
 
+        // BACKGROUND LOADING
+        for (path, err) in loader.poll() {
+            eprintln!("Could not import {}: {}", path.display(), err);
+        }
+        // One file per frame, uploading is what the worker threads cannot do.
+        match loader.next_upload() {
+            Some((LoadedAsset::Model(mut imported), LoadTarget::Spawn(matrix))) => {
+                if imported.report.warning_count() > 0 {
+                    windows.import_reports = true;
+                }
+                let joints = std::mem::take(&mut imported.joints);
+                let clips = std::mem::take(&mut imported.clips);
+                let morph_weights = MorphWeightsComponent {
+                    names: std::mem::take(&mut imported.morph_target_names),
+                    weights: std::mem::take(&mut imported.morph_weights),
+                };
+                let name = import::model_name(&imported.report.path);
+                let mesh = scene.add_model(*imported);
+                let entity = world.insert(
+                    (),
+                    vec![(TransformComponent { matrix }, MeshComponent { name, mesh })],
+                )[0];
+                if !morph_weights.names.is_empty() {
+                    windows.inspector = true;
+                    world.add_component(entity, morph_weights).unwrap();
+                }
+                if !joints.is_empty() {
+                    if !clips.is_empty() {
+                        windows.timeline = true;
+                    }
+                    world
+                        .add_component(entity, SkeletonComponent::new(joints))
+                        .unwrap();
+                    world
+                        .add_component(entity, AnimatorComponent::new(clips))
+                        .unwrap();
+                }
+            }
+            Some((LoadedAsset::Image(image, path), LoadTarget::DiffuseTexture(entity))) => {
+                let texture = Texture::from_image(&image);
+                scene.set_texture_path(texture, path);
+                if !scene.set_diffuse_texture(&mut world, entity, texture) {
+                    eprintln!("The mesh the texture was dropped on no longer exists.");
+                }
+            }
+            _ => (),
+        }
+
+        let now = Instant::now();
+        let delta = now - last_frame;
+        let delta_s = delta.as_secs() as f32 + delta.subsec_nanos() as f32 / 1_000_000_000.0;
+        last_frame = now;
+
+        scene.update_camera(&mut world, delta_s);
+
+        // ANIMATION
+        timeline.fit(scene.animation_duration(&world));
+        timeline.advance(delta_s);
+        scene.update_animation(&mut world, timeline.time());
+        if timeline.take_changed() {
+            scene.apply_keyframes(&mut world, timeline.time());
+        }
+
+        // FLY CAMERA
+        if scene.camera().mode() == CameraMode::Fly && !imgui.io().want_capture_keyboard {
+            let keyboard = KeyboardState::new(&app.events);
+            input_bindings.fly(scene.camera_mut(), &keyboard, delta_s);
+        }
+
         // RENDER SCENE
         let light_matrix = scene.camera().matrix().inverse_transform().unwrap()
             * Matrix4::from_translation(Vector3::new(-2.0, 2.0, 1.0));
         scene.light_mut().set_matrix(light_matrix);
 
-        renderer.clear(0.1, 0.1, 0.1);
-
-        let render_meshes_query = <(Read<TransformComponent>, Read<MeshComponent>)>::query();
-        for (transform, mesh) in render_meshes_query.iter(&mut world) {
-            renderer.draw_mesh(
-                &transform.matrix,
-                &mesh.mesh,
-                scene.camera(),
+        let (window_width, window_height) = app.window.size();
+        let (drawable_width, drawable_height) = app.window.drawable_size();
+        let (pane_width, pane_height) = viewports
+            .resize(
+                menu_bar_height,
+                window_width as f32,
+                window_height as f32,
+                drawable_width as f32 / window_width as f32,
+                scene.camera_mut(),
+            )
+            .unwrap();
+        post_process_stack.resize(pane_width, pane_height).unwrap();
+        if windows.materials {
+            material_editor.render_preview(
+                &mut renderer,
+                &world,
                 scene.light(),
-                gl::TRIANGLES,
+                &post_process_stack.settings,
             );
         }
 
+        let other_cameras = scene.other_cameras(&world);
+        let mesh_bounds = scene.mesh_bounds(&world);
+        let overlay_items = overlays::OverlayItems {
+            selection: scene.selection_bounds(&world),
+            cameras: &other_cameras,
+            bounds: &mesh_bounds,
+        };
+        let mut render_stats = RenderStats::default();
+        for (index, pane) in viewports.visible().iter().enumerate() {
+            let camera = viewports.camera(index, scene.camera());
+            post_process_stack.begin();
+            renderer.clear(0.1, 0.1, 0.1);
+            let stats = scene.render_with_camera(&mut renderer, &world, pane.shading_mode, camera);
+            if index == viewports.active() {
+                render_stats = stats;
+            }
+            overlays.draw(&pane.overlays, camera, &overlay_items);
+            post_process_stack.end(pane.framebuffer());
+        }
+        // The panes are drawn by imgui, this clears what they do not cover.
+        Framebuffer::unbind();
+        unsafe {
+            gl::Viewport(0, 0, drawable_width as i32, drawable_height as i32);
+        }
+        renderer.clear(0.1, 0.1, 0.1);
+
         // IMGUI STUFF
         imgui_sdl2.prepare_frame(imgui.io_mut(), &app.window, &app.events.mouse_state());
 
-        let now = Instant::now();
-        let delta = now - last_frame;
-        let delta_s = delta.as_secs() as f32 + delta.subsec_nanos() as f32 / 1_000_000_000.0;
-        last_frame = now;
-
         imgui.io_mut().delta_time = delta_s;
 
         let ui = imgui.frame();
 
         let fps = 1 as f32 / delta_s;
-        gui::debug_ui(&ui, fps as i32, &mut scene, &mut world, &app);
+        menu_bar_height = ui.frame_height();
+        gui::viewport_panes(&ui, &viewports);
+        let mut layout = viewports.layout();
+        if gui::main_menu_bar(
+            &ui,
+            &mut screenshot_settings,
+            &mut windows,
+            &mut layout,
+            viewports.active_pane_mut(),
+            &mut overlays,
+            &mut renderer,
+        ) {
+            screenshot_requested = true;
+        }
+        if layout != viewports.layout() {
+            scene.stop_looking_through();
+            viewports.set_layout(layout, scene.camera_mut());
+        }
+        let active_pane = viewports.active_pane();
+        if active_pane.overlays.orientation_gizmo {
+            let [left, top, width, _] = active_pane.rect();
+            gui::orientation_gizmo(&ui, scene.camera_mut(), [left + width, top]);
+        }
+        if windows.lighting {
+            gui::lighting_ui(
+                &ui,
+                &mut windows.lighting,
+                &mut scene,
+                &mut renderer,
+                &mut environment_baker,
+                &app,
+            );
+        }
+        if windows.inspector {
+            gui::inspector_ui(&ui, &mut windows.inspector, &mut scene, &mut world);
+        }
+        if windows.materials {
+            gui::material_editor_ui(
+                &ui,
+                &mut windows.materials,
+                &mut material_editor,
+                &mut scene,
+                &mut world,
+                &app,
+            );
+        }
+        if windows.export {
+            gui::export_ui(
+                &ui,
+                &mut windows.export,
+                &mut export_settings,
+                &scene,
+                &world,
+                &app,
+            );
+        }
+        if windows.cameras {
+            gui::cameras_ui(&ui, &mut windows.cameras, &mut scene, &mut world);
+        }
+        if windows.timeline {
+            gui::timeline_ui(
+                &ui,
+                &mut windows.timeline,
+                &mut timeline,
+                &mut scene,
+                &mut world,
+                &app,
+            );
+        }
+        if windows.post_process {
+            gui::post_process_ui(
+                &ui,
+                &mut windows.post_process,
+                &mut post_process_stack.settings,
+            );
+        }
+        if windows.preferences {
+            gui::preferences_ui(
+                &ui,
+                &mut windows.preferences,
+                &mut input_bindings,
+                &mut rebinding,
+            );
+        }
+        gui::debug_ui(
+            &ui,
+            fps as i32,
+            render_stats,
+            &mut scene,
+            &world,
+            &app,
+            &mut loader,
+        );
+        if loader.is_busy() {
+            gui::loading_ui(&ui, &loader);
+        }
+        if windows.import_reports {
+            gui::import_report_ui(&ui, &mut windows.import_reports, &mut scene);
+        }
 
         imgui_sdl2.prepare_render(&ui, &app.window);
         imgui_renderer.render(ui);
 
+        if screenshot_requested {
+            screenshot_requested = false;
+            let result = if screenshot_settings.include_overlay {
+                screenshot::capture_window(&screenshot_settings, drawable_width, drawable_height)
+            } else {
+                // The active viewport, at its size.
+                let pane = viewports.active_pane();
+                screenshot::capture_scene(
+                    &screenshot_settings,
+                    pane_width,
+                    pane_height,
+                    &mut post_process_stack,
+                    |alpha| {
+                        renderer.clear_with_alpha(0.1, 0.1, 0.1, alpha);
+                        scene.render(&mut renderer, &world, pane.shading_mode);
+                        overlays.draw(&pane.overlays, scene.camera(), &overlay_items);
+                    },
+                )
+            };
+            match result {
+                Ok(path) => println!("Saved screenshot to {}", path.display()),
+                Err(err) => eprintln!("Could not save screenshot: {}", err),
+            }
+        }
+
         app.window.gl_swap_window();
     }
 }
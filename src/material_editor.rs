@@ -0,0 +1,113 @@
+use crate::components::MeshComponent;
+use crate::post_process::create_post_process_stack;
+use crate::resources::Resources;
+use crate::{Camera, Light};
+use cgmath::prelude::*;
+use cgmath::{Matrix4, Vector2, Vector3};
+use legion::prelude::*;
+use palantir_lib::{
+    Framebuffer, Material, Mesh, PostProcessSettings, PostProcessStack, Renderer, ShadingMode,
+    SubMesh, Vertex,
+};
+use std::error::Error;
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+/// Width and height of the preview, in pixels.
+pub const PREVIEW_SIZE: u32 = 128;
+
+/// Material being edited, and its preview on a sphere.
+pub struct MaterialEditor {
+    /// Entity of the mesh and index in its materials.
+    pub selection: Option<(Entity, usize)>,
+    preview: Framebuffer,
+    post_process: PostProcessStack,
+    sphere: Mesh,
+    camera: Camera,
+}
+
+impl MaterialEditor {
+    pub fn new(resources: &Resources) -> Result<Self, Box<dyn Error>> {
+        let mut camera = Camera::from_focal_length(50.0, 36.0, 0.1, 100.0, 1.0);
+        camera.set_distance(3.2);
+        camera.rotate(0.0, 20f32.to_radians());
+        let sphere = Mesh::new(vec![uv_sphere(32, 64)]);
+        Ok(MaterialEditor {
+            selection: None,
+            preview: Framebuffer::new(PREVIEW_SIZE, PREVIEW_SIZE)?,
+            post_process: create_post_process_stack(resources, PREVIEW_SIZE, PREVIEW_SIZE)?,
+            sphere,
+            camera,
+        })
+    }
+    /// Image of the last rendered preview, its first row is the bottom one.
+    pub fn preview_texture(&self) -> u32 {
+        self.preview.color_texture()
+    }
+    /// Renders the selected material on the sphere, lit like the scene and through the same
+    /// effects as the viewports.
+    pub fn render_preview(
+        &mut self,
+        renderer: &mut Renderer,
+        world: &World,
+        light: &Light,
+        settings: &PostProcessSettings,
+    ) {
+        let material = match self.selected_material(world) {
+            Some(material) => material,
+            None => return,
+        };
+        self.sphere.materials = vec![material];
+        self.post_process.settings = *settings;
+        self.post_process.begin();
+        renderer.clear(0.1, 0.1, 0.1);
+        let identity = Matrix4::identity();
+        let solid = ShadingMode::Solid;
+        renderer.begin_opaque_pass();
+        renderer.draw_mesh_shaded(&identity, &self.sphere, 0, &self.camera, light, solid);
+        renderer.draw_skybox(&self.camera);
+        renderer.begin_transparent_pass();
+        renderer.draw_mesh_shaded(&identity, &self.sphere, 0, &self.camera, light, solid);
+        renderer.end_transparent_pass();
+        self.post_process.end(Some(&self.preview));
+    }
+    fn selected_material(&self, world: &World) -> Option<Arc<Material>> {
+        let (entity, index) = self.selection?;
+        let mesh = world.get_component::<MeshComponent>(entity)?;
+        mesh.mesh.materials.get(index).cloned()
+    }
+}
+
+/// Unit sphere with `rings` rows of `segments` quads, textured with an equirectangular map.
+fn uv_sphere(rings: u32, segments: u32) -> SubMesh {
+    let mut vertices = Vec::new();
+    for ring in 0..=rings {
+        let v = ring as f32 / rings as f32;
+        let polar = v * PI;
+        for segment in 0..=segments {
+            let u = segment as f32 / segments as f32;
+            let azimuth = u * 2.0 * PI;
+            let normal = Vector3::new(
+                polar.sin() * azimuth.cos(),
+                polar.cos(),
+                -polar.sin() * azimuth.sin(),
+            );
+            vertices.push(Vertex {
+                position: normal,
+                normal,
+                uv: Vector2::new(u, 1.0 - v),
+            });
+        }
+    }
+    let mut indices = Vec::new();
+    let row = segments + 1;
+    for ring in 0..rings {
+        for segment in 0..segments {
+            let top_left = ring * row + segment;
+            let bottom_left = top_left + row;
+            indices.extend_from_slice(&[top_left, bottom_left, top_left + 1]);
+            indices.extend_from_slice(&[top_left + 1, bottom_left, bottom_left + 1]);
+        }
+    }
+    SubMesh::new(vertices, indices, Some(0))
+}
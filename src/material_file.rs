@@ -0,0 +1,326 @@
+use cgmath::Vector3;
+use palantir_lib::{BlendMode, Material};
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+#[derive(Debug)]
+pub enum MaterialError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl std::fmt::Display for MaterialError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            MaterialError::Io(err) => err.fmt(f),
+            MaterialError::Parse { line, message } => write!(f, "Line {}: {}", line, message),
+        }
+    }
+}
+
+impl From<io::Error> for MaterialError {
+    fn from(other: io::Error) -> Self {
+        MaterialError::Io(other)
+    }
+}
+impl std::error::Error for MaterialError {}
+
+fn blend_mode_name(blend_mode: BlendMode) -> &'static str {
+    match blend_mode {
+        BlendMode::Opaque => "opaque",
+        BlendMode::Masked => "masked",
+        BlendMode::Blended => "blended",
+    }
+}
+
+/// Blend mode of a `blend_mode` statement, of a native file or a material library.
+pub fn blend_mode_from_name(name: &str) -> Option<BlendMode> {
+    BlendMode::ALL
+        .iter()
+        .copied()
+        .find(|blend_mode| blend_mode_name(*blend_mode) == name)
+}
+
+/// Parameters of a material and the file of its texture, without the GPU resources.
+///
+/// ```text
+/// diffuse 1 0.5 0.2
+/// metallic 0
+/// roughness 0.4
+/// opacity 1
+/// alpha_cutoff 0.5
+/// blend_mode opaque
+/// diffuse_texture textures/wood.png
+/// alpha_texture textures/wood_mask.png
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct MaterialFile {
+    pub diffuse: Vector3<f32>,
+    pub metallic: f32,
+    pub roughness: f32,
+    pub opacity: f32,
+    pub alpha_cutoff: f32,
+    pub blend_mode: BlendMode,
+    pub diffuse_texture: Option<PathBuf>,
+    /// Image whose luminance replaces the alpha of the diffuse texture.
+    pub alpha_texture: Option<PathBuf>,
+}
+
+impl MaterialFile {
+    pub fn new(material: &Material, diffuse_texture: Option<PathBuf>) -> Self {
+        MaterialFile {
+            diffuse: material.diffuse(),
+            metallic: material.metallic(),
+            roughness: material.roughness(),
+            opacity: material.opacity(),
+            alpha_cutoff: material.alpha_cutoff(),
+            blend_mode: material.blend_mode(),
+            diffuse_texture,
+            alpha_texture: None,
+        }
+    }
+    /// The material without its texture, which is loaded separately.
+    pub fn material(&self) -> Material {
+        let mut material = Material::new(self.diffuse, None);
+        material.set_metallic(self.metallic);
+        material.set_roughness(self.roughness);
+        material.set_opacity(self.opacity);
+        material.set_alpha_cutoff(self.alpha_cutoff);
+        material.set_blend_mode(self.blend_mode);
+        material
+    }
+    /// Reads a native material file, the texture path is relative to the file.
+    pub fn load(path: &Path) -> Result<Self, MaterialError> {
+        let directory = path.parent().unwrap_or_else(|| Path::new(""));
+        MaterialFile::parse(&fs::read_to_string(path)?, directory)
+    }
+    /// Parses the content of a material file in `directory`, see `load`.
+    pub fn parse(text: &str, directory: &Path) -> Result<Self, MaterialError> {
+        let mut file = MaterialFile::new(&Material::new(Vector3::new(1.0, 1.0, 1.0), None), None);
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parse_error = |message: String| MaterialError::Parse {
+                line: index + 1,
+                message,
+            };
+            let parse_f32 = |value: Option<&str>| {
+                let value = value.unwrap_or_default();
+                value
+                    .parse::<f32>()
+                    .map_err(|_| parse_error(format!("Invalid number {:?}.", value)))
+            };
+            let mut parts = line.splitn(2, ' ');
+            let keyword = parts.next().unwrap_or_default();
+            let rest = parts.next().unwrap_or_default().trim();
+            let mut values = rest.split_whitespace();
+            match keyword {
+                "diffuse" => {
+                    file.diffuse = Vector3::new(
+                        parse_f32(values.next())?,
+                        parse_f32(values.next())?,
+                        parse_f32(values.next())?,
+                    )
+                }
+                "metallic" => file.metallic = parse_f32(values.next())?,
+                "roughness" => file.roughness = parse_f32(values.next())?,
+                "opacity" => file.opacity = parse_f32(values.next())?,
+                "alpha_cutoff" => file.alpha_cutoff = parse_f32(values.next())?,
+                "blend_mode" => {
+                    file.blend_mode = blend_mode_from_name(rest)
+                        .ok_or_else(|| parse_error(format!("Unknown blend mode {:?}.", rest)))?
+                }
+                "diffuse_texture" => file.diffuse_texture = Some(directory.join(rest)),
+                "alpha_texture" => file.alpha_texture = Some(directory.join(rest)),
+                _ => return Err(parse_error(format!("Unknown keyword {:?}.", keyword))),
+            }
+        }
+        Ok(file)
+    }
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let directory = path.parent().unwrap_or_else(|| Path::new(""));
+        fs::write(path, self.serialize(directory))
+    }
+    /// Content of the material file written by `save` in `directory`.
+    pub fn serialize(&self, directory: &Path) -> String {
+        let mut content = String::from("# Palantir material\n");
+        let diffuse = self.diffuse;
+        content.push_str(&format!(
+            "diffuse {} {} {}\n",
+            diffuse.x, diffuse.y, diffuse.z
+        ));
+        content.push_str(&format!("metallic {}\n", self.metallic));
+        content.push_str(&format!("roughness {}\n", self.roughness));
+        content.push_str(&format!("opacity {}\n", self.opacity));
+        content.push_str(&format!("alpha_cutoff {}\n", self.alpha_cutoff));
+        content.push_str(&format!(
+            "blend_mode {}\n",
+            blend_mode_name(self.blend_mode)
+        ));
+        if let Some(texture) = self.texture_path_from(directory) {
+            content.push_str(&format!("diffuse_texture {}\n", texture));
+        }
+        if let Some(texture) = &self.alpha_texture {
+            content.push_str(&format!(
+                "alpha_texture {}\n",
+                path_from(texture, directory)
+            ));
+        }
+        content
+    }
+    /// Writes a material library with this material only, named after the file. The texture
+    /// path is relative to `texture_root`, the directory the OBJ importer resolves them from.
+    pub fn save_mtl(&self, path: &Path, texture_root: &Path) -> io::Result<()> {
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| String::from("material"));
+        let mut content = String::from("# Palantir material\n");
+        content.push_str(&self.mtl_entry(&name, texture_root));
+        fs::write(path, content)
+    }
+    /// `newmtl` statement of a material library. The roughness and metallic are written with
+    /// the `Pr` and `Pm` extension, and as a shininess for the readers without it. The blend
+    /// mode and alpha cutoff have extension statements of their own, named as in native files.
+    pub fn mtl_entry(&self, name: &str, texture_root: &Path) -> String {
+        let roughness = self.roughness.max(0.01);
+        let mut content = format!("newmtl {}\n", name);
+        let diffuse = self.diffuse;
+        content.push_str(&format!("Kd {} {} {}\n", diffuse.x, diffuse.y, diffuse.z));
+        // Inverse of the conversion done on import.
+        content.push_str(&format!("Ns {}\n", 2.0 / (roughness * roughness) - 2.0));
+        content.push_str(&format!("Pr {}\n", self.roughness));
+        content.push_str(&format!("Pm {}\n", self.metallic));
+        content.push_str(&format!("d {}\n", self.opacity));
+        content.push_str(&format!(
+            "blend_mode {}\n",
+            blend_mode_name(self.blend_mode)
+        ));
+        content.push_str(&format!("alpha_cutoff {}\n", self.alpha_cutoff));
+        if let Some(texture) = self.texture_path_from(texture_root) {
+            content.push_str(&format!("map_Kd {}\n", texture));
+        }
+        if let Some(texture) = &self.alpha_texture {
+            content.push_str(&format!("map_d {}\n", path_from(texture, texture_root)));
+        }
+        content
+    }
+    /// Path of the texture relative to `directory`, with forward slashes.
+    pub fn texture_path_from(&self, directory: &Path) -> Option<String> {
+        Some(path_from(self.diffuse_texture.as_ref()?, directory))
+    }
+}
+
+/// Goes up with `..` out of `directory` when needed. Stays absolute when the paths share no
+/// root, on different drives for instance.
+fn path_from(path: &Path, directory: &Path) -> String {
+    // Relative paths are relative to the working directory when the other one is absolute.
+    let (path, directory) = match std::env::current_dir() {
+        Ok(current) if path.has_root() || directory.has_root() => {
+            (current.join(path), current.join(directory))
+        }
+        _ => (path.to_path_buf(), directory.to_path_buf()),
+    };
+    let components: Vec<Component> = path.components().collect();
+    let directory_components: Vec<Component> = directory.components().collect();
+    let common = components
+        .iter()
+        .zip(&directory_components)
+        .take_while(|(component, other)| component == other)
+        .count();
+    let relative: PathBuf = if common == 0 && path.has_root() {
+        path.clone()
+    } else {
+        (common..directory_components.len())
+            .map(|_| Component::ParentDir)
+            .chain(components[common..].iter().copied())
+            .collect()
+    };
+    relative.to_string_lossy().replace('\\', "/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn masked_file(directory: &Path) -> MaterialFile {
+        MaterialFile {
+            diffuse: Vector3::new(1.0, 0.5, 0.25),
+            metallic: 0.5,
+            roughness: 0.25,
+            opacity: 1.0,
+            alpha_cutoff: 0.3,
+            blend_mode: BlendMode::Masked,
+            diffuse_texture: Some(directory.join("textures").join("leaf.png")),
+            alpha_texture: Some(directory.join("textures").join("leaf_mask.png")),
+        }
+    }
+
+    #[test]
+    fn serialize_and_parse() {
+        let directory = Path::new("/models");
+        let file = masked_file(directory);
+        let content = file.serialize(directory);
+        assert!(content.contains("diffuse_texture textures/leaf.png\n"));
+        assert_eq!(MaterialFile::parse(&content, directory).unwrap(), file);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let directory = Path::new("/models");
+        match MaterialFile::parse("metallic 0\nroughness rough\n", directory) {
+            Err(MaterialError::Parse { line, .. }) => assert_eq!(line, 2),
+            other => panic!("Unexpected result {:?}.", other),
+        }
+        match MaterialFile::parse("blend_mode additive\n", directory) {
+            Err(MaterialError::Parse { line, .. }) => assert_eq!(line, 1),
+            other => panic!("Unexpected result {:?}.", other),
+        }
+    }
+
+    #[test]
+    fn texture_path_from_parent() {
+        let mut file = masked_file(Path::new("/models/textures"));
+        assert_eq!(
+            file.texture_path_from(Path::new("/models/textures"))
+                .unwrap(),
+            "textures/leaf.png"
+        );
+        assert_eq!(
+            file.texture_path_from(Path::new("/exports/scene")).unwrap(),
+            "../../models/textures/textures/leaf.png"
+        );
+        file.diffuse_texture = Some(PathBuf::from("textures/leaf.png"));
+        assert_eq!(
+            file.texture_path_from(Path::new("exports")).unwrap(),
+            "../textures/leaf.png"
+        );
+    }
+
+    #[test]
+    fn mtl_entry_keeps_blending() {
+        let directory = Path::new("/models");
+        let mut blended = masked_file(directory);
+        blended.blend_mode = BlendMode::Blended;
+        for file in &[masked_file(directory), blended] {
+            let entry = file.mtl_entry("leaf", directory);
+            let (materials, _) = tobj::load_mtl_buf(&mut entry.as_bytes()).unwrap();
+            let material = &materials[0];
+            assert_eq!(material.dissolve, file.opacity);
+            assert_eq!(material.diffuse_texture, "textures/leaf.png");
+            assert_eq!(material.dissolve_texture, "textures/leaf_mask.png");
+            let parameter = |name: &str| material.unknown_param[name].trim().to_owned();
+            assert_eq!(
+                blend_mode_from_name(&parameter("blend_mode")),
+                Some(file.blend_mode)
+            );
+            assert_eq!(
+                parameter("alpha_cutoff").parse::<f32>(),
+                Ok(file.alpha_cutoff)
+            );
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use crate::components::{Camera, Projection};
+use crate::resources::Resources;
+use cgmath::prelude::*;
+use cgmath::{Vector3, Vector4};
+use palantir_lib::{Aabb, Grid, LineRenderer, ShaderError, ShaderProgram, TCamera};
+
+/// Which viewport helpers are drawn, each viewport has its own.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OverlaySettings {
+    pub grid: bool,
+    pub axes: bool,
+    pub orientation_gizmo: bool,
+    pub cameras: bool,
+    pub bounding_boxes: bool,
+}
+
+impl Default for OverlaySettings {
+    fn default() -> Self {
+        OverlaySettings {
+            grid: true,
+            axes: true,
+            orientation_gizmo: true,
+            cameras: true,
+            bounding_boxes: false,
+        }
+    }
+}
+
+/// What the overlays show of the scene, in world space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OverlayItems<'a> {
+    pub selection: Option<Aabb>,
+    /// Camera entities other than the one looked through.
+    pub cameras: &'a [Camera],
+    /// Bounds of every mesh.
+    pub bounds: &'a [Aabb],
+}
+
+/// Scene helpers drawn on top of the meshes: the reference grid, the world axes, the camera
+/// entities and bounding boxes.
+///
+/// The orientation gizmo is drawn with imgui, see `gui::orientation_gizmo`.
+pub struct Overlays {
+    pub axis_length: f32,
+    grid: Grid,
+    lines: LineRenderer,
+}
+
+impl Overlays {
+    pub fn new(resources: &Resources) -> Result<Self, ShaderError> {
+        let load = |name: &str| {
+            ShaderProgram::from_path(
+                resources.resource_name_to_path(&format!("shaders/overlays/{}", name)),
+            )
+        };
+        Ok(Overlays {
+            axis_length: 1.0,
+            grid: Grid::new(load("grid.glsl")?),
+            lines: LineRenderer::new(load("line.glsl")?),
+        })
+    }
+    pub fn grid_mut(&mut self) -> &mut Grid {
+        &mut self.grid
+    }
+    /// Must be called after the scene so the grid blends over the background.
+    pub fn draw<A: TCamera>(
+        &mut self,
+        settings: &OverlaySettings,
+        camera: &A,
+        items: &OverlayItems,
+    ) {
+        if settings.cameras {
+            for other in items.cameras {
+                self.push_camera(other);
+            }
+        }
+        if settings.bounding_boxes {
+            for bounds in items.bounds {
+                self.lines.push_box(bounds, Vector3::new(0.3, 0.7, 0.9));
+            }
+        }
+        if let Some(bounds) = items.selection {
+            self.lines.push_box(&bounds, Vector3::new(1.0, 0.6, 0.1));
+        }
+        if settings.axes {
+            let origin = Vector3::new(0.0, 0.0, 0.0);
+            let length = self.axis_length;
+            self.lines.push_line(
+                origin,
+                Vector3::unit_x() * length,
+                Vector3::new(0.9, 0.2, 0.2),
+            );
+            self.lines.push_line(
+                origin,
+                Vector3::unit_y() * length,
+                Vector3::new(0.2, 0.8, 0.2),
+            );
+            self.lines.push_line(
+                origin,
+                Vector3::unit_z() * length,
+                Vector3::new(0.2, 0.3, 0.9),
+            );
+        }
+        self.lines.draw(camera);
+        if settings.grid {
+            self.grid.draw(camera);
+        }
+    }
+    /// Outlines the start of the view volume of a camera.
+    fn push_camera(&mut self, camera: &Camera) {
+        const DEPTH: f32 = 0.3;
+        const COLOR: Vector3<f32> = Vector3::new(0.8, 0.8, 0.3);
+
+        let view_to_world = match camera.matrix().inverse_transform() {
+            Some(matrix) => matrix,
+            None => return,
+        };
+        let depth = match camera.projection() {
+            Projection::Perspective => DEPTH,
+            Projection::Orthographic => camera.distance(),
+        };
+        let half_height = depth * camera.sensor_size() * 0.5 / camera.focal_length();
+        let half_width = half_height * camera.aspect_ratio();
+        let point =
+            |x: f32, y: f32, z: f32| (view_to_world * Vector4::new(x, y, z, 1.0)).truncate();
+        let far = [
+            point(-half_width, -half_height, -DEPTH),
+            point(half_width, -half_height, -DEPTH),
+            point(half_width, half_height, -DEPTH),
+            point(-half_width, half_height, -DEPTH),
+        ];
+        let near = match camera.projection() {
+            Projection::Perspective => [point(0.0, 0.0, 0.0); 4],
+            Projection::Orthographic => [
+                point(-half_width, -half_height, 0.0),
+                point(half_width, -half_height, 0.0),
+                point(half_width, half_height, 0.0),
+                point(-half_width, half_height, 0.0),
+            ],
+        };
+        for i in 0..4 {
+            self.lines.push_line(near[i], far[i], COLOR);
+            self.lines.push_line(far[i], far[(i + 1) % 4], COLOR);
+            if camera.projection() == Projection::Orthographic {
+                self.lines.push_line(near[i], near[(i + 1) % 4], COLOR);
+            }
+        }
+        // Marks the up side.
+        let up = point(0.0, half_height * 1.4, -DEPTH);
+        self.lines.push_line(far[2], up, COLOR);
+        self.lines.push_line(up, far[3], COLOR);
+    }
+}
@@ -0,0 +1,82 @@
+use crate::components::{MeshComponent, TransformComponent};
+use cgmath::prelude::*;
+use cgmath::{Vector3, Vector4};
+use legion::prelude::*;
+use palantir_lib::TCamera;
+use std::cmp::Ordering;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: Vector3<f32>,
+    /// Normalized.
+    pub direction: Vector3<f32>,
+}
+
+impl Ray {
+    /// Ray from the camera through a point of the viewport, in pixels from its top left corner.
+    ///
+    /// None when the camera matrices cannot be inverted, as with a zero sized viewport.
+    pub fn from_screen<A: TCamera>(
+        camera: &A,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    ) -> Option<Self> {
+        let ndc_x = 2.0 * x / width - 1.0;
+        let ndc_y = 1.0 - 2.0 * y / height;
+        let inverse = (camera.projection_matrix() * camera.matrix()).invert()?;
+        let unproject = |depth: f32| {
+            let point = inverse * Vector4::new(ndc_x, ndc_y, depth, 1.0);
+            point.truncate() / point.w
+        };
+        let near = unproject(-1.0);
+        let far = unproject(1.0);
+        let direction = (far - near).normalize();
+        if !near.magnitude2().is_finite() || !direction.magnitude2().is_finite() {
+            return None;
+        }
+        Some(Ray {
+            origin: near,
+            direction,
+        })
+    }
+    pub fn at(&self, distance: f32) -> Vector3<f32> {
+        self.origin + self.direction * distance
+    }
+    /// Hit with the y = 0 plane, if it is in front of the ray.
+    pub fn intersect_ground(&self) -> Option<Vector3<f32>> {
+        if self.direction.y.abs() < 1e-6 {
+            return None;
+        }
+        let distance = -self.origin.y / self.direction.y;
+        if distance < 0.0 {
+            return None;
+        }
+        Some(self.at(distance))
+    }
+}
+
+/// Closest mesh entity hit by the ray, against the triangles of the meshes keeping their
+/// geometry and the bounding boxes of the others.
+pub fn pick_entity(world: &World, ray: &Ray) -> Option<Entity> {
+    let query = <(Read<TransformComponent>, Read<MeshComponent>)>::query();
+    query
+        .iter_entities(world)
+        .filter_map(|(entity, (transform, mesh))| {
+            let bounds = mesh.mesh.bounds().transformed(&transform.matrix);
+            if bounds.is_empty() {
+                return None;
+            }
+            bounds.intersect_ray(ray.origin, ray.direction)?;
+            // The direction is not normalized in mesh space, so distances stay the world ones.
+            let inverse = transform.matrix.invert()?;
+            let origin = (inverse * ray.origin.extend(1.0)).truncate();
+            let direction = (inverse * ray.direction.extend(0.0)).truncate();
+            mesh.mesh
+                .intersect_ray(origin, direction)
+                .map(|distance| (entity, distance))
+        })
+        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
+        .map(|(entity, _)| entity)
+}
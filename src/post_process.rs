@@ -0,0 +1,20 @@
+use crate::resources::Resources;
+use palantir_lib::{PostProcessShaders, PostProcessStack, ShaderProgram};
+use std::error::Error;
+
+pub fn create_post_process_stack(
+    resources: &Resources,
+    width: u32,
+    height: u32,
+) -> Result<PostProcessStack, Box<dyn Error>> {
+    let load = |name: &str| {
+        ShaderProgram::from_path(resources.resource_name_to_path(&format!("shaders/post/{}", name)))
+    };
+    let shaders = PostProcessShaders {
+        bloom_extract: load("bloom_extract.glsl")?,
+        blur: load("blur.glsl")?,
+        composite: load("composite.glsl")?,
+        fxaa: load("fxaa.glsl")?,
+    };
+    Ok(PostProcessStack::new(width, height, shaders)?)
+}
@@ -26,6 +26,7 @@ impl std::fmt::Display for ResourceError {
 
 impl std::error::Error for ResourceError {}
 
+#[derive(Clone, Debug)]
 pub struct Resources {
     root_path: PathBuf,
 }
@@ -1,15 +1,134 @@
+use crate::components::{
+    AnimatorComponent, CameraComponent, CameraTransition, KeyframesComponent, MeshComponent,
+    MorphWeightsComponent, SkeletonComponent, TransformComponent,
+};
+use crate::curves::{
+    camera_values, light_values, transform_values, AnimationFile, AnimationTarget, CurveSet, Field,
+};
+use crate::export::{ExportedMesh, ExportedSubmesh};
+use crate::import::{
+    read_model, with_alpha, ImportReport, ImportSettings, ImportedModel, ImportedSubmesh,
+};
+use crate::material_file::MaterialFile;
 use crate::resources::Resources;
 use crate::{Camera, Light};
-use cgmath::{Matrix4, Point3, Vector2, Vector3};
-use palantir_lib::{Material, Mesh, SubMesh, Texture, Vertex};
+use cgmath::prelude::*;
+use cgmath::{Matrix4, Point3, Vector3};
+use legion::prelude::*;
+use palantir_lib::{
+    Aabb, Material, Mesh, MeshData, MeshLod, Renderer, ShadingMode, SubMesh, TCamera, Texture,
+};
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::error::Error;
-use std::path::PathBuf;
+use std::f32::consts::PI;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tobj;
+
+/// Meshes drawn and skipped by frustum culling in a render.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    pub drawn: usize,
+    pub culled: usize,
+    /// Triangles of the levels of detail drawn.
+    pub triangles: usize,
+}
+
+/// Parameters of the mesh processing tools.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ProcessingSettings {
+    /// Faces meeting at more than this angle keep separate normals, in degrees.
+    pub normal_angle: f32,
+    /// Vertices closer than this are welded, in mesh units.
+    pub weld_distance: f32,
+}
+
+impl Default for ProcessingSettings {
+    fn default() -> Self {
+        ProcessingSettings {
+            normal_angle: 60.0,
+            weld_distance: 0.0001,
+        }
+    }
+}
+
+/// Edit of the geometry or pivot of a mesh.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MeshOperation {
+    SmoothNormals,
+    FlatNormals,
+    /// Smooths the normals up to the `normal_angle` of the settings.
+    AutoSmoothNormals,
+    Weld,
+    FlipNormals,
+    /// Moves the pivot to the center of the bounds.
+    CenterPivot,
+    /// Moves the pivot to the world origin.
+    ResetPivot,
+    /// Bakes the transform in the vertices.
+    ApplyTransform,
+}
+
+impl MeshOperation {
+    pub const ALL: [MeshOperation; 8] = [
+        MeshOperation::SmoothNormals,
+        MeshOperation::FlatNormals,
+        MeshOperation::AutoSmoothNormals,
+        MeshOperation::Weld,
+        MeshOperation::FlipNormals,
+        MeshOperation::CenterPivot,
+        MeshOperation::ResetPivot,
+        MeshOperation::ApplyTransform,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MeshOperation::SmoothNormals => "Smooth Normals",
+            MeshOperation::FlatNormals => "Flat Normals",
+            MeshOperation::AutoSmoothNormals => "Auto Smooth Normals",
+            MeshOperation::Weld => "Weld Vertices",
+            MeshOperation::FlipNormals => "Flip Normals",
+            MeshOperation::CenterPivot => "Center Pivot",
+            MeshOperation::ResetPivot => "Reset Pivot",
+            MeshOperation::ApplyTransform => "Apply Transform",
+        }
+    }
+    /// Whether it moves the vertices, which skinned and morphed meshes do not support.
+    pub fn moves_vertices(self) -> bool {
+        matches!(
+            self,
+            MeshOperation::CenterPivot | MeshOperation::ResetPivot | MeshOperation::ApplyTransform
+        )
+    }
+    /// Whether it splits or merges vertices, which the morph targets do not follow.
+    /// Smoothing every normal keeps the vertices.
+    pub fn changes_vertex_count(self) -> bool {
+        matches!(
+            self,
+            MeshOperation::FlatNormals | MeshOperation::AutoSmoothNormals | MeshOperation::Weld
+        )
+    }
+}
 
 pub struct Scene {
     camera: Camera,
     light: Light,
+    import_settings: ImportSettings,
+    processing_settings: ProcessingSettings,
+    import_reports: Vec<ImportReport>,
+    selection: Option<Entity>,
+    /// Camera entity the viewport shows, it follows the viewport camera while set.
+    looking_through: Option<Entity>,
+    camera_transition: Option<CameraTransition>,
+    light_curves: CurveSet,
+    /// Files the textures were loaded from, by texture object.
+    texture_paths: HashMap<u32, PathBuf>,
+    /// Files whose luminance replaced the alpha of the textures, by texture object.
+    texture_alpha_paths: HashMap<u32, PathBuf>,
+    /// Skips the meshes outside of the camera's view.
+    pub frustum_culling: bool,
+    /// Draws lower levels of detail of the meshes small on screen.
+    pub lod_selection: bool,
 }
 
 impl Scene {
@@ -22,7 +141,21 @@ impl Scene {
             Vector3::unit_y(),
         ));
 
-        Scene { camera, light }
+        Scene {
+            camera,
+            light,
+            import_settings: ImportSettings::default(),
+            processing_settings: ProcessingSettings::default(),
+            import_reports: Vec::new(),
+            selection: None,
+            looking_through: None,
+            camera_transition: None,
+            light_curves: CurveSet::new(),
+            texture_paths: HashMap::new(),
+            texture_alpha_paths: HashMap::new(),
+            frustum_culling: true,
+            lod_selection: true,
+        }
     }
 }
 
@@ -44,60 +177,701 @@ impl Scene {
     pub fn camera_mut(&mut self) -> &mut Camera {
         &mut self.camera
     }
+    /// Saves the current view as a new camera entity.
+    pub fn bookmark_camera(&self, world: &mut World) -> Entity {
+        let count = <Read<CameraComponent>>::query().iter(world).count();
+        let component = CameraComponent {
+            name: format!("Camera {}", count + 1),
+            camera: self.camera,
+        };
+        world.insert((), vec![(component,)])[0]
+    }
+    pub fn looking_through(&self) -> Option<Entity> {
+        self.looking_through
+    }
+    /// Animates the viewport to the view of a camera entity and keeps it there.
+    pub fn look_through(&mut self, world: &World, entity: Entity) -> bool {
+        const TRANSITION_DURATION: f32 = 0.5;
+
+        let mut target = match world.get_component::<CameraComponent>(entity) {
+            Some(component) => component.camera,
+            None => return false,
+        };
+        target.set_aspect_ratio(self.camera.aspect_ratio());
+        self.camera_transition = Some(CameraTransition::new(
+            self.camera,
+            target,
+            TRANSITION_DURATION,
+        ));
+        self.looking_through = Some(entity);
+        true
+    }
+    /// Detaches the viewport from the camera entity and stops any transition.
+    pub fn stop_looking_through(&mut self) {
+        self.looking_through = None;
+        self.camera_transition = None;
+    }
+    /// Advances camera transitions and writes the viewport camera to the entity it looks
+    /// through.
+    pub fn update_camera(&mut self, world: &mut World, delta_s: f32) {
+        if let Some(transition) = &mut self.camera_transition {
+            self.camera = transition.advance(delta_s);
+            if transition.is_finished() {
+                self.camera_transition = None;
+            }
+            return;
+        }
+        if let Some(entity) = self.looking_through {
+            match world.get_component_mut::<CameraComponent>(entity) {
+                Some(mut component) => component.camera = self.camera,
+                None => self.looking_through = None,
+            }
+        }
+    }
+    /// Camera entities other than the one looked through, for drawing them.
+    pub fn other_cameras(&self, world: &World) -> Vec<Camera> {
+        <Read<CameraComponent>>::query()
+            .iter_entities(world)
+            .filter(|(entity, _)| Some(*entity) != self.looking_through)
+            .map(|(_, component)| component.camera)
+            .collect()
+    }
 }
 
-// Meshes stuff
+// Selection stuff
 impl Scene {
-    pub fn load_obj(&mut self, path: PathBuf, res: &Resources) -> Result<Mesh, Box<dyn Error>> {
-        let (models, materials) = tobj::load_obj(path, true)?;
-
-        let mut submeshes = Vec::<SubMesh>::new();
-        for model in models {
-            let obj_mesh = &model.mesh;
-            let mut vertices: Vec<Vertex> = Vec::new();
-            let indices = obj_mesh.indices.clone();
-            for i in 0..obj_mesh.positions.len() / 3 {
-                let position = Vector3::<f32>::new(
-                    obj_mesh.positions[i * 3],
-                    obj_mesh.positions[i * 3 + 1],
-                    obj_mesh.positions[i * 3 + 2],
-                );
-                let normal = Vector3::<f32>::new(
-                    obj_mesh.normals[i * 3],
-                    obj_mesh.normals[i * 3 + 1],
-                    obj_mesh.normals[i * 3 + 2],
-                );
-                let uv =
-                    Vector2::<f32>::new(obj_mesh.texcoords[i * 2], obj_mesh.texcoords[i * 2 + 1]);
-                vertices.push(Vertex {
-                    position,
-                    normal,
-                    uv,
-                });
+    pub fn selection(&self) -> Option<Entity> {
+        self.selection
+    }
+    pub fn set_selection(&mut self, selection: Option<Entity>) {
+        self.selection = selection;
+    }
+    /// World space bounds of every mesh.
+    pub fn bounds(&self, world: &World) -> Aabb {
+        let query = <(Read<TransformComponent>, Read<MeshComponent>)>::query();
+        query
+            .iter(world)
+            .map(|(transform, mesh)| mesh.mesh.bounds().transformed(&transform.matrix))
+            .fold(Aabb::empty(), |bounds, mesh_bounds| {
+                bounds.union(&mesh_bounds)
+            })
+    }
+    /// Frames every mesh, or looks at the origin when there are none.
+    pub fn frame_all(&mut self, world: &World) {
+        let bounds = self.bounds(world);
+        if bounds.is_empty() {
+            self.camera.focus();
+        } else {
+            self.camera.frame_bounds(&bounds);
+        }
+    }
+    /// World space bounds of each mesh.
+    pub fn mesh_bounds(&self, world: &World) -> Vec<Aabb> {
+        let query = <(Read<TransformComponent>, Read<MeshComponent>)>::query();
+        query
+            .iter(world)
+            .map(|(transform, mesh)| mesh.mesh.bounds().transformed(&transform.matrix))
+            .collect()
+    }
+    /// World space bounds of the selected mesh.
+    pub fn selection_bounds(&self, world: &World) -> Option<Aabb> {
+        let entity = self.selection?;
+        let transform = world.get_component::<TransformComponent>(entity)?;
+        let mesh = world.get_component::<MeshComponent>(entity)?;
+        Some(mesh.mesh.bounds().transformed(&transform.matrix))
+    }
+}
+
+// Animation stuff
+impl Scene {
+    /// Poses the skeletons for the animations at `time`.
+    pub fn update_animation(&self, world: &mut World, time: f32) {
+        let query = <(Write<SkeletonComponent>, Read<AnimatorComponent>)>::query();
+        for (mut skeleton, animator) in query.iter_mut(world) {
+            let pose = animator.pose(time, &skeleton.rest_pose());
+            skeleton.set_pose(pose);
+        }
+    }
+    /// Writes the keyframed fields of the transforms, cameras and light at `time`. The
+    /// viewport follows the camera it looks through.
+    pub fn apply_keyframes(&mut self, world: &mut World, time: f32) {
+        let transforms = <(Write<TransformComponent>, Read<KeyframesComponent>)>::query();
+        for (mut transform, keyframes) in transforms.iter_mut(world) {
+            keyframes
+                .curves
+                .apply_transform(time, &mut transform.matrix);
+        }
+        let cameras = <(Write<CameraComponent>, Read<KeyframesComponent>)>::query();
+        for (entity, (mut component, keyframes)) in cameras.iter_entities_mut(world) {
+            keyframes.curves.apply_camera(time, &mut component.camera);
+            if Some(entity) == self.looking_through && self.camera_transition.is_none() {
+                let aspect = self.camera.aspect_ratio();
+                self.camera = component.camera;
+                self.camera.set_aspect_ratio(aspect);
             }
-            let submesh = SubMesh::new(vertices, indices, obj_mesh.material_id);
-            submeshes.push(submesh);
         }
-        let mut mesh = Mesh {
-            submeshes,
-            materials: Vec::new(),
+        self.light_curves.apply_light(time, &mut self.light);
+    }
+    /// Keys the transform of a mesh at `time`.
+    pub fn key_transform(&self, world: &mut World, entity: Entity, time: f32) -> bool {
+        let matrix = match world.get_component::<TransformComponent>(entity) {
+            Some(transform) => transform.matrix,
+            None => return false,
         };
+        key_entity(world, entity, &transform_values(&matrix), time);
+        true
+    }
+    /// Keys the view of the camera entity looked through at `time`.
+    pub fn key_camera(&self, world: &mut World, time: f32) -> bool {
+        match self.looking_through {
+            Some(entity) => {
+                key_entity(world, entity, &camera_values(&self.camera), time);
+                true
+            }
+            None => false,
+        }
+    }
+    pub fn key_light(&mut self, time: f32) {
+        self.light_curves.key(&light_values(&self.light), time);
+    }
+    pub fn light_curves_mut(&mut self) -> &mut CurveSet {
+        &mut self.light_curves
+    }
+    /// Length of the longest animation clip or keyframe curve, in seconds.
+    pub fn animation_duration(&self, world: &World) -> f32 {
+        let clips = <Read<AnimatorComponent>>::query()
+            .iter(world)
+            .map(|animator| animator.duration())
+            .fold(0.0, f32::max);
+        <Read<KeyframesComponent>>::query()
+            .iter(world)
+            .map(|keyframes| keyframes.curves.duration())
+            .fold(clips, f32::max)
+            .max(self.light_curves.duration())
+    }
+    /// Keyframes of the scene, to be saved.
+    pub fn animation_file(&self, world: &World, length: f32) -> AnimationFile {
+        let mut targets = Vec::new();
+        if !self.light_curves.is_empty() {
+            targets.push((AnimationTarget::Light, self.light_curves.clone()));
+        }
+        let meshes = <(Read<MeshComponent>, Read<KeyframesComponent>)>::query();
+        for (mesh, keyframes) in meshes.iter(world) {
+            let target = AnimationTarget::Mesh(mesh.name.clone());
+            targets.push((target, keyframes.curves.clone()));
+        }
+        let cameras = <(Read<CameraComponent>, Read<KeyframesComponent>)>::query();
+        for (camera, keyframes) in cameras.iter(world) {
+            let target = AnimationTarget::Camera(camera.name.clone());
+            targets.push((target, keyframes.curves.clone()));
+        }
+        AnimationFile { length, targets }
+    }
+    /// Replaces the keyframes of the scene with the ones of a file. Each target goes to the
+    /// first mesh or camera of its name not animated yet, the ones missing from the scene are
+    /// returned.
+    pub fn set_animation_file(
+        &mut self,
+        world: &mut World,
+        file: AnimationFile,
+    ) -> Vec<AnimationTarget> {
+        let keyed: Vec<Entity> = <Read<KeyframesComponent>>::query()
+            .iter_entities(world)
+            .map(|(entity, _)| entity)
+            .collect();
+        for entity in keyed {
+            world
+                .remove_component::<KeyframesComponent>(entity)
+                .unwrap();
+        }
+        self.light_curves = CurveSet::new();
+
+        let mut meshes: Vec<(Entity, String)> = <Read<MeshComponent>>::query()
+            .iter_entities(world)
+            .map(|(entity, mesh)| (entity, mesh.name.clone()))
+            .collect();
+        let mut cameras: Vec<(Entity, String)> = <Read<CameraComponent>>::query()
+            .iter_entities(world)
+            .map(|(entity, camera)| (entity, camera.name.clone()))
+            .collect();
+        let mut missing = Vec::new();
+        for (target, curves) in file.targets {
+            let entity = match &target {
+                AnimationTarget::Light => {
+                    self.light_curves = curves;
+                    continue;
+                }
+                AnimationTarget::Mesh(name) => take_named(&mut meshes, name),
+                AnimationTarget::Camera(name) => take_named(&mut cameras, name),
+            };
+            match entity {
+                Some(entity) => world
+                    .add_component(entity, KeyframesComponent { curves })
+                    .unwrap(),
+                None => missing.push(target),
+            }
+        }
+        missing
+    }
+}
 
-        for material in materials {
-            let texture: Option<Texture>;
-            let texture_path = material.diffuse_texture;
-            if texture_path != "" {
-                let full_path = res.resource_name_to_path(&texture_path.replace("res://", ""));
-                texture = Some(Texture::new(full_path));
+fn key_entity(world: &mut World, entity: Entity, values: &[(Field, f32)], time: f32) {
+    if let Some(mut keyframes) = world.get_component_mut::<KeyframesComponent>(entity) {
+        keyframes.curves.key(values, time);
+        return;
+    }
+    let mut curves = CurveSet::new();
+    curves.key(values, time);
+    world
+        .add_component(entity, KeyframesComponent { curves })
+        .unwrap();
+}
+
+fn take_named(entities: &mut Vec<(Entity, String)>, name: &str) -> Option<Entity> {
+    let index = entities.iter().position(|(_, other)| other == name)?;
+    Some(entities.remove(index).0)
+}
+
+// Rendering stuff
+impl Scene {
+    pub fn render(
+        &self,
+        renderer: &mut Renderer,
+        world: &World,
+        shading_mode: ShadingMode,
+    ) -> RenderStats {
+        self.render_with_camera(renderer, world, shading_mode, &self.camera)
+    }
+    /// Renders from another point of view than the scene's camera, for extra viewports.
+    pub fn render_with_camera(
+        &self,
+        renderer: &mut Renderer,
+        world: &World,
+        shading_mode: ShadingMode,
+        camera: &Camera,
+    ) -> RenderStats {
+        let frustum = camera.frustum();
+        let view = camera.matrix();
+        let mut stats = RenderStats::default();
+        // Meshes with blended materials, by view space depth, drawn after the opaque ones.
+        let mut transparent_meshes = Vec::new();
+        renderer.begin_opaque_pass();
+        let render_meshes_query = <(Read<TransformComponent>, Read<MeshComponent>)>::query();
+        for (entity, (transform, mesh)) in render_meshes_query.iter_entities(world) {
+            let sphere = mesh.mesh.bounding_sphere().transformed(&transform.matrix);
+            // The sphere test is cheap and rejects most meshes, the box is tighter. Animated
+            // meshes are not culled, they can leave the bounds of their rest pose.
+            let animated = world.get_component::<SkeletonComponent>(entity).is_some()
+                || world
+                    .get_component::<MorphWeightsComponent>(entity)
+                    .is_some();
+            if self.frustum_culling && !animated {
+                let visible = frustum.intersects_sphere(&sphere)
+                    && frustum.intersects_aabb(&mesh.mesh.bounds().transformed(&transform.matrix));
+                if !visible {
+                    stats.culled += 1;
+                    continue;
+                }
+            }
+            let level = if self.lod_selection {
+                mesh.mesh.select_lod(camera.screen_size(&sphere))
             } else {
-                texture = None;
+                0
             };
+            self.draw_entity(renderer, world, entity, level, camera, shading_mode);
+            if mesh.mesh.has_transparent_submeshes(level) {
+                let depth = (view * sphere.center.extend(1.0)).z;
+                transparent_meshes.push((depth, entity, level));
+            }
+            stats.drawn += 1;
+            stats.triangles += mesh
+                .mesh
+                .lod_submeshes(level)
+                .iter()
+                .map(|submesh| submesh.index_buffer().count as usize / 3)
+                .sum::<usize>();
+        }
+        renderer.draw_skybox(camera);
+
+        // The camera looks down -z, the farthest meshes have the lowest depth.
+        transparent_meshes
+            .sort_by(|(a, _, _), (b, _, _)| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        renderer.begin_transparent_pass();
+        for (_, entity, level) in transparent_meshes {
+            self.draw_entity(renderer, world, entity, level, camera, shading_mode);
+        }
+        renderer.end_transparent_pass();
+        stats
+    }
+    /// Draws the mesh of an entity with its pose and morph weights.
+    fn draw_entity(
+        &self,
+        renderer: &mut Renderer,
+        world: &World,
+        entity: Entity,
+        level: usize,
+        camera: &Camera,
+        shading_mode: ShadingMode,
+    ) {
+        let (transform, mesh) = match (
+            world.get_component::<TransformComponent>(entity),
+            world.get_component::<MeshComponent>(entity),
+        ) {
+            (Some(transform), Some(mesh)) => (transform, mesh),
+            _ => return,
+        };
+        let skeleton = world.get_component::<SkeletonComponent>(entity);
+        let morph_weights = world.get_component::<MorphWeightsComponent>(entity);
+        if let Some(skeleton) = &skeleton {
+            renderer.set_joint_matrices(skeleton.skinning_matrices());
+        }
+        if let Some(morph_weights) = &morph_weights {
+            renderer.set_morph_weights(&morph_weights.weights);
+        }
+        renderer.draw_mesh_shaded(
+            &transform.matrix,
+            &mesh.mesh,
+            level,
+            camera,
+            &self.light,
+            shading_mode,
+        );
+        if skeleton.is_some() {
+            renderer.set_joint_matrices(&[]);
+        }
+        if morph_weights.is_some() {
+            renderer.set_morph_weights(&[]);
+        }
+    }
+}
+
+// Meshes stuff
+impl Scene {
+    pub fn import_settings_mut(&mut self) -> &mut ImportSettings {
+        &mut self.import_settings
+    }
+    /// Validation reports of the imported files, oldest first.
+    pub fn import_reports(&self) -> &[ImportReport] {
+        &self.import_reports
+    }
+    pub fn clear_import_reports(&mut self) {
+        self.import_reports.clear();
+    }
+    pub fn import_settings(&self) -> &ImportSettings {
+        &self.import_settings
+    }
+    /// Reads and uploads a model file, blocking until it is done. Skeletons are left out.
+    pub fn load_model(&mut self, path: PathBuf, res: &Resources) -> Result<Mesh, Box<dyn Error>> {
+        let imported = read_model(&path, res, &self.import_settings, &|_, _| ())
+            .map_err(|err| err as Box<dyn Error>)?;
+        Ok(self.add_model(imported))
+    }
+    /// Creates the GPU resources of a file read by `read_model` and keeps its report.
+    pub fn add_model(&mut self, imported: ImportedModel) -> Mesh {
+        // Screen size below which level 1 is drawn, halved for each following level.
+        const LOD_SCREEN_SIZE: f32 = 0.5;
+
+        let upload = |submeshes: Vec<ImportedSubmesh>, keep_data: bool| -> Vec<SubMesh> {
+            submeshes
+                .into_iter()
+                .map(|submesh| {
+                    let data = MeshData {
+                        vertices: submesh.vertices,
+                        skin: submesh.skin,
+                        indices: submesh.indices,
+                    };
+                    let mut uploaded = SubMesh::from_data(data, submesh.material_index);
+                    if !keep_data {
+                        uploaded.release_data();
+                    }
+                    uploaded.set_morph_targets(&submesh.morph_targets, submesh.morph_weight_offset);
+                    uploaded
+                })
+                .collect()
+        };
+        let keep_data = self.import_settings.keep_mesh_data;
+        let mut mesh = Mesh::new(upload(imported.submeshes, keep_data));
+        let mut screen_size = LOD_SCREEN_SIZE;
+        for lod in imported.lods {
+            mesh.lods.push(MeshLod {
+                submeshes: upload(lod, false),
+                screen_size,
+            });
+            screen_size *= 0.5;
+        }
+
+        for material in imported.materials {
+            let texture = material.diffuse_image.as_ref().map(Texture::from_image);
+            if let (Some(texture), Some(path)) = (texture, material.diffuse_path) {
+                self.set_texture_path(texture, path);
+            }
+            if let (Some(texture), Some(path)) = (texture, material.alpha_path) {
+                self.texture_alpha_paths.insert(texture.id(), path);
+            }
+            let mut palantir_material = Material::new(material.diffuse, texture);
+            palantir_material.set_roughness(material.roughness);
+            palantir_material.set_metallic(material.metallic);
+            palantir_material.set_opacity(material.opacity);
+            palantir_material.set_alpha_cutoff(material.alpha_cutoff);
+            palantir_material.set_blend_mode(material.blend_mode);
+            mesh.materials.push(Arc::new(palantir_material));
+        }
+        self.import_reports.push(imported.report);
+        mesh
+    }
+    /// Replaces the diffuse texture of every material of the entity's mesh.
+    pub fn set_diffuse_texture(
+        &mut self,
+        world: &mut World,
+        entity: Entity,
+        texture: Texture,
+    ) -> bool {
+        let mut mesh_component = match world.get_component_mut::<MeshComponent>(entity) {
+            Some(mesh_component) => mesh_component,
+            None => return false,
+        };
+        let mesh = &mut mesh_component.mesh;
+        if mesh.materials.is_empty() {
+            mesh.materials
+                .push(Arc::new(Material::new(Vector3::new(1.0, 1.0, 1.0), None)));
+        }
+        for submesh in &mut mesh.submeshes {
+            if submesh.material_index.is_none() {
+                submesh.material_index = Some(0);
+            }
+        }
+        for material in &mut mesh.materials {
+            Arc::make_mut(material).set_diffuse_texture(Some(texture));
+        }
+        true
+    }
+}
+
+// Materials stuff
+impl Scene {
+    /// File a texture was loaded from, unknown for the textures embedded in models.
+    pub fn texture_path(&self, texture: Texture) -> Option<&Path> {
+        self.texture_paths.get(&texture.id()).map(PathBuf::as_path)
+    }
+    pub fn set_texture_path(&mut self, texture: Texture, path: PathBuf) {
+        self.texture_paths.insert(texture.id(), path);
+    }
+    /// Reads and uploads an image file, blocking until it is done.
+    pub fn load_texture(&mut self, path: &Path) -> Result<Texture, image::ImageError> {
+        let texture = Texture::from_image(&image::open(path)?.flipv());
+        self.set_texture_path(texture, path.to_path_buf());
+        Ok(texture)
+    }
+    /// Material with the parameters and texture of a native material file.
+    pub fn load_material(&mut self, path: &Path) -> Result<Material, Box<dyn Error>> {
+        let file = MaterialFile::load(path)?;
+        let mut material = file.material();
+        let texture = match (&file.diffuse_texture, &file.alpha_texture) {
+            (Some(texture_path), None) => Some(self.load_texture(texture_path)?),
+            (texture_path, Some(alpha_path)) => {
+                let diffuse_image = match texture_path {
+                    Some(texture_path) => Some(image::open(texture_path)?),
+                    None => None,
+                };
+                let image = with_alpha(diffuse_image, &image::open(alpha_path)?);
+                let texture = Texture::from_image(&image.flipv());
+                if let Some(texture_path) = texture_path {
+                    self.set_texture_path(texture, texture_path.clone());
+                }
+                self.texture_alpha_paths
+                    .insert(texture.id(), alpha_path.clone());
+                Some(texture)
+            }
+            (None, None) => None,
+        };
+        material.set_diffuse_texture(texture);
+        Ok(material)
+    }
+    pub fn material_file(&self, material: &Material) -> MaterialFile {
+        let texture = material.diffuse_texture();
+        let texture_path = texture
+            .and_then(|texture| self.texture_path(texture))
+            .map(Path::to_path_buf);
+        MaterialFile {
+            alpha_texture: texture
+                .and_then(|texture| self.texture_alpha_paths.get(&texture.id()))
+                .cloned(),
+            ..MaterialFile::new(material, texture_path)
+        }
+    }
+    /// Full detail geometry and materials of every mesh.
+    pub fn export_meshes(&self, world: &World) -> Vec<ExportedMesh> {
+        let query = <(Read<TransformComponent>, Read<MeshComponent>)>::query();
+        query
+            .iter(world)
+            .map(|(transform, mesh)| ExportedMesh {
+                name: mesh.name.clone(),
+                matrix: transform.matrix,
+                submeshes: mesh
+                    .mesh
+                    .submeshes
+                    .iter()
+                    .map(|submesh| {
+                        let data = submesh.to_data();
+                        ExportedSubmesh {
+                            vertices: data.vertices,
+                            indices: data.indices,
+                            material_index: submesh.material_index,
+                        }
+                    })
+                    .collect(),
+                materials: mesh
+                    .mesh
+                    .materials
+                    .iter()
+                    .map(|material| self.material_file(material))
+                    .collect(),
+            })
+            .collect()
+    }
+}
 
-            mesh.materials.push(Arc::new(Material::new(
-                Vector3::<f32>::new(1.0, 1.0, 1.0),
-                texture,
-            )));
+// Mesh processing stuff
+impl Scene {
+    pub fn processing_settings_mut(&mut self) -> &mut ProcessingSettings {
+        &mut self.processing_settings
+    }
+    /// Edits the mesh of an entity.
+    pub fn process_mesh(
+        &self,
+        world: &mut World,
+        entity: Entity,
+        operation: MeshOperation,
+    ) -> Result<(), Box<dyn Error>> {
+        let matrix = match world.get_component::<TransformComponent>(entity) {
+            Some(transform) => transform.matrix,
+            None => return Err("The entity has no transform.".into()),
+        };
+        let mut mesh_component = match world.get_component_mut::<MeshComponent>(entity) {
+            Some(mesh_component) => mesh_component,
+            None => return Err("The entity has no mesh.".into()),
+        };
+        let mesh = &mut mesh_component.mesh;
+        if operation.moves_vertices() && mesh.is_deformable() {
+            return Err("Skinned and morphed meshes keep their vertices in place.".into());
         }
-        Ok(mesh)
+        if operation.changes_vertex_count() && mesh.has_morph_targets() {
+            return Err("Morphed meshes keep their vertex count.".into());
+        }
+        let settings = self.processing_settings;
+        let new_matrix = match operation {
+            MeshOperation::SmoothNormals => {
+                mesh.try_edit(|data| data.recompute_normals(PI))?;
+                return Ok(());
+            }
+            MeshOperation::FlatNormals => {
+                mesh.try_edit(|data| data.recompute_normals(0.0))?;
+                return Ok(());
+            }
+            MeshOperation::AutoSmoothNormals => {
+                let angle = settings.normal_angle.to_radians();
+                mesh.try_edit(|data| data.recompute_normals(angle))?;
+                return Ok(());
+            }
+            MeshOperation::Weld => {
+                mesh.edit(|data| {
+                    data.weld(settings.weld_distance);
+                });
+                return Ok(());
+            }
+            MeshOperation::FlipNormals => {
+                mesh.edit(MeshData::flip_normals);
+                return Ok(());
+            }
+            MeshOperation::CenterPivot => {
+                let center = mesh.bounds().center();
+                matrix * Matrix4::from_translation(center)
+            }
+            MeshOperation::ResetPivot => {
+                let translation = Matrix4::from_translation(matrix.w.truncate());
+                translation.invert().unwrap() * matrix
+            }
+            MeshOperation::ApplyTransform => Matrix4::identity(),
+        };
+        // Keeps the vertices where they are in the world.
+        let to_new_space = match new_matrix.invert() {
+            Some(inverse) => inverse * matrix,
+            None => return Err("The new transform is not invertible.".into()),
+        };
+        mesh.edit(|data| data.transform(&to_new_space));
+        drop(mesh_component);
+        if let Some(mut transform) = world.get_component_mut::<TransformComponent>(entity) {
+            transform.matrix = new_matrix;
+        }
+        Ok(())
+    }
+    /// Moves the geometry and materials of `source` into the mesh of `target` and deletes it.
+    pub fn merge_meshes(&mut self, world: &mut World, target: Entity, source: Entity) -> bool {
+        if target == source {
+            return false;
+        }
+        let matrix = match (
+            world.get_component::<TransformComponent>(target),
+            world.get_component::<TransformComponent>(source),
+        ) {
+            (Some(target), Some(source)) => match target.matrix.invert() {
+                Some(inverse) => inverse * source.matrix,
+                None => return false,
+            },
+            _ => return false,
+        };
+        let deformable = |entity| match world.get_component::<MeshComponent>(entity) {
+            Some(mesh) => mesh.mesh.is_deformable(),
+            None => true,
+        };
+        if deformable(target) || deformable(source) {
+            return false;
+        }
+        let mesh = match world.get_component_mut::<MeshComponent>(source) {
+            Some(mut source) => std::mem::replace(&mut source.mesh, Mesh::new(Vec::new())),
+            None => return false,
+        };
+        if let Some(mut target) = world.get_component_mut::<MeshComponent>(target) {
+            target.mesh.merge(mesh, &matrix);
+        }
+        if self.selection == Some(source) {
+            self.selection = Some(target);
+        }
+        world.delete(source);
+        true
+    }
+    /// Replaces an entity by one per material of its mesh, named after it, and selects the
+    /// first. Returns the new entities.
+    pub fn split_by_material(&mut self, world: &mut World, entity: Entity) -> Vec<Entity> {
+        let matrix = match world.get_component::<TransformComponent>(entity) {
+            Some(transform) => transform.matrix,
+            None => return Vec::new(),
+        };
+        let (name, mesh) = match world.get_component_mut::<MeshComponent>(entity) {
+            Some(mut component)
+                if !component.mesh.submeshes.is_empty() && !component.mesh.is_deformable() =>
+            {
+                (
+                    component.name.clone(),
+                    std::mem::replace(&mut component.mesh, Mesh::new(Vec::new())),
+                )
+            }
+            _ => return Vec::new(),
+        };
+        let components: Vec<_> = mesh
+            .split_by_material()
+            .into_iter()
+            .map(|(material_index, mesh)| {
+                let name = match material_index {
+                    Some(index) => format!("{} {}", name, index),
+                    None => format!("{} default", name),
+                };
+                (TransformComponent { matrix }, MeshComponent { name, mesh })
+            })
+            .collect();
+        let entities = world.insert((), components).to_vec();
+        world.delete(entity);
+        self.selection = entities.first().copied();
+        entities
     }
 }
@@ -0,0 +1,140 @@
+use image::codecs::hdr::HdrEncoder;
+use palantir_lib::{Framebuffer, PostProcessStack};
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScreenshotFormat {
+    Png,
+    /// Radiance HDR, in place of EXR.
+    Hdr,
+}
+
+impl ScreenshotFormat {
+    /// Why floating point captures are not EXR files, shown next to the format.
+    pub const HDR_NOTE: &'static str =
+        "EXR cannot be written yet, linear captures are saved as Radiance HDR.";
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ScreenshotFormat::Png => "png",
+            ScreenshotFormat::Hdr => "hdr",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScreenshotSettings {
+    pub directory: PathBuf,
+    pub format: ScreenshotFormat,
+    pub scale: u32,
+    pub include_overlay: bool,
+    pub transparent_background: bool,
+}
+
+impl Default for ScreenshotSettings {
+    fn default() -> Self {
+        ScreenshotSettings {
+            directory: PathBuf::from("screenshots"),
+            format: ScreenshotFormat::Png,
+            scale: 1,
+            include_overlay: false,
+            transparent_background: false,
+        }
+    }
+}
+
+/// Saves the content of the window's back buffer, imgui overlay included. It holds the
+/// tonemapped image at window size with an opaque background, so the capture is always a PNG
+/// whatever the scale, format and background of the settings.
+///
+/// Must be called after everything has been drawn and before the buffers are swapped.
+pub fn capture_window(
+    settings: &ScreenshotSettings,
+    width: u32,
+    height: u32,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let path = next_screenshot_path(&settings.directory, ScreenshotFormat::Png)?;
+    Framebuffer::read_window_pixels(width, height).save(&path)?;
+    Ok(path)
+}
+
+/// Renders the scene to an offscreen target `settings.scale` times the size of the window and
+/// saves it, without any imgui overlay.
+///
+/// `draw_scene` is called with the background alpha once the target is bound, it clears and
+/// draws the scene. PNG screenshots go through the post-process stack, HDR ones are the linear
+/// scene colors.
+pub fn capture_scene<F: FnOnce(f32)>(
+    settings: &ScreenshotSettings,
+    width: u32,
+    height: u32,
+    post_process_stack: &mut PostProcessStack,
+    draw_scene: F,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let path = next_screenshot_path(&settings.directory, settings.format)?;
+    let capture_width = width * settings.scale;
+    let capture_height = height * settings.scale;
+
+    post_process_stack.resize(capture_width, capture_height)?;
+    post_process_stack.begin();
+    let alpha = if settings.transparent_background {
+        0.0
+    } else {
+        1.0
+    };
+    draw_scene(alpha);
+
+    let result = match settings.format {
+        ScreenshotFormat::Png => save_png(&path, capture_width, capture_height, post_process_stack),
+        ScreenshotFormat::Hdr => save_hdr(&path, post_process_stack.hdr_target()),
+    };
+
+    Framebuffer::unbind();
+    post_process_stack.resize(width, height)?;
+    unsafe {
+        gl::Viewport(0, 0, width as i32, height as i32);
+    }
+    result.map(|_| path)
+}
+
+fn save_png(
+    path: &Path,
+    width: u32,
+    height: u32,
+    post_process_stack: &mut PostProcessStack,
+) -> Result<(), Box<dyn Error>> {
+    let framebuffer = Framebuffer::new(width, height)?;
+    post_process_stack.end(Some(&framebuffer));
+    framebuffer.read_pixels().save(path)?;
+    Ok(())
+}
+
+fn save_hdr(path: &Path, framebuffer: &Framebuffer) -> Result<(), Box<dyn Error>> {
+    let hdr_image = framebuffer.read_pixels_hdr();
+    let pixels: Vec<_> = hdr_image.pixels().cloned().collect();
+    let writer = BufWriter::new(File::create(path)?);
+    HdrEncoder::new(writer).encode(
+        &pixels,
+        hdr_image.width() as usize,
+        hdr_image.height() as usize,
+    )?;
+    Ok(())
+}
+
+fn next_screenshot_path(
+    directory: &Path,
+    format: ScreenshotFormat,
+) -> Result<PathBuf, Box<dyn Error>> {
+    fs::create_dir_all(directory)?;
+    let mut index = 0;
+    loop {
+        let path = directory.join(format!("screenshot_{:04}.{}", index, format.extension()));
+        if !path.exists() {
+            return Ok(path);
+        }
+        index += 1;
+    }
+}
@@ -0,0 +1,20 @@
+use crate::resources::Resources;
+use palantir_lib::{ShaderError, ShaderProgram, Shading, ShadingShaders, Texture};
+
+pub fn create_shading(resources: &Resources) -> Result<Shading, ShaderError> {
+    let load_shaders = |defines: &[&str]| -> Result<ShadingShaders, ShaderError> {
+        let load = |name: &str| {
+            ShaderProgram::from_path_with_defines(resources.resource_name_to_path(name), defines)
+        };
+        Ok(ShadingShaders {
+            wireframe: load("shaders/flat.glsl")?,
+            debug: load("shaders/shading/debug.glsl")?,
+            normals: load("shaders/shading/normals.glsl")?,
+        })
+    };
+    let shaders = load_shaders(&[])?;
+    let skinned_shaders = load_shaders(&["SKINNED"])?;
+    let uv_checker = Texture::new(resources.resource_name_to_path("textures/uv-grid.jpg"));
+    let matcap = Texture::new(resources.resource_name_to_path("textures/matcap-clay.png"));
+    Ok(Shading::new(shaders, skinned_shaders, uv_checker, matcap))
+}
@@ -0,0 +1,85 @@
+/// Scene time the animations are sampled at, played, paused or scrubbed from the GUI.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Timeline {
+    time: f32,
+    end: f32,
+    /// Shortest end, to leave room for keyframes past the last one.
+    pub length: f32,
+    pub playing: bool,
+    /// Starts over at the end instead of stopping.
+    pub looping: bool,
+    pub speed: f32,
+    changed: bool,
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Timeline::new()
+    }
+}
+
+impl Timeline {
+    pub fn new() -> Self {
+        Timeline {
+            time: 0.0,
+            end: 0.0,
+            length: 10.0,
+            playing: true,
+            looping: true,
+            speed: 1.0,
+            changed: true,
+        }
+    }
+    /// Current time, in seconds.
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+    pub fn set_time(&mut self, time: f32) {
+        self.move_to(time.clamp(0.0, self.end));
+    }
+    /// Length of the timeline, in seconds.
+    pub fn end(&self) -> f32 {
+        self.end
+    }
+    /// Ends the timeline with the animations, or at `length` if they are shorter.
+    pub fn fit(&mut self, duration: f32) {
+        self.end = duration.max(self.length).max(0.0);
+        self.move_to(self.time.min(self.end));
+    }
+    pub fn rewind(&mut self) {
+        self.move_to(0.0);
+    }
+    /// Makes the next `take_changed` return true, after the keyframes were edited.
+    pub fn refresh(&mut self) {
+        self.changed = true;
+    }
+    /// Whether the time moved since the last call. Keyframed fields are only written then, so
+    /// they can be edited while the timeline is paused.
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::replace(&mut self.changed, false)
+    }
+    pub fn advance(&mut self, delta_s: f32) {
+        if !self.playing {
+            return;
+        }
+        let mut time = self.time + delta_s * self.speed;
+        if time > self.end {
+            // Keeps playing while there is nothing to animate, for the animations loaded next.
+            if self.end <= 0.0 {
+                time = 0.0;
+            } else if self.looping {
+                time %= self.end;
+            } else {
+                time = self.end;
+                self.playing = false;
+            }
+        }
+        self.move_to(time);
+    }
+    fn move_to(&mut self, time: f32) {
+        if time != self.time {
+            self.time = time;
+            self.changed = true;
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use crate::components::{Camera, Projection};
+use crate::overlays::OverlaySettings;
+use palantir_lib::{Framebuffer, FramebufferError, ShadingMode};
+use std::f32::consts::FRAC_PI_2;
+
+/// How the window is split between viewports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layout {
+    Single,
+    /// Two panes side by side.
+    Split,
+    /// Two rows of two panes.
+    Quad,
+}
+
+impl Layout {
+    pub const ALL: [Layout; 3] = [Layout::Single, Layout::Split, Layout::Quad];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Layout::Single => "Single",
+            Layout::Split => "Split",
+            Layout::Quad => "Quad",
+        }
+    }
+
+    /// Columns and rows of panes.
+    fn grid(self) -> (usize, usize) {
+        match self {
+            Layout::Single => (1, 1),
+            Layout::Split => (2, 1),
+            Layout::Quad => (2, 2),
+        }
+    }
+
+    pub fn pane_count(self) -> usize {
+        let (columns, rows) = self.grid();
+        columns * rows
+    }
+}
+
+/// Point of view a pane starts from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum View {
+    Perspective,
+    Top,
+    Front,
+    Side,
+}
+
+impl View {
+    pub const ALL: [View; 4] = [View::Perspective, View::Top, View::Front, View::Side];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            View::Perspective => "Perspective",
+            View::Top => "Top",
+            View::Front => "Front",
+            View::Side => "Side",
+        }
+    }
+
+    /// Orients the camera, the axis aligned views are orthographic.
+    pub fn apply(self, camera: &mut Camera) {
+        let (projection, pitch, yaw) = match self {
+            View::Perspective => (Projection::Perspective, camera.pitch(), camera.yaw()),
+            View::Top => (Projection::Orthographic, FRAC_PI_2, 0.0),
+            View::Front => (Projection::Orthographic, 0.0, 0.0),
+            View::Side => (Projection::Orthographic, 0.0, FRAC_PI_2),
+        };
+        camera.set_projection(projection);
+        camera.set_orientation(pitch, yaw);
+    }
+}
+
+pub struct Viewport {
+    pub view: View,
+    pub shading_mode: ShadingMode,
+    pub overlays: OverlaySettings,
+    /// Stale for the active pane, whose camera is the scene's.
+    camera: Camera,
+    framebuffer: Option<Framebuffer>,
+    /// Left, top, width and height in points, from the top left corner of the window.
+    rect: [f32; 4],
+}
+
+impl Viewport {
+    pub fn rect(&self) -> [f32; 4] {
+        self.rect
+    }
+    /// What the pane shows, `None` until the first `Viewports::resize`.
+    pub fn framebuffer(&self) -> Option<&Framebuffer> {
+        self.framebuffer.as_ref()
+    }
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        let [left, top, width, height] = self.rect;
+        x >= left && x < left + width && y >= top && y < top + height
+    }
+}
+
+/// Panes splitting the window, each with its own camera, shading mode and overlays.
+///
+/// The active pane is the one navigated, its camera lives in the scene while it is active.
+pub struct Viewports {
+    layout: Layout,
+    panes: Vec<Viewport>,
+    active: usize,
+}
+
+impl Viewports {
+    pub fn new(camera: &Camera) -> Self {
+        let panes = View::ALL
+            .iter()
+            .map(|&view| {
+                let mut pane_camera = *camera;
+                view.apply(&mut pane_camera);
+                Viewport {
+                    view,
+                    shading_mode: ShadingMode::default(),
+                    overlays: OverlaySettings::default(),
+                    camera: pane_camera,
+                    framebuffer: None,
+                    rect: [0.0; 4],
+                }
+            })
+            .collect();
+        Viewports {
+            layout: Layout::Single,
+            panes,
+            active: 0,
+        }
+    }
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+    /// Changes the layout, `resize` must be called afterwards.
+    pub fn set_layout(&mut self, layout: Layout, scene_camera: &mut Camera) {
+        self.layout = layout;
+        if self.active >= layout.pane_count() {
+            self.activate(0, scene_camera);
+        }
+    }
+    pub fn active(&self) -> usize {
+        self.active
+    }
+    pub fn active_pane(&self) -> &Viewport {
+        &self.panes[self.active]
+    }
+    pub fn active_pane_mut(&mut self) -> &mut Viewport {
+        &mut self.panes[self.active]
+    }
+    /// Panes shown by the layout, in reading order.
+    pub fn visible(&self) -> &[Viewport] {
+        &self.panes[..self.layout.pane_count()]
+    }
+    /// Camera of a visible pane, the scene's one for the active pane.
+    pub fn camera<'a>(&'a self, index: usize, scene_camera: &'a Camera) -> &'a Camera {
+        if index == self.active {
+            scene_camera
+        } else {
+            &self.panes[index].camera
+        }
+    }
+    pub fn pane_at(&self, x: f32, y: f32) -> Option<usize> {
+        self.visible().iter().position(|pane| pane.contains(x, y))
+    }
+    /// Makes another pane the one navigated, swapping its camera with the scene's.
+    pub fn activate(&mut self, index: usize, scene_camera: &mut Camera) {
+        if index == self.active {
+            return;
+        }
+        self.panes[self.active].camera = *scene_camera;
+        *scene_camera = self.panes[index].camera;
+        self.active = index;
+    }
+    /// Splits the area below `top` between the visible panes. Sizes are in points,
+    /// `pixel_scale` converts them to framebuffer pixels on high DPI displays.
+    ///
+    /// Returns the size of the panes in pixels, they are all the same.
+    pub fn resize(
+        &mut self,
+        top: f32,
+        width: f32,
+        height: f32,
+        pixel_scale: f32,
+        scene_camera: &mut Camera,
+    ) -> Result<(u32, u32), FramebufferError> {
+        let (columns, rows) = self.layout.grid();
+        let pane_width = (width / columns as f32).floor();
+        let pane_height = ((height - top) / rows as f32).floor().max(1.0);
+        let pixel_width = ((pane_width * pixel_scale) as u32).max(1);
+        let pixel_height = ((pane_height * pixel_scale) as u32).max(1);
+
+        for (index, pane) in self.panes[..columns * rows].iter_mut().enumerate() {
+            let column = index % columns;
+            let row = index / columns;
+            pane.rect = [
+                column as f32 * pane_width,
+                top + row as f32 * pane_height,
+                pane_width,
+                pane_height,
+            ];
+            pane.camera.set_aspect_ratio(pane_width / pane_height);
+            let resized = match &pane.framebuffer {
+                Some(framebuffer) => {
+                    framebuffer.width() != pixel_width || framebuffer.height() != pixel_height
+                }
+                None => true,
+            };
+            if resized {
+                pane.framebuffer = Some(Framebuffer::new(pixel_width, pixel_height)?);
+            }
+        }
+        scene_camera.set_aspect_ratio(pane_width / pane_height);
+        Ok((pixel_width, pixel_height))
+    }
+}